@@ -1,10 +1,6 @@
-use mleml::{
-    extra::builtin::FourOpFm,
-    resource::{Mod, ModData, ResConfig},
-    types::ReadyNote,
-};
+use mleml::{extra::wav::WavSampleFormat, prelude::*};
 use serde_json::json;
-use std::{fs::OpenOptions, io::Write, path::Path};
+use std::{fs::File, path::Path};
 
 fn main() {
     let fop = FourOpFm {};
@@ -13,6 +9,7 @@ fn main() {
         decay_time: 2.0,
         pitch: Some(256.0),
         velocity: 64,
+        ..Default::default()
     });
     let conf = ResConfig::from_values(
         json!([
@@ -26,19 +23,16 @@ fn main() {
     .unwrap();
     let state: Vec<u8> = Vec::new();
     let out = fop.apply(&note, &conf, state.as_slice()).unwrap().0;
-    let synthesized: Vec<u8> = out
-        .as_sound()
-        .unwrap()
-        .data()
-        .iter()
-        .map(|x| x[0])
-        .flat_map(|x| x.to_le_bytes())
-        .collect();
 
-    let path = Path::new("example_fm.pcm");
-    let mut file = match OpenOptions::new().write(true).create(true).open(path) {
+    let path = Path::new("example_fm.wav");
+    let file = match File::create(path) {
         Ok(file) => file,
         Err(e) => panic!("couldn't open {}: {}", path.display(), e),
     };
-    file.write_all(&synthesized).unwrap();
+    //Operators are quiet, bring the peak close to full scale
+    out.as_sound()
+        .unwrap()
+        .normalize(0.9)
+        .write_wav(file, WavSampleFormat::F32)
+        .unwrap();
 }
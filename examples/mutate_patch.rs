@@ -0,0 +1,33 @@
+//! Render a grid of mutations of the piano patch to `mutation_<row>_<seed>.wav`
+//! files, where `<row>` is the index of the mutation amount.
+
+use mleml::{
+    extra::{builtin::patches, patch::mutate, wav::WavSampleFormat},
+    prelude::*,
+};
+use std::fs::File;
+
+fn main() {
+    let fm = FourOpFm();
+    let base = patches::create_piano_patch();
+    let meta = patches::param_meta();
+    let note = ModData::ReadyNote(ReadyNote {
+        len: 1.0,
+        decay_time: 0.5,
+        pitch: Some(261.63),
+        velocity: 128,
+        ..Default::default()
+    });
+
+    for (row, amount) in [0.05, 0.1, 0.2, 0.4].into_iter().enumerate() {
+        for seed in 0..4 {
+            let conf = mutate(&fm, &base, &meta, amount, seed).unwrap();
+            let out = fm.apply(&note, &conf, &[]).unwrap().0;
+            let file = File::create(format!("mutation_{row}_{seed}.wav")).unwrap();
+            out.as_sound()
+                .unwrap()
+                .write_wav(file, WavSampleFormat::F32)
+                .unwrap();
+        }
+    }
+}
@@ -1,21 +1,13 @@
 #![feature(closure_lifetime_binder)]
 
 use dasp::{
-    frame::Stereo,
-    interpolate::linear::Linear,
-    signal,
-    slice::{add_in_place, map_in_place},
-    Frame, Signal,
-};
-use mleml::{
-    extra::builtin::{SimpleMixer, SimpleMod},
-    resource::{JsonArray, Mixer, Mod, ModData, ResConfig, ResState, StringError},
-    types::{ReadyNote, Sound},
+    frame::Stereo, interpolate::linear::Linear, signal, slice::map_in_place, Frame, Signal,
 };
+use mleml::{extra::wav::WavSampleFormat, prelude::*};
 use serde_json::json;
-use std::{fs::OpenOptions, io::Write, mem::discriminant, path::Path};
+use std::{fs::File, mem::discriminant, path::Path};
 
-//Writes a file with pcm_f32le format
+//Writes a WAV file with 32-bit float samples
 fn main() {
     //Simple square generator
     let square: SimpleMod = SimpleMod::new(
@@ -31,12 +23,20 @@ fn main() {
                 .ok_or(StringError("input needs to be a ReadyNote".to_string()))?;
             match input.pitch {
                 Some(hz) => {
-                    let signal = signal::rate(48000.0).const_hz(hz.into()).square();
-                    let data = signal
-                        .take((input.len * 48000.0).ceil() as usize)
-                        .map(|x: f64| [x as f32, x as f32])
-                        .collect();
-                    Ok((ModData::Sound(Sound::new(data, 48000)), Box::new([])))
+                    let frames = (input.len * 48000.0).ceil() as usize;
+                    //Glide to the end pitch, if there is one
+                    let (start, end) = (hz as f64, input.end_pitch.unwrap_or(hz) as f64);
+                    let curve = input.glide_curve;
+                    let freq = (0..)
+                        .map(move |i| curve.frequency(start, end, i as f64 / frames.max(1) as f64));
+                    let signal = signal::rate(48000.0)
+                        .hz(signal::from_iter(freq))
+                        .square()
+                        .map(|x: f64| [x as f32, x as f32]);
+                    Ok((
+                        ModData::Sound(Sound::from_signal(signal, frames, 48000)),
+                        Box::new([]),
+                    ))
                 }
                 None => todo!(),
             }
@@ -74,12 +74,12 @@ fn main() {
                         .scale_amp(0.5)
                         .offset_amp(1.0);
                     let interp = Linear::new(0.0, 1.0);
-                    let out = s2
-                        .mul_hz(interp, s1)
-                        .take((input.len * 48000.0).ceil() as usize)
-                        .map(|x| [x as f32, x as f32])
-                        .collect();
-                    Ok((ModData::Sound(Sound::new(out, 48000)), Box::new([])))
+                    let out = s2.mul_hz(interp, s1).map(|x| [x as f32, x as f32]);
+                    let frames = (input.len * 48000.0).ceil() as usize;
+                    Ok((
+                        ModData::Sound(Sound::from_signal(out, frames, 48000)),
+                        Box::new([]),
+                    ))
                 }
                 None => todo!(),
             }
@@ -103,25 +103,25 @@ fn main() {
                 .to_owned(),
         )
         .unwrap(),
-        for<'a, 'b, 'c, 'd, 'e> |input: &'b [(bool, &'e [Stereo<f32>])],
-                                 _play: u32,
-                                 _conf: &'c ResConfig,
-                                 _state: &'d ResState|
-                                 -> Result<
+        for<'a, 'b, 'c, 'd> |input: &'b [(bool, &'a [Stereo<f32>])],
+                             play: u32,
+                             _conf: &'c ResConfig,
+                             _state: &'d ResState|
+                             -> Result<
             (Box<Sound>, Box<ResState>, Box<[Option<&'a [Stereo<f32>]>]>),
             StringError,
         > {
             if input.len() != 2 {
                 Err(StringError("mixer needs exactly two channels".to_owned()))
             } else {
-                let mut out = input[0].1.to_owned();
-                add_in_place(&mut out, input[1].1);
+                let mut out = sum_channels(input, play as usize, false);
                 map_in_place(&mut out, |x| x.mul_amp([0.5, 0.5]));
-                Ok((
-                    Sound::new(out.into(), 48000),
-                    Box::new([]),
-                    Box::new([None, None]),
-                ))
+                //Parts that did not fit are returned to be mixed next time
+                let leftovers = input
+                    .iter()
+                    .map(|(_, x)| x.get(play as usize..).filter(|x| !x.is_empty()))
+                    .collect();
+                Ok((Sound::new(out, 48000), Box::new([]), leftovers))
             }
         },
         |_| true,
@@ -131,6 +131,7 @@ fn main() {
         decay_time: 0.0,
         pitch: Some(440.0),
         velocity: 128,
+        ..Default::default()
     });
     let square_note = square.apply(&note, &JsonArray::new(), &[]).unwrap().0;
     // let square_note: Sound = todo!();
@@ -143,25 +144,26 @@ fn main() {
         .unwrap()
         .0;
     // let sines_note: Sound = todo!();
-    let premix = vec![
-        (true, square_note.as_sound().unwrap().as_ref()),
-        (true, sines_note.as_sound().unwrap().as_ref()),
-    ];
+    let premix = PremixBuilder::new()
+        .channel_count(2)
+        .new_sound(square_note.as_sound().unwrap())
+        .new_sound(sines_note.as_sound().unwrap());
+    let square_sound = square_note.as_sound().unwrap();
+    let length = square_sound
+        .len_frames()
+        .max(sines_note.as_sound().unwrap().len_frames());
     let res = mixer
-        .mix(premix.as_slice(), 9999, &JsonArray::new(), &[])
+        .mix(
+            premix.as_premixed().unwrap(),
+            length as u32,
+            &JsonArray::new(),
+            &[],
+        )
         .unwrap();
-    let synthesized: Vec<u8> = res
-        .0
-        .data()
-        .iter()
-        .flatten()
-        .flat_map(|x| x.to_le_bytes())
-        .collect();
-
-    let path = Path::new("one_sound.pcm");
-    let mut file = match OpenOptions::new().write(true).create(true).open(path) {
+    let path = Path::new("one_sound.wav");
+    let file = match File::create(path) {
         Ok(file) => file,
         Err(e) => panic!("couldn't open {}: {}", path.display(), e),
     };
-    file.write_all(&synthesized).unwrap();
+    res.0.write_wav(file, WavSampleFormat::F32).unwrap();
 }
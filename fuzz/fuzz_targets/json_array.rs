@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mleml::resource::JsonArray;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+    if let Some(arr) = JsonArray::from_value(value) {
+        assert!(arr
+            .as_slice()
+            .iter()
+            .all(|x| !(x.is_array() || x.is_object())));
+        let parsed: Value = serde_json::from_slice(&arr.as_byte_vec()).unwrap();
+        assert!(JsonArray::from_value(parsed).is_some());
+    }
+});
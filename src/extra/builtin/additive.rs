@@ -0,0 +1,238 @@
+use std::{
+    f32::consts::TAU,
+    mem::{discriminant, Discriminant},
+};
+
+use crate::{
+    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
+    types::{
+        units::{velocity_to_gain, VelocityConvention},
+        Sound,
+    },
+};
+
+use super::note_frames;
+
+/// Sampling rate of the produced sound.
+const SAMPLING_RATE: f32 = 48000.0;
+
+/// Maximum number of harmonics in the config.
+const MAX_HARMONICS: usize = 16;
+
+/// Additive synthesizer that plays chords.
+///
+/// Every note of the chord is a sum of sines at multiples of its pitch, which fades
+/// out linearly over its decay. Notes without pitch are rests. Output is divided by
+/// the number of notes and the sum of amplitudes, so that it never clips. Notes
+/// longer than 10 minutes, together with their decay, are rejected.
+///
+/// Config is amplitudes of harmonics, starting from the fundamental: 1 to 16 floats
+/// in range [0, 1], at least one of which is not zero. Has no state.
+pub struct AdditiveSynth();
+
+impl Resource for AdditiveSynth {
+    fn orig_name(&self) -> &str {
+        "Additive synthesizer"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_ADDITIVE_SYNTH"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.is_empty() || conf.len() > MAX_HARMONICS {
+            return Err(StringError(format!(
+                "wrong number of values: expected 1 to {MAX_HARMONICS}, got {}",
+                conf.len()
+            )));
+        }
+        for (i, x) in conf.iter().enumerate() {
+            if !x.as_f64().is_some_and(|x| (0.0..=1.0).contains(&x)) {
+                return Err(StringError(format!(
+                    "argument {} (amplitude of harmonic) is not float in range [0, 1]",
+                    i + 1
+                )));
+            }
+        }
+        //Output is divided by the sum of amplitudes
+        if conf.iter().all(|x| x.as_f64() == Some(0.0)) {
+            return Err(StringError(
+                "all amplitudes of harmonics are zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in additive synthesizer that plays chords"
+    }
+}
+
+impl Mod for AdditiveSynth {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let notes = input
+            .as_ready_notes()
+            .ok_or(StringError("input has to be ReadyNotes".to_string()))?;
+        let amplitudes: Vec<f32> = conf
+            .as_slice()
+            .iter()
+            .map(|x| x.as_f64().unwrap() as f32)
+            .collect();
+        let mut frames = 0;
+        for note in notes {
+            let seconds = note.len + note.decay_time;
+            if !(seconds.is_finite() && seconds >= 0.0 && note.len >= 0.0) {
+                return Err(StringError(format!(
+                    "note length of {seconds} s is not finite and nonnegative"
+                )));
+            }
+            if note.pitch.is_some_and(|x| !(x.is_finite() && x > 0.0)) {
+                return Err(StringError(format!(
+                    "pitch {} Hz is not positive",
+                    note.pitch.unwrap()
+                )));
+            }
+            frames = frames.max(note_frames(seconds as f64, SAMPLING_RATE as f64)?);
+        }
+
+        let scale = notes.len() as f32 * amplitudes.iter().sum::<f32>();
+        let mut data = vec![0.0_f32; frames];
+        for note in notes {
+            let Some(pitch) = note.pitch else {
+                continue;
+            };
+            let gain = velocity_to_gain(note.velocity, VelocityConvention::Byte) / scale;
+            let held = (note.len * SAMPLING_RATE) as usize;
+            let decay = (note.decay_time * SAMPLING_RATE) as usize;
+            for (i, out) in data.iter_mut().take(held + decay).enumerate() {
+                let envelope = match i < held {
+                    true => 1.0,
+                    false => (held + decay - i - 1) as f32 / decay as f32,
+                };
+                let phase = TAU * pitch * i as f32 / SAMPLING_RATE;
+                let x: f32 = amplitudes
+                    .iter()
+                    .enumerate()
+                    .map(|(k, amp)| amp * (phase * (k + 1) as f32).sin())
+                    .sum();
+                *out += x * gain * envelope;
+            }
+        }
+        Ok((
+            ModData::Sound(Sound::from_mono(data.into(), SAMPLING_RATE as u32)),
+            Box::new([]),
+        ))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNotes(Vec::new()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use serde_json::json;
+
+    use crate::{
+        extra::builtin::{ConvertChord, FourOpFm},
+        resource::{JsonArray, Pipeline},
+        types::{Note, ReadyNote, TickDuration},
+    };
+
+    use super::*;
+
+    #[test]
+    fn chord_goes_through_the_pipeline() {
+        let pipeline: Vec<Rc<dyn Mod>> = vec![Rc::new(ConvertChord()), Rc::new(AdditiveSynth())];
+        assert!(pipeline.is_valid().is_ok());
+
+        let chord = [0, 4, 7].map(|pitch| Note {
+            len: TickDuration::new(2),
+            pitch: Some(pitch),
+            velocity: 255,
+            ..Default::default()
+        });
+        let convert = JsonArray::from_value(json!([8.1758, 0.125, 4, 1, 0])).unwrap();
+        let (ready, _) = ConvertChord()
+            .apply(&ModData::Notes(chord.to_vec()), &convert, &[])
+            .unwrap();
+        let ready = ready.as_ready_notes().unwrap();
+        assert_eq!(ready.len(), 3);
+        assert!((ready[0].pitch.unwrap() - 261.63).abs() < 0.01);
+
+        let harmonics = JsonArray::from_value(json!([1.0, 0.5])).unwrap();
+        let (out, _) = AdditiveSynth()
+            .apply(&ModData::ReadyNotes(ready.to_vec()), &harmonics, &[])
+            .unwrap();
+        let sound = out.as_sound().unwrap();
+        assert_eq!(sound.len_frames(), 18000);
+        assert!(sound.peak() > 0.1 && sound.peak() <= 1.0);
+        assert_eq!(sound.data()[17999], [0.0, 0.0]);
+
+        let err = FourOpFm()
+            .apply(
+                &ModData::ReadyNotes(ready.to_vec()),
+                &crate::extra::builtin::patches::create_piano_patch(),
+                &[],
+            )
+            .err()
+            .unwrap();
+        assert!(err.0.contains("chords are not supported"), "{}", err.0);
+    }
+
+    #[test]
+    fn empty_chord_is_silent() {
+        let conf = JsonArray::from_value(json!([1.0])).unwrap();
+        let (out, _) = AdditiveSynth()
+            .apply(&ModData::ReadyNotes(Vec::new()), &conf, &[])
+            .unwrap();
+        assert!(out.as_sound().unwrap().is_empty());
+        for bad in [json!([]), json!([1.5]), json!(["loud"])] {
+            let conf = JsonArray::from_value(bad).unwrap();
+            assert!(AdditiveSynth().check_config(&conf).is_err());
+        }
+    }
+
+    #[test]
+    fn zero_amplitudes_are_rejected() {
+        // Would divide the output by zero, giving NaN
+        let conf = JsonArray::from_value(json!([0.0, 0])).unwrap();
+        let note = ReadyNote {
+            len: 0.01,
+            pitch: Some(440.0),
+            ..Default::default()
+        };
+        let out = AdditiveSynth().apply(&ModData::ReadyNotes(vec![note]), &conf, &[]);
+        assert!(out.is_err());
+    }
+
+    #[test]
+    fn overly_long_note_is_rejected() {
+        let conf = JsonArray::from_value(json!([1.0])).unwrap();
+        let note = ReadyNote {
+            len: 1.0e9,
+            pitch: Some(440.0),
+            ..Default::default()
+        };
+        let out = AdditiveSynth().apply(&ModData::ReadyNotes(vec![note]), &conf, &[]);
+        assert!(out.is_err());
+    }
+}
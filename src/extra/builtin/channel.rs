@@ -3,17 +3,22 @@ use std::{
     rc::Rc,
 };
 
-use serde_json::json;
-
 use crate::{
     resource::{
-        Channel, JsonArray, Mod, ModData, PipelineStateChanges, ResConfig, ResState, Resource,
-        StringError,
+        Channel, ChannelCommand, ConfigField, ConfigSchema, FieldType, Mod, ModData, Pipeline,
+        PipelineStateChanges, ResConfig, ResState, Resource, StringError,
     },
-    types::{Note, Sound},
+    types::{Note, NoteConversionParams, Sound, TickDuration},
 };
 
 /// A channel that would find and automatically configure ConvertNote
+///
+/// Channel's state holds the settings that [commands][ChannelCommand] change:
+/// octave and volume (one byte each), default length in ticks (little-endian `u32`),
+/// post-release length (one byte) and detune in cents (little-endian `i16`). Empty
+/// state means that the settings are taken from the fields of the channel, with no
+/// detune. States of older versions, where default length is one byte, are accepted
+/// too.
 pub struct SimpleChannel {
     /// Name of the channel
     pub name: String,
@@ -25,6 +30,10 @@ pub struct SimpleChannel {
     pub tick_length: f32,
 
     /// Volume of the sound in platform's units
+    ///
+    /// Before the note enters the pipeline, its velocity is scaled to
+    /// `velocity * min(volume, max_volume) / max_volume`, rounded to the nearest
+    /// integer, where `max_volume` is the last value of the channel's config.
     pub volume: u8,
 
     /// Number of octaves above C-1.
@@ -33,7 +42,7 @@ pub struct SimpleChannel {
     /// Default length for a note, in ticks.
     ///
     /// Used if note's length is None.
-    pub length: u8,
+    pub length: TickDuration,
 
     /// Duration of the sound after the note has been released, in ticks.
     pub post_release: u8,
@@ -56,7 +65,7 @@ impl SimpleChannel {
         tick_length: f32,
         volume: u8,
         octave: u8,
-        length: u8,
+        length: TickDuration,
         post_release: u8,
         mods: Vec<Rc<dyn Mod>>,
         states: Vec<Rc<ResState>>,
@@ -75,6 +84,134 @@ impl SimpleChannel {
             configs,
         }
     }
+
+    /// Replace the states of the pipeline with initial states of the mods.
+    pub fn reset_states(&mut self) {
+        self.states = self
+            .mods
+            .iter()
+            .map(|x| Rc::from(x.initial_state()))
+            .collect();
+    }
+
+    /// Replace the mod at `index` together with its config and state, keeping the
+    /// rest of the pipeline as it is.
+    ///
+    /// The new mod has to have the same input and output types as the old one. Its
+    /// state is made by `migrate` from the old mod's state, or is the new mod's
+    /// [initial state][Resource::initial_state()] if `migrate` is None.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mod does not fit into the pipeline, or if it rejects
+    /// the config or the migrated state. The channel is not changed in that case.
+    pub fn replace_mod(
+        &mut self,
+        index: usize,
+        new_mod: Rc<dyn Mod>,
+        new_config: Rc<ResConfig>,
+        migrate: Option<&StateMigration>,
+    ) -> Result<(), StringError> {
+        let name = self.name.clone();
+        let error = |what: String| StringError(format!("[{name}] {what}"));
+        if (index >= self.states.len()) || (index >= self.configs.len()) {
+            return Err(error(format!("mod {index} has no state or config")));
+        }
+        let old = self
+            .mods
+            .replace_checked(index, new_mod.clone())
+            .map_err(|e| error(format!("can not replace mod {index}: {e}")))?;
+        let state = new_mod
+            .check_config(&new_config)
+            .map_err(|e| error(format!("mod {index} config: {}", e.0)))
+            .and_then(|_| {
+                let state = match migrate {
+                    Some(migrate) => migrate(&self.states[index]),
+                    None => new_mod.initial_state(),
+                };
+                new_mod
+                    .check_state(&state)
+                    .map(|_| state)
+                    .ok_or_else(|| error(format!("mod {index} state is invalid")))
+            });
+        match state {
+            Ok(state) => {
+                self.configs[index] = new_config;
+                self.states[index] = Rc::from(state);
+                Ok(())
+            }
+            Err(e) => {
+                self.mods[index] = old;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Function that makes a state for a new mod from the state of the mod it replaces.
+pub type StateMigration = dyn Fn(&ResState) -> Box<ResState>;
+
+/// Settings that are kept in [`SimpleChannel`]'s state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Settings {
+    octave: u8,
+    volume: u8,
+    length: Option<TickDuration>,
+    post_release: u8,
+    detune: i16,
+}
+
+/// Length of non-empty state.
+const STATE_LEN: usize = 9;
+
+impl Settings {
+    fn from_bytes(state: &ResState) -> Option<Self> {
+        match state.len() {
+            STATE_LEN => Some(Settings {
+                octave: state[0],
+                volume: state[1],
+                length: TickDuration::new(u32::from_le_bytes(state[2..6].try_into().unwrap())),
+                post_release: state[6],
+                detune: i16::from_le_bytes([state[7], state[8]]),
+            }),
+            _ => None,
+        }
+    }
+
+    fn to_bytes(self) -> Box<ResState> {
+        let length = self.length.map_or(0, TickDuration::ticks).to_le_bytes();
+        let detune = self.detune.to_le_bytes();
+        Box::new([
+            self.octave,
+            self.volume,
+            length[0],
+            length[1],
+            length[2],
+            length[3],
+            self.post_release,
+            detune[0],
+            detune[1],
+        ])
+    }
+}
+
+impl Default for SimpleChannel {
+    /// Channel with an empty pipeline that plays quarter notes in the 4th octave at
+    /// full volume, with one tick being an eighth of a beat at 120 BPM.
+    fn default() -> Self {
+        SimpleChannel {
+            name: "Default channel".to_string(),
+            id: "DEFAULT_CHANNEL".to_string(),
+            tick_length: 0.125,
+            volume: 128,
+            octave: 4,
+            length: TickDuration::new(4).unwrap(),
+            post_release: 1,
+            mods: Vec::new(),
+            states: Vec::new(),
+            configs: Vec::new(),
+        }
+    }
 }
 
 impl Resource for SimpleChannel {
@@ -95,79 +232,126 @@ impl Resource for SimpleChannel {
             }
         }
 
-        let conf = conf.as_slice();
-
         to_result(conf.len() == 5, "incorrect config length".to_string())?;
-
-        to_result(
-            conf[0].is_f64(),
-            "argument 1 (frequency of C-1) is not float".to_string(),
-        )?;
-
-        to_result(
-            conf[1].is_f64(),
-            "argument 2 (Length of one tick) is not float".to_string(),
-        )?;
-
-        to_result(
-            conf[2].is_i64(),
-            "argument 3 (number of ticks in one whole note) is not integer".to_string(),
-        )?;
-
-        to_result(
-            conf[3].is_f64(),
-            "argument 4 (ticks per beat) is not float".to_string(),
-        )?;
-
-        to_result(
-            conf[4].is_i64(),
-            "argument 5 (maximum volume setting) is not integer".to_string(),
-        )?;
+        conf.try_f64(0).map_err(|e| e.named("frequency of C-1"))?;
+        conf.try_f64(1).map_err(|e| e.named("length of one tick"))?;
+        conf.try_i64(2)
+            .map_err(|e| e.named("number of ticks in one whole note"))?;
+        conf.try_f64(3).map_err(|e| e.named("ticks per beat"))?;
+        conf.try_i64(4)
+            .map_err(|e| e.named("maximum volume setting"))?;
 
         Ok(())
     }
 
-    fn check_state(&self, _state: &ResState) -> Option<()> {
-        Some(())
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        matches!(state.len(), 0 | STATE_LEN).then_some(())
+    }
+
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        Some(ConfigSchema::new(vec![
+            ConfigField::new("c_neg1_hz", FieldType::Float, "Frequency of C-1 in Hz"),
+            ConfigField::new(
+                "tick_length",
+                FieldType::Float,
+                "Length of one tick in seconds",
+            ),
+            ConfigField::new(
+                "zenlen",
+                FieldType::Integer,
+                "Number of ticks in one whole note",
+            ),
+            ConfigField::new("tempo", FieldType::Float, "Ticks per beat"),
+            ConfigField::new("max_volume", FieldType::Integer, "Maximum volume setting")
+                .with_min(1.0),
+        ]))
     }
 
     fn description(&self) -> &str {
         "A simple channel that auto-configures a builtin Note -> ResNote converter."
     }
+
+    fn initial_state(&self) -> Box<ResState> {
+        self.settings(&[]).unwrap().to_bytes()
+    }
 }
 
-impl Channel for SimpleChannel {
-    fn play(
+impl SimpleChannel {
+    /// Get the settings from channel's state.
+    fn settings(&self, state: &ResState) -> Result<Settings, StringError> {
+        match state.is_empty() {
+            true => Ok(Settings {
+                octave: self.octave,
+                volume: self.volume,
+                length: Some(self.length),
+                post_release: self.post_release,
+                detune: 0,
+            }),
+            false => Settings::from_bytes(state).ok_or(StringError(format!(
+                "state has {} bytes, expected 0 or {STATE_LEN}",
+                state.len()
+            ))),
+        }
+    }
+
+    fn scale_velocity(velocity: u8, volume: u8, max_volume: u64) -> u8 {
+        let volume = (volume as u64).min(max_volume);
+        ((velocity as u64 * volume * 2 + max_volume) / (max_volume * 2)) as u8
+    }
+
+    fn play_pipeline(
         &self,
         item: ModData,
-        _state: &ResState,
+        state: &ResState,
         config: &ResConfig,
     ) -> Result<(ModData, PipelineStateChanges, Box<ResState>), StringError> {
-        if (self.mods.len() != self.states.len()) || (self.mods.len() != self.states.len()) {
+        #[cfg(feature = "debug-checks")]
+        super::checks::pipeline_lockstep(
+            &self.name,
+            self.mods.len(),
+            self.configs.len(),
+            self.states.len(),
+        );
+        if (self.mods.len() != self.configs.len()) || (self.mods.len() != self.states.len()) {
             return Err(StringError(
                 "number of mods, configs and states is not equal".to_owned(),
             ));
         }
 
-        if !item.is_note() {
-            return Err(StringError("channel expects a Note".to_string()));
+        let settings = self.settings(state)?;
+        let mut item = item;
+        match &mut item {
+            ModData::Note(note) => {
+                note.len = note.len.or(settings.length);
+                let max_volume = config
+                    .as_slice()
+                    .get(4)
+                    .and_then(|x| x.as_u64())
+                    .filter(|x| *x > 0)
+                    .ok_or(StringError(
+                        "maximum volume is not positive integer".to_string(),
+                    ))?;
+                note.velocity = Self::scale_velocity(note.velocity, settings.volume, max_volume);
+            }
+            _ => return Err(StringError("channel expects a Note".to_string())),
         }
 
-        let mut item = item;
         let mut state_changes: Vec<Box<ResState>> = Vec::new();
 
         for i in 0..self.mods.len() {
+            #[cfg(feature = "debug-checks")]
+            super::checks::mod_state(&self.name, i, self.mods[i].as_ref(), &self.states[i]);
             if self.mods[i].id() == "BUILTIN_CONVERT_NOTE" {
                 let cccc = config.as_ref().get(0).unwrap().as_f64().unwrap();
                 let tick_len = config.as_ref().get(1).unwrap().as_f64().unwrap();
-                let conf = JsonArray::from_value(json!([
-                    cccc,
-                    tick_len,
-                    self.octave,
-                    self.post_release,
-                    0
-                ]))
-                .unwrap();
+                let conf = NoteConversionParams {
+                    c_neg1_hz: cccc,
+                    tick_length: tick_len,
+                    octave: settings.octave as u64,
+                    post_release_ticks: settings.post_release as i64,
+                    added_cents: settings.detune as i64,
+                }
+                .into_config();
                 match self.mods[i].apply(&item, &conf, &self.states[i]) {
                     Ok((new, state)) => {
                         item = new;
@@ -193,10 +377,22 @@ impl Channel for SimpleChannel {
         }
 
         match item {
-            ModData::Sound(out) => Ok((ModData::Sound(out), state_changes, Box::new([]))),
+            ModData::Sound(out) => Ok((ModData::Sound(out), state_changes, Box::from(state))),
             _ => Err(StringError("pipeline produced incorrect type".to_string())),
         }
     }
+}
+
+impl Channel for SimpleChannel {
+    fn play(
+        &self,
+        item: ModData,
+        state: &ResState,
+        config: &ResConfig,
+    ) -> Result<(ModData, PipelineStateChanges, Box<ResState>), StringError> {
+        self.play_pipeline(item, state, config)
+            .map_err(|e| StringError(format!("[{}] {}", self.name(), e.0)))
+    }
 
     fn input_type(&self) -> Discriminant<ModData> {
         discriminant(&ModData::Note(Note::default()))
@@ -205,4 +401,378 @@ impl Channel for SimpleChannel {
     fn output_type(&self) -> Discriminant<ModData> {
         discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
     }
+
+    /// Passes silence through the mods at the end of the pipeline that take a Sound,
+    /// using the states of the pipeline.
+    fn flush_tail(
+        &self,
+        _state: &ResState,
+        _config: &ResConfig,
+        max_seconds: f32,
+        sampling_rate: u32,
+    ) -> Result<Option<Box<Sound>>, StringError> {
+        super::effect_channel::flush_effects(
+            &self.mods,
+            &self.configs,
+            &self.states,
+            max_seconds,
+            sampling_rate,
+        )
+        .map_err(|e| StringError(format!("[{}] {}", self.name(), e.0)))
+    }
+
+    /// Supports every command except [`Raw`][ChannelCommand::Raw].
+    fn apply_command(
+        &self,
+        command: &ChannelCommand,
+        state: &ResState,
+    ) -> Result<Box<ResState>, StringError> {
+        let error = |msg: String| StringError(format!("[{}] {msg}", self.name()));
+        let mut settings = self.settings(state).map_err(|e| error(e.0))?;
+        match command {
+            ChannelCommand::OctaveUp => {
+                settings.octave = settings
+                    .octave
+                    .checked_add(1)
+                    .ok_or(error("octave is already the highest".to_string()))?
+            }
+            ChannelCommand::OctaveDown => {
+                settings.octave = settings
+                    .octave
+                    .checked_sub(1)
+                    .ok_or(error("octave is already the lowest".to_string()))?
+            }
+            ChannelCommand::Octave(octave) => settings.octave = *octave,
+            ChannelCommand::DefaultLength(length) => {
+                settings.length = Some(
+                    TickDuration::new(*length as u32)
+                        .ok_or(error("default length has to be positive".to_string()))?,
+                )
+            }
+            ChannelCommand::Volume(volume) => settings.volume = *volume,
+            ChannelCommand::Detune(cents) => settings.detune = *cents,
+            ChannelCommand::PostRelease(ticks) => settings.post_release = *ticks,
+            ChannelCommand::Raw(raw) => {
+                return Err(error(format!("raw command {raw:?} is unsupported")))
+            }
+        }
+        Ok(settings.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        extra::builtin::{ConvertNote, SimpleMod},
+        resource::JsonArray,
+        types::{
+            units::{velocity_to_gain, VelocityConvention},
+            ReadyNote,
+        },
+    };
+
+    use super::*;
+
+    /// Constant sound with amplitude equal to the gain of note's velocity.
+    fn velocity_meter(
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let note = input.as_ready_note().unwrap();
+        let x = velocity_to_gain(note.velocity, VelocityConvention::Byte);
+        let data = vec![[x, x]; (note.len * 48000.0) as usize].into_boxed_slice();
+        Ok((ModData::Sound(Sound::new(data, 48000)), Box::new([])))
+    }
+
+    /// Single frame with note's pitch in kHz.
+    fn pitch_meter(
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let x = input.as_ready_note().unwrap().pitch.unwrap() / 1000.0;
+        Ok((
+            ModData::Sound(Sound::new(Box::new([[x, x]]), 48000)),
+            Box::new([]),
+        ))
+    }
+
+    fn meter() -> SimpleMod {
+        SimpleMod::builder()
+            .name("Velocity meter".to_string())
+            .id("VELOCITY_METER".to_string())
+            .desc("Plays note's velocity".to_string())
+            .schema(JsonArray::new())
+            .apply(velocity_meter)
+            .check_state(|_| true)
+            .input_type(discriminant(&ModData::ReadyNote(ReadyNote::default())))
+            .output_type(discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))))
+            .build()
+            .unwrap()
+    }
+
+    fn peak_at_volume(volume: u8) -> f32 {
+        let channel = SimpleChannel {
+            volume,
+            mods: vec![Rc::new(ConvertNote()), Rc::new(meter())],
+            states: vec![Rc::from([]), Rc::from([])],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            len: TickDuration::new(4),
+            pitch: Some(9),
+            velocity: 255,
+            ..Default::default()
+        };
+        let (out, _, _) = channel.play(ModData::Note(note), &[], &config).unwrap();
+        out.as_sound().unwrap().data()[0][0]
+    }
+
+    #[test]
+    fn volume_is_folded_into_velocity() {
+        let full = peak_at_volume(15);
+        let half = peak_at_volume(8);
+        assert_eq!(full, 1.0);
+        assert!((half / full - 0.5).abs() < 0.05);
+        // Volume above the maximum is clamped
+        assert_eq!(peak_at_volume(100), full);
+    }
+
+    #[test]
+    fn errors_are_prefixed_with_channel_name() {
+        let channel = SimpleChannel {
+            name: "Lead".to_string(),
+            ..Default::default()
+        };
+        let err = channel
+            .play(ModData::String("c".to_string()), &[], &JsonArray::new())
+            .err()
+            .unwrap();
+        assert_eq!(err.0, "[Lead] channel expects a Note");
+    }
+
+    #[test]
+    fn default_channel_is_usable() {
+        let channel = SimpleChannel::default();
+        assert_eq!(channel.id(), "DEFAULT_CHANNEL");
+        assert_eq!(channel.octave, 4);
+        assert!(channel.mods.is_empty());
+    }
+
+    /// Channel that has a state for only one of its two mods.
+    fn inconsistent_channel() -> SimpleChannel {
+        SimpleChannel {
+            name: "Broken".to_string(),
+            mods: vec![Rc::new(ConvertNote()), Rc::new(meter())],
+            states: vec![Rc::from([])],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        }
+    }
+
+    fn play_note(channel: &SimpleChannel) -> Result<(), StringError> {
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            len: TickDuration::new(4),
+            ..Default::default()
+        };
+        channel.play(ModData::Note(note), &[], &config).map(|_| ())
+    }
+
+    #[cfg(not(feature = "debug-checks"))]
+    #[test]
+    fn inconsistent_pipeline_is_an_error() {
+        assert!(play_note(&inconsistent_channel()).is_err());
+    }
+
+    #[cfg(feature = "debug-checks")]
+    #[test]
+    fn inconsistent_pipeline_panics_with_details() {
+        let channel = inconsistent_channel();
+        let payload =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| play_note(&channel)))
+                .unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "[Broken] pipeline is out of lockstep: 2 mods, 2 configs, 1 states"
+        );
+    }
+
+    #[test]
+    fn channel_plays_from_initial_states() {
+        let mut channel = SimpleChannel {
+            mods: vec![Rc::new(ConvertNote()), Rc::new(meter())],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        channel.reset_states();
+        assert_eq!(channel.states.len(), 2);
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            len: TickDuration::new(4),
+            pitch: Some(9),
+            velocity: 255,
+            ..Default::default()
+        };
+        assert!(channel.play(ModData::Note(note), &[], &config).is_ok());
+    }
+
+    fn pitch_after(commands: &[ChannelCommand]) -> f32 {
+        let pitch_meter = SimpleMod::builder()
+            .name("Pitch meter".to_string())
+            .id("PITCH_METER".to_string())
+            .desc("Plays note's pitch".to_string())
+            .schema(JsonArray::new())
+            .apply(pitch_meter)
+            .check_state(|_| true)
+            .input_type(discriminant(&ModData::ReadyNote(ReadyNote::default())))
+            .output_type(discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))))
+            .build()
+            .unwrap();
+        let mut channel = SimpleChannel {
+            mods: vec![Rc::new(ConvertNote()), Rc::new(pitch_meter)],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        channel.reset_states();
+        let state = commands.iter().fold(channel.initial_state(), |state, cmd| {
+            channel.apply_command(cmd, &state).unwrap()
+        });
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            pitch: Some(10),
+            ..Default::default()
+        };
+        let (out, _, new_state) = channel.play(ModData::Note(note), &state, &config).unwrap();
+        assert_eq!(new_state, state);
+        out.as_sound().unwrap().data()[0][0]
+    }
+
+    #[test]
+    fn octave_up_raises_pitch_by_octave() {
+        let base = pitch_after(&[]);
+        let raised = pitch_after(&[ChannelCommand::OctaveUp]);
+        assert!((raised / base - 2.0).abs() < 1e-4);
+        let back = pitch_after(&[ChannelCommand::OctaveUp, ChannelCommand::OctaveDown]);
+        assert_eq!(back, base);
+        let detuned = pitch_after(&[ChannelCommand::Detune(1200)]);
+        assert!((detuned / raised - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn replaced_mod_is_played() {
+        let mut channel = SimpleChannel {
+            mods: vec![Rc::new(ConvertNote()), Rc::new(meter())],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        channel.reset_states();
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            len: TickDuration::new(4),
+            pitch: Some(9),
+            ..Default::default()
+        };
+        let play = |channel: &SimpleChannel| {
+            let (out, _, _) = channel
+                .play(ModData::Note(note.clone()), &[], &config)
+                .unwrap();
+            out.as_sound().unwrap().len_frames()
+        };
+        let before = play(&channel);
+
+        let pitch_meter = SimpleMod::builder()
+            .name("Pitch meter".to_string())
+            .id("PITCH_METER".to_string())
+            .desc("Plays note's pitch".to_string())
+            .schema(JsonArray::new())
+            .apply(pitch_meter)
+            .check_state(|x| x == [1])
+            .input_type(discriminant(&ModData::ReadyNote(ReadyNote::default())))
+            .output_type(discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))))
+            .build()
+            .unwrap();
+        let migrate: &StateMigration = &|_| Box::new([1]);
+        channel
+            .replace_mod(
+                1,
+                Rc::new(pitch_meter),
+                Rc::new(JsonArray::new()),
+                Some(migrate),
+            )
+            .unwrap();
+        assert_eq!(*channel.states[1], [1]);
+        assert_ne!(play(&channel), before);
+    }
+
+    #[test]
+    fn incompatible_replacement_is_rejected() {
+        let mut channel = SimpleChannel {
+            mods: vec![Rc::new(ConvertNote()), Rc::new(meter())],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        channel.reset_states();
+        let err = channel
+            .replace_mod(1, Rc::new(ConvertNote()), Rc::new(JsonArray::new()), None)
+            .err()
+            .unwrap();
+        assert!(err.0.contains("can not replace mod 1"), "{}", err.0);
+        assert!(channel
+            .replace_mod(2, Rc::new(meter()), Rc::new(JsonArray::new()), None)
+            .is_err());
+        // Rejected config does not leave the new mod in the pipeline
+        assert!(channel
+            .replace_mod(
+                1,
+                Rc::new(meter()),
+                Rc::new(JsonArray::from_values([json!(1)]).unwrap()),
+                None
+            )
+            .is_err());
+        assert_eq!(channel.mods[1].id(), "VELOCITY_METER");
+    }
+
+    #[test]
+    fn default_length_can_exceed_a_byte() {
+        let channel = SimpleChannel::default();
+        let state = channel
+            .apply_command(
+                &ChannelCommand::DefaultLength(300),
+                &channel.initial_state(),
+            )
+            .unwrap();
+        assert_eq!(
+            Settings::from_bytes(&state).unwrap().length,
+            TickDuration::new(300)
+        );
+        let settings = Settings::from_bytes(&state).unwrap();
+        assert_eq!(Settings::from_bytes(&settings.to_bytes()), Some(settings));
+    }
+
+    #[test]
+    fn bad_commands_are_rejected() {
+        let channel = SimpleChannel::default();
+        let state = channel.initial_state();
+        assert!(channel.check_state(&state).is_some());
+        let err = channel
+            .apply_command(&ChannelCommand::DefaultLength(0), &state)
+            .err()
+            .unwrap();
+        assert_eq!(err.0, "[Default channel] default length has to be positive");
+        assert!(channel
+            .apply_command(&ChannelCommand::Raw("@v".to_string()), &state)
+            .is_err());
+        let lowest = channel
+            .apply_command(&ChannelCommand::Octave(0), &state)
+            .unwrap();
+        assert!(channel
+            .apply_command(&ChannelCommand::OctaveDown, &lowest)
+            .is_err());
+    }
 }
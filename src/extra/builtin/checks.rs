@@ -0,0 +1,72 @@
+//! Assertions for invariants that are otherwise kept only by convention.
+//!
+//! Enabled with the `debug-checks` feature. Every check panics with a message that
+//! names the resource and the mismatching counts, so that a broken setup is found
+//! where it is made instead of somewhere down the pipeline.
+
+use std::mem::Discriminant;
+
+use crate::resource::{Mod, ModData, ResState};
+
+/// Mods, configs and states of a channel's pipeline have to be in lockstep.
+pub(crate) fn pipeline_lockstep(channel: &str, mods: usize, configs: usize, states: usize) {
+    assert!(
+        mods == configs && mods == states,
+        "[{channel}] pipeline is out of lockstep: {mods} mods, {configs} configs, {states} states"
+    );
+}
+
+/// State given to a mod in a channel's pipeline has to be accepted by the mod.
+pub(crate) fn mod_state(channel: &str, index: usize, item: &dyn Mod, state: &ResState) {
+    assert!(
+        item.check_state(state).is_some(),
+        "[{channel}] mod {} at {index} was given invalid state of {} bytes",
+        item.id(),
+        state.len()
+    );
+}
+
+/// Output of a mod has to be of the type that the mod declares.
+pub(crate) fn mod_output(id: &str, expected: Discriminant<ModData>, output: &ModData) {
+    assert!(
+        std::mem::discriminant(output) == expected,
+        "mod {id} returned data that is not of its declared output type"
+    );
+}
+
+/// Mixer has to return exactly one leftover for every channel.
+pub(crate) fn mixer_leftovers(id: &str, channels: usize, leftovers: usize) {
+    assert!(
+        channels == leftovers,
+        "mixer {id} returned {leftovers} leftovers for {channels} channels"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+
+    fn panic_message(f: impl FnOnce()) -> String {
+        let payload = catch_unwind(AssertUnwindSafe(f)).unwrap_err();
+        match payload.downcast::<String>() {
+            Ok(msg) => *msg,
+            Err(payload) => payload.downcast::<&str>().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn messages_name_the_mismatch() {
+        assert_eq!(
+            panic_message(|| pipeline_lockstep("Lead", 2, 2, 1)),
+            "[Lead] pipeline is out of lockstep: 2 mods, 2 configs, 1 states"
+        );
+        assert_eq!(
+            panic_message(|| mixer_leftovers("AGC", 3, 2)),
+            "mixer AGC returned 2 leftovers for 3 channels"
+        );
+        pipeline_lockstep("Lead", 2, 2, 2);
+        mixer_leftovers("AGC", 3, 3);
+    }
+}
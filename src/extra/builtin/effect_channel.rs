@@ -0,0 +1,397 @@
+use std::{
+    mem::{discriminant, Discriminant},
+    rc::Rc,
+};
+
+use crate::{
+    resource::{
+        Channel, Mod, ModData, PipelineStateChanges, ResConfig, ResState, Resource, StringError,
+    },
+    types::Sound,
+};
+
+/// A channel that passes a sound through a pipeline of effects.
+///
+/// Both input and output are [`Sound`], so the output of another channel can be
+/// sent through it, like through an effects bus. Every mod is applied with its own
+/// config and state, the channel itself takes an empty config and no state.
+pub struct EffectChannel {
+    /// Name of the channel
+    pub name: String,
+
+    /// ID of the channel
+    pub id: String,
+
+    /// Data pipeline, every mod has to take and return a Sound
+    pub mods: Vec<Rc<dyn Mod>>,
+
+    /// States for the pipeline
+    pub states: Vec<Rc<ResState>>,
+
+    /// Configurations for the pipeline
+    pub configs: Vec<Rc<ResConfig>>,
+}
+
+impl EffectChannel {
+    /// Create new EffectChannel.
+    pub fn new(
+        name: String,
+        id: String,
+        mods: Vec<Rc<dyn Mod>>,
+        states: Vec<Rc<ResState>>,
+        configs: Vec<Rc<ResConfig>>,
+    ) -> Self {
+        EffectChannel {
+            name,
+            id,
+            mods,
+            states,
+            configs,
+        }
+    }
+
+    /// Replace the states of the pipeline with initial states of the mods.
+    pub fn reset_states(&mut self) {
+        self.states = self
+            .mods
+            .iter()
+            .map(|x| Rc::from(x.initial_state()))
+            .collect();
+    }
+
+    fn play_pipeline(&self, item: ModData) -> Result<(ModData, PipelineStateChanges), StringError> {
+        #[cfg(feature = "debug-checks")]
+        super::checks::pipeline_lockstep(
+            &self.name,
+            self.mods.len(),
+            self.configs.len(),
+            self.states.len(),
+        );
+        if (self.mods.len() != self.configs.len()) || (self.mods.len() != self.states.len()) {
+            return Err(StringError(
+                "number of mods, configs and states is not equal".to_owned(),
+            ));
+        }
+        if !item.is_sound() {
+            return Err(StringError("channel expects a Sound".to_string()));
+        }
+
+        let mut item = item;
+        let mut state_changes: Vec<Box<ResState>> = Vec::new();
+        for i in 0..self.mods.len() {
+            #[cfg(feature = "debug-checks")]
+            super::checks::mod_state(&self.name, i, self.mods[i].as_ref(), &self.states[i]);
+            if discriminant(&item) != self.mods[i].input_type() {
+                return Err(StringError(format!(
+                    "pipeline broken at {i} (type mismath)"
+                )));
+            }
+            match self.mods[i].apply(&item, &self.configs[i], &self.states[i]) {
+                Ok((new, state)) => {
+                    item = new;
+                    state_changes.push(state);
+                }
+                Err(what) => return Err(StringError(format!("mod error at {i}: {}", what))),
+            }
+        }
+
+        match item {
+            ModData::Sound(_) => Ok((item, state_changes)),
+            _ => Err(StringError("pipeline produced incorrect type".to_string())),
+        }
+    }
+}
+
+/// Pass silence at `sampling_rate` through the mods at the end of a pipeline that
+/// take a [`Sound`], for as long as their tails last, but no longer than `max_seconds`.
+pub(crate) fn flush_effects(
+    mods: &[Rc<dyn Mod>],
+    configs: &[Rc<ResConfig>],
+    states: &[Rc<ResState>],
+    max_seconds: f32,
+    sampling_rate: u32,
+) -> Result<Option<Box<Sound>>, StringError> {
+    if (mods.len() != configs.len()) || (mods.len() != states.len()) {
+        return Err(StringError(
+            "number of mods, configs and states is not equal".to_owned(),
+        ));
+    }
+    let sound_type = discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)));
+    let start = mods
+        .iter()
+        .rposition(|x| x.input_type() != sound_type)
+        .map_or(0, |i| i + 1);
+    let tail = (start..mods.len())
+        .map(|i| mods[i].tail_seconds(&configs[i]))
+        .sum::<f32>()
+        .min(max_seconds);
+    if start == mods.len() || tail.is_nan() || tail <= 0.0 {
+        return Ok(None);
+    }
+
+    // Half a frame more, so that the length is rounded to the nearest frame
+    let half_frame = 0.5 / sampling_rate as f32;
+    let mut item = ModData::Sound(Sound::silence(tail + half_frame, sampling_rate));
+    for i in start..mods.len() {
+        item = match mods[i].apply(&item, &configs[i], &states[i]) {
+            Ok((new, _)) => new,
+            Err(what) => return Err(StringError(format!("mod error at {i}: {}", what))),
+        };
+    }
+    match item {
+        ModData::Sound(out) => Ok(Some(out)),
+        _ => Err(StringError("pipeline produced incorrect type".to_string())),
+    }
+}
+
+impl Resource for EffectChannel {
+    fn orig_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        match conf.is_empty() {
+            true => Ok(()),
+            false => Err(StringError(format!(
+                "wrong number of values: expected 0, got {}",
+                conf.len()
+            ))),
+        }
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        state.is_empty().then_some(())
+    }
+
+    fn description(&self) -> &str {
+        "A channel that applies a pipeline of effects to a sound."
+    }
+}
+
+impl Channel for EffectChannel {
+    fn play(
+        &self,
+        item: ModData,
+        _state: &ResState,
+        _config: &ResConfig,
+    ) -> Result<(ModData, PipelineStateChanges, Box<ResState>), StringError> {
+        self.play_pipeline(item)
+            .map(|(out, changes)| (out, changes, Box::new([]) as Box<ResState>))
+            .map_err(|e| StringError(format!("[{}] {}", self.name(), e.0)))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+
+    /// Passes silence through the effects, using the states of the pipeline.
+    fn flush_tail(
+        &self,
+        _state: &ResState,
+        _config: &ResConfig,
+        max_seconds: f32,
+        sampling_rate: u32,
+    ) -> Result<Option<Box<Sound>>, StringError> {
+        flush_effects(
+            &self.mods,
+            &self.configs,
+            &self.states,
+            max_seconds,
+            sampling_rate,
+        )
+        .map_err(|e| StringError(format!("[{}] {}", self.name(), e.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        extra::builtin::{ConvertNote, SimpleChannel, SimpleMod},
+        resource::JsonArray,
+        test_util::Delay,
+        types::{Note, ReadyNote, TickDuration},
+    };
+
+    use super::*;
+
+    fn pulse(
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let note = input.as_ready_note().unwrap();
+        let data = (0..(note.len * 48000.0) as usize)
+            .map(|i| match i % 100 < 25 {
+                true => [0.5, 0.5],
+                false => [-0.5, -0.5],
+            })
+            .collect();
+        Ok((ModData::Sound(Sound::new(data, 48000)), Box::new([])))
+    }
+
+    /// Echo that adds the sound delayed by 10 frames at half volume.
+    fn echo(
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let data = input.as_sound().unwrap().data();
+        let out = (0..data.len())
+            .map(|i| match i.checked_sub(10) {
+                Some(j) => [data[i][0] + data[j][0] * 0.5, data[i][1] + data[j][1] * 0.5],
+                None => data[i],
+            })
+            .collect();
+        Ok((ModData::Sound(Sound::new(out, 48000)), Box::new([])))
+    }
+
+    type ApplyFn =
+        fn(&ModData, &ResConfig, &ResState) -> Result<(ModData, Box<ResState>), StringError>;
+
+    fn simple_mod(name: &str, apply: ApplyFn, input: ModData) -> Rc<dyn Mod> {
+        Rc::new(
+            SimpleMod::builder()
+                .name(name.to_string())
+                .id(name.to_uppercase())
+                .desc(name.to_string())
+                .schema(JsonArray::new())
+                .apply(apply)
+                .check_state(|_| true)
+                .input_type(discriminant(&input))
+                .output_type(discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))))
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn echo_mod() -> Rc<dyn Mod> {
+        simple_mod("echo", echo, ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+
+    #[test]
+    fn routing_through_effects_equals_applying_them() {
+        let source = SimpleChannel {
+            mods: vec![
+                Rc::new(ConvertNote()),
+                simple_mod("pulse", pulse, ModData::ReadyNote(ReadyNote::default())),
+            ],
+            states: vec![Rc::from([]), Rc::from([])],
+            configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+            ..Default::default()
+        };
+        let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+        let note = Note {
+            len: TickDuration::new(1),
+            velocity: 255,
+            ..Default::default()
+        };
+        let (dry, _, _) = source
+            .play(ModData::Note(note.clone()), &[], &config)
+            .unwrap();
+
+        let mut bus = EffectChannel::new(
+            "Bus".to_string(),
+            "BUS".to_string(),
+            vec![echo_mod()],
+            Vec::new(),
+            vec![Rc::new(JsonArray::new())],
+        );
+        bus.reset_states();
+        let (wet, changes, _) = bus.play(dry, &[], &JsonArray::new()).unwrap();
+        assert_eq!(changes.len(), 1);
+
+        let (dry, _, _) = source.play(ModData::Note(note), &[], &config).unwrap();
+        let (direct, _) = echo_mod().apply(&dry, &JsonArray::new(), &[]).unwrap();
+        assert_eq!(wet.as_sound().unwrap(), direct.as_sound().unwrap());
+    }
+
+    #[test]
+    fn notes_are_rejected() {
+        let bus = EffectChannel::new(
+            "Bus".to_string(),
+            "BUS".to_string(),
+            vec![echo_mod()],
+            vec![Rc::from([])],
+            vec![Rc::new(JsonArray::new())],
+        );
+        let err = bus
+            .play(ModData::Note(Note::default()), &[], &JsonArray::new())
+            .err()
+            .unwrap();
+        assert_eq!(err.0, "[Bus] channel expects a Sound");
+    }
+
+    #[test]
+    fn tail_keeps_last_echoes() {
+        let mut bus = EffectChannel::new(
+            "Bus".to_string(),
+            "BUS".to_string(),
+            vec![Rc::new(Delay())],
+            Vec::new(),
+            vec![Rc::new(JsonArray::new())],
+        );
+        bus.reset_states();
+        let mut impulse = vec![[0.0, 0.0]; 20];
+        impulse[0] = [1.0, 1.0];
+        let input = ModData::Sound(Sound::new(impulse.into_boxed_slice(), 48000));
+        let (out, changes, _) = bus.play(input, &[], &JsonArray::new()).unwrap();
+        let out = out.as_sound().unwrap().data().to_vec();
+        assert_eq!((out[0][0], out[10][0]), (1.0, 0.5));
+        assert_eq!(out.len(), 20);
+
+        // Without new states there is nothing left to flush
+        let tail = bus
+            .flush_tail(&[], &JsonArray::new(), 1.0, 48000)
+            .unwrap()
+            .unwrap();
+        assert!(tail.data().iter().all(|x| x[0] == 0.0));
+
+        bus.states = changes.into_iter().map(Rc::from).collect();
+        let tail = bus
+            .flush_tail(&[], &JsonArray::new(), 1.0, 48000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tail.data().len(), 30);
+        assert_eq!((tail.data()[0][0], tail.data()[10][0]), (0.25, 0.125));
+
+        // Tail is cut to the given length
+        let tail = bus
+            .flush_tail(&[], &JsonArray::new(), 0.0001, 48000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tail.data().len(), 5);
+
+        // Tail is rendered at the requested rate
+        let tail = bus
+            .flush_tail(&[], &JsonArray::new(), 1.0, 96000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tail.sampling_rate(), 96000);
+        assert_eq!(tail.data().len(), 60);
+    }
+
+    #[test]
+    fn stateless_effects_have_no_tail() {
+        let bus = EffectChannel::new(
+            "Bus".to_string(),
+            "BUS".to_string(),
+            vec![echo_mod()],
+            vec![Rc::from([])],
+            vec![Rc::new(JsonArray::new())],
+        );
+        assert!(bus
+            .flush_tail(&[], &JsonArray::new(), 1.0, 48000)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,10 +1,9 @@
-use std::mem::discriminant;
-
 use dasp::frame::Stereo;
 
 use crate::{
     resource::{
-        JsonArray, LeftoverSound, Mixer, PremixedSound, ResConfig, ResState, Resource, StringError,
+        json_array_find_deviation, validate, LeftoverSound, Mixer, PremixedSound, ResConfig,
+        ResState, Resource, StringError, ValueConstraint,
     },
     types::Sound,
 };
@@ -15,6 +14,7 @@ pub struct SimpleMixer<'a> {
     id: String,
     desc: String,
     schema: ResConfig,
+    constraints: Vec<ValueConstraint>,
     values: ResConfig,
     mix: fn(
         &[(bool, &'a [Stereo<f32>])],
@@ -34,7 +34,7 @@ impl<'a> SimpleMixer<'a> {
         schema: ResConfig,
         values: ResConfig,
         mix: fn(
-            PremixedSound,
+            &[(bool, &'a [Stereo<f32>])],
             u32,
             &ResConfig,
             &ResState,
@@ -46,11 +46,21 @@ impl<'a> SimpleMixer<'a> {
             id,
             desc,
             schema,
+            constraints: Vec::new(),
             values,
             mix,
             check_state,
         }
     }
+
+    /// Set constraints that configs are [validated][validate] against, in addition
+    /// to the schema.
+    pub fn with_constraints(self, constraints: Vec<ValueConstraint>) -> Self {
+        SimpleMixer {
+            constraints,
+            ..self
+        }
+    }
 }
 
 impl<'a> Resource for SimpleMixer<'a> {
@@ -63,10 +73,10 @@ impl<'a> Resource for SimpleMixer<'a> {
     }
 
     fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
-        match json_array_find_deviation(&self.schema, conf) {
-            Some(i) => Err(StringError(format!("type mismatch at index {}", i))),
-            None => Ok(()),
+        if let Some(i) = json_array_find_deviation(&self.schema, conf) {
+            return Err(StringError(format!("type mismatch at index {}", i)));
         }
+        Ok(validate(conf, &self.constraints)?)
     }
 
     fn check_state(&self, state: &ResState) -> Option<()> {
@@ -90,11 +100,237 @@ impl<'a> Mixer<'a> for SimpleMixer<'a> {
         conf: &ResConfig,
         state: &ResState,
     ) -> Result<(Box<Sound>, Box<ResState>, LeftoverSound<'a>), StringError> {
-        (self.mix)(channels, play_time, conf, state)
+        let out = (self.mix)(channels, play_time, conf, state);
+        #[cfg(feature = "debug-checks")]
+        if let Ok((_, _, leftovers)) = &out {
+            super::checks::mixer_leftovers(&self.id, channels.len(), leftovers.len());
+        }
+        out
     }
 }
 
-fn json_array_find_deviation(reference: &JsonArray, given: &JsonArray) -> Option<usize> {
-    (0..given.len())
-        .find(|&i| discriminant(&reference.as_slice()[i]) != discriminant(&given.as_slice()[i]))
+/// Sum the first `frames` frames of every channel.
+///
+/// Frames are accumulated in `f64` in the order of the slice and converted to `f32`
+/// once at the end. Sums of `f32` values rarely round in `f64`, so in most cases the
+/// order of the channels does not change the result, but this is not guaranteed.
+/// [`PremixedSound`] has no channel indices, so callers that need bit-identical
+/// output regardless of the order should use [`sum_keyed_channels()`].
+/// Channels that are shorter than `frames` contribute silence.
+///
+/// If `kahan` is true, compensated (Kahan) summation is used, which keeps the error
+/// low when many channels are mixed.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::builtin::sum_channels;
+/// let first = [[0.25, 0.5], [0.25, 0.5]];
+/// let second = [[0.5, -0.5]];
+/// let sum = sum_channels(&[(true, &first), (false, &second)], 2, false);
+/// assert_eq!(*sum, [[0.75, 0.0], [0.25, 0.5]]);
+/// ```
+pub fn sum_channels(channels: PremixedSound, frames: usize, kahan: bool) -> Box<[Stereo<f32>]> {
+    sum_in_order(channels.iter().map(|(_, x)| *x), frames, kahan)
+}
+
+/// Sum the first `frames` frames of every channel, like [`sum_channels()`], in the
+/// order of the channel indices they are paired with.
+///
+/// The result does not depend on the order of the slice, so a host that reorders
+/// its channels gets the same output bits as long as the indices stay the same.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::builtin::sum_keyed_channels;
+/// let (a, b) = ([[1.0e8, 0.0]], [[1.0, 0.0]]);
+/// let ab = sum_keyed_channels(&[(0, &a), (1, &b)], 1, true);
+/// assert_eq!(ab, sum_keyed_channels(&[(1, &b), (0, &a)], 1, true));
+/// ```
+pub fn sum_keyed_channels(
+    channels: &[(usize, &[Stereo<f32>])],
+    frames: usize,
+    kahan: bool,
+) -> Box<[Stereo<f32>]> {
+    let mut sorted: Vec<_> = channels.iter().collect();
+    sorted.sort_by_key(|(index, _)| *index);
+    sum_in_order(sorted.into_iter().map(|(_, x)| *x), frames, kahan)
+}
+
+fn sum_in_order<'a>(
+    channels: impl Iterator<Item = &'a [Stereo<f32>]> + Clone,
+    frames: usize,
+    kahan: bool,
+) -> Box<[Stereo<f32>]> {
+    (0..frames)
+        .map(|i| {
+            let mut sum = [0.0_f64; 2];
+            let mut compensation = [0.0_f64; 2];
+            for channel in channels.clone() {
+                let Some(frame) = channel.get(i) else {
+                    continue;
+                };
+                for side in 0..2 {
+                    let value = frame[side] as f64;
+                    if kahan {
+                        let y = value - compensation[side];
+                        let t = sum[side] + y;
+                        compensation[side] = (t - sum[side]) - y;
+                        sum[side] = t;
+                    } else {
+                        sum[side] += value;
+                    }
+                }
+            }
+            [sum[0] as f32, sum[1] as f32]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::resource::{JsonArray, MixInput, PremixBuilder};
+
+    use super::*;
+
+    type MixFn<'a> = fn(
+        &[(bool, &'a [Stereo<f32>])],
+        u32,
+        &ResConfig,
+        &ResState,
+    ) -> Result<(Box<Sound>, Box<ResState>, LeftoverSound<'a>), StringError>;
+
+    fn mixer<'a>(name: &str, mix: MixFn<'a>) -> SimpleMixer<'a> {
+        SimpleMixer::new(
+            name.to_string(),
+            name.to_uppercase(),
+            format!("{name} mixer for tests"),
+            JsonArray::new(),
+            JsonArray::new(),
+            mix,
+            |_| true,
+        )
+    }
+
+    #[test]
+    fn sum_channels_does_not_round_intermediate_sums() {
+        // Summing these in f32 gives 0.0 or 1.0 depending on the order, in f64 the
+        // intermediate sums are exact.
+        let a = [[1.0e8, 1.0e8]];
+        let b = [[1.0, 1.0]];
+        let c = [[-1.0e8, -1.0e8]];
+        for kahan in [false, true] {
+            let abc = sum_channels(&[(true, &a), (true, &b), (true, &c)], 1, kahan);
+            let acb = sum_channels(&[(true, &a), (true, &c), (true, &b)], 1, kahan);
+            assert_eq!(abc[0][0].to_bits(), acb[0][0].to_bits());
+            assert_eq!(*abc, [[1.0, 1.0]]);
+        }
+    }
+
+    #[test]
+    fn keyed_sum_does_not_depend_on_order() {
+        // Values far apart in magnitude, so that the order of f64 additions matters
+        let channels: Vec<Vec<Stereo<f32>>> = (0..12)
+            .map(|i| {
+                (0..8)
+                    .map(|j| {
+                        let x = ((i * 7 + j * 3) % 11) as f32 - 5.0;
+                        [x * 10.0_f32.powi(i - 6), -x * 3.0_f32.powi(j - i)]
+                    })
+                    .collect()
+            })
+            .collect();
+        let keyed: Vec<(usize, &[Stereo<f32>])> = channels
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i, x.as_slice()))
+            .collect();
+        let bits =
+            |x: &[Stereo<f32>]| -> Vec<u32> { x.iter().flatten().map(|x| x.to_bits()).collect() };
+        for kahan in [false, true] {
+            let expected = sum_keyed_channels(&keyed, 8, kahan);
+            let mut permuted = keyed.clone();
+            for step in 1..keyed.len() {
+                permuted.rotate_left(step);
+                permuted.swap(0, step);
+                let sum = sum_keyed_channels(&permuted, 8, kahan);
+                assert_eq!(bits(&sum), bits(&expected));
+            }
+            let premixed: Vec<_> = keyed.iter().map(|(_, x)| (true, *x)).collect();
+            assert_eq!(bits(&sum_channels(&premixed, 8, kahan)), bits(&expected));
+        }
+    }
+
+    #[test]
+    fn mix_silent_has_requested_length() {
+        let mixer = mixer("Silence", |_, _, _, _| {
+            Err(StringError("not used".to_string()))
+        });
+        let silence = mixer.mix_silent(100);
+        assert_eq!(silence.data().len(), 100);
+        assert_eq!(silence.sampling_rate(), 48000);
+        assert!(silence.data().iter().all(|x| *x == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn check_config_rejects_extra_values() {
+        let mixer = mixer("Silence", |_, _, _, _| {
+            Err(StringError("not used".to_string()))
+        });
+        let conf = JsonArray::try_from_iter([json!(1)]).unwrap();
+        assert!(mixer.check_config(&JsonArray::new()).is_ok());
+        assert!(mixer.check_config(&conf).is_err());
+    }
+
+    #[test]
+    fn structured_mix_matches_mix() {
+        let mixer = mixer("Sum", |channels, play_time, _, _| {
+            let sum = sum_channels(channels, play_time as usize, false);
+            let leftovers = vec![None; channels.len()].into_boxed_slice();
+            Ok((Sound::new(sum, 48000), Box::new([]), leftovers))
+        });
+        let a = [[0.5, 0.25]; 3];
+        let b = [[0.25, 0.5]; 2];
+        let premixed = [(true, a.as_slice()), (false, b.as_slice())];
+        let conf = JsonArray::new();
+        let (sound, state, leftovers) = mixer.mix(&premixed, 3, &conf, &[]).unwrap();
+        let out = mixer
+            .mix_structured(MixInput::new(&premixed, 3), &conf, &[])
+            .unwrap();
+        assert_eq!(out.sound, sound);
+        assert_eq!(out.state, state);
+        assert_eq!(out.leftovers, leftovers);
+    }
+
+    #[test]
+    fn leftovers_borrow_from_input() {
+        let mixer = mixer("Cut", |channels, play_time, _, _| {
+            let sum = sum_channels(channels, play_time as usize, false);
+            let leftovers = channels
+                .iter()
+                .map(|(_, x)| x.get(play_time as usize..).filter(|x| !x.is_empty()))
+                .collect();
+            Ok((Sound::new(sum, 48000), Box::new([]), leftovers))
+        });
+        let long = Sound::new(Box::new([[0.5, 0.5]; 5]), 48000);
+        let short = Sound::new(Box::new([[0.25, 0.25]; 2]), 48000);
+        let premix = PremixBuilder::new().new_sound(&long).new_sound(&short);
+        let (sound, _, leftovers) = mixer
+            .mix(premix.as_premixed().unwrap(), 3, &JsonArray::new(), &[])
+            .unwrap();
+        assert_eq!(sound.len_frames(), 3);
+        assert_eq!(leftovers[0], Some(long.split_at_frame(3).1));
+        assert_eq!(leftovers[1], None);
+    }
+
+    #[test]
+    fn sum_channels_pads_short_channels() {
+        let a = [[0.5, 0.5]; 3];
+        let b = [[0.25, 0.25]];
+        let sum = sum_channels(&[(true, &a), (false, &b)], 4, false);
+        assert_eq!(*sum, [[0.75, 0.75], [0.5, 0.5], [0.5, 0.5], [0.0, 0.0]]);
+    }
 }
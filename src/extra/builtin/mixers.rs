@@ -0,0 +1,286 @@
+use crate::{
+    resource::{
+        JsonArray, LeftoverSound, Mixer, PremixedSound, ResConfig, ResState, Resource, StringError,
+    },
+    types::Sound,
+};
+
+use super::sum_channels;
+
+/// Gain and peak that are used when the state is empty.
+const INITIAL_STATE: AgcState = AgcState {
+    gain: 1.0,
+    peak: 0.0,
+};
+
+/// Mixer with automatic gain control.
+///
+/// Sums the channels and scales the result so that its peak approaches the target
+/// level. Gain is changed smoothly, frame by frame, and is carried over between
+/// calls to [`mix()`][Mixer::mix()] in the state, so that long renders do not pump.
+///
+/// Config is `[target peak, speed, maximum gain]`, all floats, optionally followed
+/// by a bool:
+/// - target peak is in range (0, 1]
+/// - speed is the fraction of the difference between current and desired gain that
+///   is closed every frame, in range (0, 1]
+/// - maximum gain is at least 1, and limits how much quiet sound is amplified
+/// - if the bool is true, channels are summed with compensated (Kahan) summation,
+///   see [`sum_channels()`]. It is false by default.
+///
+/// State is two little-endian `f32`: current gain and the highest peak of mixed sound
+/// seen so far.
+/// Empty state means unity gain.
+///
+/// `play_time` is the number of frames to mix, parts of channels after that are
+/// returned as leftovers.
+pub struct AgcMixer();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AgcState {
+    gain: f32,
+    peak: f32,
+}
+
+impl AgcState {
+    fn from_bytes(state: &ResState) -> Option<Self> {
+        match state.len() {
+            0 => Some(INITIAL_STATE),
+            8 => {
+                let gain = f32::from_le_bytes(state[0..4].try_into().unwrap());
+                let peak = f32::from_le_bytes(state[4..8].try_into().unwrap());
+                (gain.is_finite() && gain >= 0.0 && peak.is_finite() && peak >= 0.0)
+                    .then_some(AgcState { gain, peak })
+            }
+            _ => None,
+        }
+    }
+
+    fn to_bytes(self) -> Box<ResState> {
+        let mut out = [0; 8];
+        out[0..4].copy_from_slice(&self.gain.to_le_bytes());
+        out[4..8].copy_from_slice(&self.peak.to_le_bytes());
+        Box::new(out)
+    }
+}
+
+impl Resource for AgcMixer {
+    fn orig_name(&self) -> &str {
+        "Automatic gain control mixer"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_AGC_MIXER"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if !matches!(conf.len(), 3 | 4) {
+            return Err(StringError(format!(
+                "wrong number of values: expected 3 or 4, got {}",
+                conf.len()
+            )));
+        }
+        if conf.get(3).is_some_and(|x| !x.is_boolean()) {
+            return Err(StringError(
+                "argument 4 (compensated summation) is not bool".to_string(),
+            ));
+        }
+        let target = get_float(conf, 0, "target peak")?;
+        let speed = get_float(conf, 1, "speed")?;
+        let max_gain = get_float(conf, 2, "maximum gain")?;
+        if !(target > 0.0 && target <= 1.0) {
+            return Err(StringError(format!(
+                "target peak {target} is outside of range (0, 1]"
+            )));
+        }
+        if !(speed > 0.0 && speed <= 1.0) {
+            return Err(StringError(format!(
+                "speed {speed} is outside of range (0, 1]"
+            )));
+        }
+        if !(max_gain >= 1.0 && max_gain.is_finite()) {
+            return Err(StringError(format!(
+                "maximum gain {max_gain} is less than 1"
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        AgcState::from_bytes(state).map(|_| ())
+    }
+
+    fn initial_state(&self) -> Box<ResState> {
+        INITIAL_STATE.to_bytes()
+    }
+
+    fn description(&self) -> &str {
+        "Builtin mixer that sums channels and smoothly adjusts the gain to reach target peak."
+    }
+}
+
+impl<'a> Mixer<'a> for AgcMixer {
+    fn get_values(&self) -> ResConfig {
+        JsonArray::new()
+    }
+
+    fn mix(
+        &self,
+        channels: PremixedSound<'a>,
+        play_time: u32,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(Box<Sound>, Box<ResState>, LeftoverSound<'a>), StringError> {
+        self.check_config(conf)?;
+        let mut agc =
+            AgcState::from_bytes(state).ok_or(StringError("mixer state is invalid".to_string()))?;
+        let conf = conf.as_slice();
+        let target = conf[0].as_f64().unwrap() as f32;
+        let speed = conf[1].as_f64().unwrap() as f32;
+        let max_gain = conf[2].as_f64().unwrap() as f32;
+        let kahan = conf.get(3).is_some_and(|x| x.as_bool().unwrap());
+
+        let frames = play_time as usize;
+        let mut mixed = sum_channels(channels, frames, kahan);
+
+        let block_peak = mixed
+            .iter()
+            .flatten()
+            .fold(0.0_f32, |acc, x| acc.max(x.abs()));
+        agc.peak = agc.peak.max(block_peak);
+        let desired = match agc.peak > 0.0 {
+            true => (target / agc.peak).min(max_gain),
+            false => agc.gain,
+        };
+        for frame in mixed.iter_mut() {
+            agc.gain += (desired - agc.gain) * speed;
+            frame[0] *= agc.gain;
+            frame[1] *= agc.gain;
+        }
+
+        let leftovers: LeftoverSound<'a> = channels
+            .iter()
+            .map(|(_, data)| (data.len() > frames).then(|| &data[frames..]))
+            .collect();
+        #[cfg(feature = "debug-checks")]
+        super::checks::mixer_leftovers(self.id(), channels.len(), leftovers.len());
+
+        Ok((
+            Sound::new(mixed, self.sampling_rate()),
+            agc.to_bytes(),
+            leftovers,
+        ))
+    }
+}
+
+fn get_float(conf: &[serde_json::Value], index: usize, name: &str) -> Result<f64, StringError> {
+    conf[index].as_f64().ok_or(StringError(format!(
+        "argument {} ({name}) is not float",
+        index + 1
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::resource::MixInput;
+
+    use super::*;
+
+    fn config() -> ResConfig {
+        JsonArray::from_values([json!(0.25), json!(0.002), json!(16.0)]).unwrap()
+    }
+
+    #[test]
+    fn gain_converges_across_calls() {
+        let mixer = AgcMixer();
+        let conf = config();
+        let channel = vec![[0.5_f32, -0.5_f32]; 1000];
+        let premixed = [(true, channel.as_slice())];
+
+        let mut state: Box<ResState> = Box::new([]);
+        let mut distances = Vec::new();
+        for _ in 0..3 {
+            let (sound, new_state, leftovers) = mixer.mix(&premixed, 1000, &conf, &state).unwrap();
+            assert_eq!(sound.data().len(), 1000);
+            assert!(leftovers.iter().all(|x| x.is_none()));
+            assert!(mixer.check_state(&new_state).is_some());
+            let gain = AgcState::from_bytes(&new_state).unwrap().gain;
+            distances.push((gain - 0.5).abs());
+            state = new_state;
+        }
+        // Gain approaches 0.25 / 0.5 with every call
+        assert!(distances[0] > distances[1]);
+        assert!(distances[1] > distances[2]);
+        assert!(distances[2] < 0.01);
+    }
+
+    #[test]
+    fn longer_channels_are_left_over() {
+        let mixer = AgcMixer();
+        let long = vec![[0.1_f32, 0.1_f32]; 10];
+        let short = vec![[0.1_f32, 0.1_f32]; 4];
+        let premixed = [(true, long.as_slice()), (true, short.as_slice())];
+        let (_, _, leftovers) = mixer.mix(&premixed, 6, &config(), &[]).unwrap();
+        assert_eq!(leftovers[0].unwrap().len(), 4);
+        assert!(leftovers[1].is_none());
+    }
+
+    #[test]
+    fn structured_mix_matches_mix() {
+        let mixer = AgcMixer();
+        let long = vec![[0.3_f32, -0.2_f32]; 10];
+        let short = vec![[0.1_f32, 0.4_f32]; 4];
+        let premixed = [(true, long.as_slice()), (false, short.as_slice())];
+        let state = mixer.initial_state();
+        let (sound, new_state, leftovers) = mixer.mix(&premixed, 6, &config(), &state).unwrap();
+        let out = mixer
+            .mix_structured(MixInput::new(&premixed, 6), &config(), &state)
+            .unwrap();
+        assert_eq!(out.sound, sound);
+        assert_eq!(out.state, new_state);
+        assert_eq!(out.leftovers, leftovers);
+    }
+
+    #[test]
+    fn compensated_summation_is_configurable() {
+        let mixer = AgcMixer();
+        let mut conf = config();
+        // Ones that are lost when added to the large value without compensation
+        let (large, one, small) = ([[1.0e17_f32, 0.0]], [[1.0_f32, 0.0]], [[-1.0e17_f32, 0.0]]);
+        let premixed: Vec<_> = std::iter::once(large.as_slice())
+            .chain(std::iter::repeat_n(one.as_slice(), 16))
+            .chain(std::iter::once(small.as_slice()))
+            .map(|x| (true, x))
+            .collect();
+        let plain = mixer.mix(&premixed, 1, &conf, &[]).unwrap().0;
+        assert_eq!(plain.data()[0][0], 0.0);
+        conf.push(json!(true)).unwrap();
+        assert!(mixer.check_config(&conf).is_ok());
+        let kahan = mixer.mix(&premixed, 1, &conf, &[]).unwrap().0;
+        assert!(kahan.data()[0][0] > 0.0);
+        conf.pop();
+        conf.push(json!(1)).unwrap();
+        assert!(mixer.check_config(&conf).is_err());
+    }
+
+    #[test]
+    fn initial_state_is_valid() {
+        let mixer = AgcMixer();
+        let state = mixer.initial_state();
+        assert!(mixer.check_state(&state).is_some());
+        assert_eq!(AgcState::from_bytes(&state), Some(INITIAL_STATE));
+    }
+
+    #[test]
+    fn bad_state_is_rejected() {
+        let mixer = AgcMixer();
+        assert!(mixer.check_state(&[]).is_some());
+        assert!(mixer.check_state(&[0; 3]).is_none());
+        let mut negative = [0; 8];
+        negative[0..4].copy_from_slice(&(-1.0_f32).to_le_bytes());
+        assert!(mixer.check_state(&negative).is_none());
+    }
+}
@@ -1,13 +1,47 @@
 //! A collection of implementations of mods, channels, and mixers.
 
+mod additive;
 mod channel;
+#[cfg(feature = "debug-checks")]
+mod checks;
+mod effect_channel;
 mod mixer_template;
+mod mixers;
 mod mod_template;
+mod noise;
+pub mod patches;
+mod sampler;
 mod synth;
 mod utility_mods;
 
-pub use channel::SimpleChannel;
-pub use mixer_template::SimpleMixer;
-pub use mod_template::SimpleMod;
+pub use additive::AdditiveSynth;
+pub use channel::{SimpleChannel, StateMigration};
+pub use effect_channel::EffectChannel;
+pub use mixer_template::{sum_channels, sum_keyed_channels, SimpleMixer};
+pub use mixers::AgcMixer;
+pub use mod_template::{SimpleMod, SimpleModBuilder};
+pub use noise::Noise;
+pub use sampler::SamplePlayer;
 pub use synth::FourOpFm;
-pub use utility_mods::ConvertNote;
+pub use utility_mods::{
+    AutoVelocity, ChannelVolume, ConvertChord, ConvertNote, ConvertNoteParams, FrequencyParser,
+    KeySignatureMod, Portamento, Quantize, ReleaseShaper,
+};
+
+use crate::resource::StringError;
+
+/// Maximum number of frames that a mod renders for one note (10 minutes at 48000 Hz).
+const MAX_FRAMES: f64 = 10.0 * 60.0 * 48000.0;
+
+/// Get the number of frames in `seconds` of sound, rejecting sounds that are longer
+/// than [`MAX_FRAMES`].
+fn note_frames(seconds: f64, sampling_rate: f64) -> Result<usize, StringError> {
+    let frames = seconds * sampling_rate;
+    if frames > MAX_FRAMES {
+        return Err(StringError(format!(
+            "note with decay lasts {seconds} s, which is longer than the maximum of {} s",
+            MAX_FRAMES / sampling_rate
+        )));
+    }
+    Ok(frames as usize)
+}
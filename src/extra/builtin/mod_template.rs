@@ -1,6 +1,12 @@
 use std::mem::{discriminant, Discriminant};
 
-use crate::resource::{JsonArray, Mod, ModData, ResConfig, ResState, Resource, StringError};
+use crate::resource::{
+    json_array_find_deviation, validate, Mod, ModData, ResConfig, ResState, Resource, StringError,
+    ValueConstraint,
+};
+
+/// Function that [`SimpleMod`] calls to apply itself.
+type ApplyFn = fn(&ModData, &ResConfig, &ResState) -> Result<(ModData, Box<ResState>), StringError>;
 
 /// A mod template that is easy to create and use.
 pub struct SimpleMod {
@@ -8,11 +14,8 @@ pub struct SimpleMod {
     id: String,
     desc: String,
     schema: ResConfig,
-    apply: fn(
-        input: &ModData,
-        conf: &ResConfig,
-        state: &ResState,
-    ) -> Result<(ModData, Box<ResState>), StringError>,
+    constraints: Vec<ValueConstraint>,
+    apply: ApplyFn,
     check_state: fn(&ResState) -> bool,
     input_type: Discriminant<ModData>,
     output_type: Discriminant<ModData>,
@@ -25,7 +28,7 @@ impl SimpleMod {
         id: String,
         desc: String,
         schema: ResConfig,
-        apply: fn(&ModData, &ResConfig, &ResState) -> Result<(ModData, Box<ResState>), StringError>,
+        apply: ApplyFn,
         check_state: fn(&ResState) -> bool,
         input_type: Discriminant<ModData>,
         output_type: Discriminant<ModData>,
@@ -35,12 +38,148 @@ impl SimpleMod {
             id,
             desc,
             schema,
+            constraints: Vec::new(),
             apply,
             check_state,
             input_type,
             output_type,
         }
     }
+
+    /// Set constraints that configs are [validated][validate] against, in addition
+    /// to the schema.
+    pub fn with_constraints(self, constraints: Vec<ValueConstraint>) -> Self {
+        SimpleMod {
+            constraints,
+            ..self
+        }
+    }
+
+    /// Create a [`SimpleModBuilder`] to construct the mod field by field.
+    pub fn builder() -> SimpleModBuilder {
+        SimpleModBuilder::default()
+    }
+}
+
+/// Builder for [`SimpleMod`].
+///
+/// All fields besides [constraints][SimpleModBuilder::constraints()] have to be set
+/// before calling [`build()`][SimpleModBuilder::build()].
+///
+/// # Examples
+///
+/// ```
+/// # use std::mem::discriminant;
+/// # use mleml::extra::builtin::SimpleMod;
+/// # use mleml::resource::{JsonArray, ModData, StringError};
+/// # use mleml::types::Note;
+/// let note = discriminant(&ModData::Note(Note::default()));
+/// let identity: SimpleMod = SimpleMod::builder()
+///     .name("Identity".to_string())
+///     .id("IDENTITY".to_string())
+///     .desc("Returns the note unchanged".to_string())
+///     .schema(JsonArray::new())
+///     .apply(|input, _conf, _state| match input {
+///         ModData::Note(note) => Ok((ModData::Note(note.clone()), Box::new([]))),
+///         _ => Err(StringError("input has to be a Note".to_string())),
+///     })
+///     .check_state(|_| true)
+///     .input_type(note)
+///     .output_type(note)
+///     .build()
+///     .expect("not all fields were set");
+/// ```
+#[derive(Default)]
+pub struct SimpleModBuilder {
+    name: Option<String>,
+    id: Option<String>,
+    desc: Option<String>,
+    schema: Option<ResConfig>,
+    constraints: Vec<ValueConstraint>,
+    apply: Option<ApplyFn>,
+    check_state: Option<fn(&ResState) -> bool>,
+    input_type: Option<Discriminant<ModData>>,
+    output_type: Option<Discriminant<ModData>>,
+}
+
+impl SimpleModBuilder {
+    /// Set mod's name.
+    pub fn name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Set mod's ID.
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Set mod's description.
+    pub fn desc(mut self, desc: String) -> Self {
+        self.desc = Some(desc);
+        self
+    }
+
+    /// Set schema that configs are checked against.
+    pub fn schema(mut self, schema: ResConfig) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Set constraints that configs are [validated][validate] against, in addition
+    /// to the schema. By default there are none.
+    pub fn constraints(mut self, constraints: Vec<ValueConstraint>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Set function that is called when the mod is applied.
+    pub fn apply(mut self, apply: ApplyFn) -> Self {
+        self.apply = Some(apply);
+        self
+    }
+
+    /// Set function that checks mod's state.
+    pub fn check_state(mut self, check_state: fn(&ResState) -> bool) -> Self {
+        self.check_state = Some(check_state);
+        self
+    }
+
+    /// Set type that the mod accepts.
+    pub fn input_type(mut self, input_type: Discriminant<ModData>) -> Self {
+        self.input_type = Some(input_type);
+        self
+    }
+
+    /// Set type that the mod produces.
+    pub fn output_type(mut self, output_type: Discriminant<ModData>) -> Self {
+        self.output_type = Some(output_type);
+        self
+    }
+
+    /// Create the mod.
+    ///
+    /// # Errors
+    ///
+    /// If any of the fields was not set, an error naming the field is returned.
+    pub fn build(self) -> Result<SimpleMod, StringError> {
+        fn require<T>(field: Option<T>, name: &str) -> Result<T, StringError> {
+            field.ok_or_else(|| StringError(format!("field `{name}` is not set")))
+        }
+
+        Ok(SimpleMod {
+            name: require(self.name, "name")?,
+            id: require(self.id, "id")?,
+            desc: require(self.desc, "desc")?,
+            schema: require(self.schema, "schema")?,
+            constraints: self.constraints,
+            apply: require(self.apply, "apply")?,
+            check_state: require(self.check_state, "check_state")?,
+            input_type: require(self.input_type, "input_type")?,
+            output_type: require(self.output_type, "output_type")?,
+        })
+    }
 }
 
 impl Resource for SimpleMod {
@@ -53,10 +192,17 @@ impl Resource for SimpleMod {
     }
 
     fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
-        match json_array_find_deviation(&self.schema, conf) {
-            Some(i) => Err(StringError(format!("type mismatch at index {}", i))),
-            None => Ok(()),
+        if conf.len() != self.schema.len() {
+            return Err(StringError(format!(
+                "wrong number of values: expected {}, got {}",
+                self.schema.len(),
+                conf.len()
+            )));
+        }
+        if let Some(i) = json_array_find_deviation(&self.schema, conf) {
+            return Err(StringError(format!("type mismatch at index {}", i)));
         }
+        Ok(validate(conf, &self.constraints)?)
     }
 
     fn check_state(&self, state: &ResState) -> Option<()> {
@@ -78,7 +224,12 @@ impl Mod for SimpleMod {
         if discriminant(input) != self.input_type {
             Err(StringError("incorrect input type".to_string()))
         } else {
-            (self.apply)(input, conf, state)
+            let out = (self.apply)(input, conf, state);
+            #[cfg(feature = "debug-checks")]
+            if let Ok((data, _)) = &out {
+                super::checks::mod_output(&self.id, self.output_type, data);
+            }
+            out
         }
     }
 
@@ -91,7 +242,72 @@ impl Mod for SimpleMod {
     }
 }
 
-fn json_array_find_deviation(reference: &JsonArray, given: &JsonArray) -> Option<usize> {
-    (0..given.len())
-        .find(|&i| discriminant(&reference.as_slice()[i]) != discriminant(&given.as_slice()[i]))
+#[cfg(test)]
+mod tests {
+    use crate::{resource::JsonArray, types::Note};
+
+    use super::*;
+
+    fn identity(
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        match input {
+            ModData::Note(note) => Ok((ModData::Note(note.clone()), Box::new([]))),
+            _ => Err(StringError("input has to be a Note".to_string())),
+        }
+    }
+
+    fn full_builder() -> SimpleModBuilder {
+        let note = discriminant(&ModData::Note(Note::default()));
+        SimpleMod::builder()
+            .name("Identity".to_string())
+            .id("IDENTITY".to_string())
+            .desc("Returns the note unchanged".to_string())
+            .schema(JsonArray::new())
+            .apply(identity)
+            .check_state(|_| true)
+            .input_type(note)
+            .output_type(note)
+    }
+
+    #[test]
+    fn builder_builds_working_mod() {
+        let identity = full_builder().build().unwrap();
+        assert_eq!(identity.id(), "IDENTITY");
+        let (out, _) = identity
+            .apply(&ModData::Note(Note::default()), &JsonArray::new(), &[])
+            .unwrap();
+        assert!(out.is_note());
+    }
+
+    #[test]
+    fn builder_reports_missing_field() {
+        let mut builder = full_builder();
+        builder.check_state = None;
+        let err = builder.build().err().unwrap();
+        assert_eq!(err.0, "field `check_state` is not set");
+    }
+
+    #[test]
+    fn config_of_wrong_length_is_rejected() {
+        let identity = full_builder().build().unwrap();
+        let long = JsonArray::from_values([serde_json::json!(1)]).unwrap();
+        let err = identity.check_config(&long).err().unwrap();
+        assert_eq!(err.0, "wrong number of values: expected 0, got 1");
+    }
+
+    #[test]
+    fn constraints_are_checked() {
+        let identity = full_builder()
+            .schema(JsonArray::from_values([serde_json::json!(0)]).unwrap())
+            .constraints(vec![ValueConstraint::IntRange { min: 0, max: 7 }])
+            .build()
+            .unwrap();
+        let conf = |x: i64| JsonArray::from_values([serde_json::json!(x)]).unwrap();
+        assert!(identity.check_config(&conf(7)).is_ok());
+        let err = identity.check_config(&conf(99)).err().unwrap();
+        assert_eq!(err.0, "argument 1 is outside of its range");
+    }
 }
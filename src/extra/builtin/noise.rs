@@ -0,0 +1,165 @@
+use std::mem::{discriminant, Discriminant};
+
+use crate::{
+    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
+    types::{ReadyNote, Sound},
+};
+
+use super::note_frames;
+
+/// Sampling rate of the produced sound.
+const SAMPLING_RATE: f32 = 48000.0;
+
+/// Amplitude of the produced noise.
+const AMPLITUDE: f32 = 0.5;
+
+/// Noise generator, modeled after 15-bit LFSR noise channels of sound chips.
+///
+/// Plays only percussive notes, non-percussive notes are treated as rests.
+/// If the note has a pitch, the LFSR is clocked at that frequency, otherwise it is
+/// clocked at the sampling rate, producing white noise.
+///
+/// Notes longer than 10 minutes, together with their decay, are rejected.
+///
+/// Takes no config and has no state. Output is deterministic.
+pub struct Noise();
+
+impl Resource for Noise {
+    fn orig_name(&self) -> &str {
+        "Noise generator"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_NOISE"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        match conf.is_empty() {
+            true => Ok(()),
+            false => Err(StringError(format!(
+                "wrong number of values: expected 0, got {}",
+                conf.len()
+            ))),
+        }
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in noise generator for percussive notes"
+    }
+}
+
+impl Mod for Noise {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_ready_note()
+            .ok_or(StringError("input has to be a ReadyNote".to_string()))?;
+        let seconds = input.len + input.decay_time;
+        if !seconds.is_finite() || (seconds < 0.0) {
+            return Err(StringError(format!(
+                "note length of {seconds} s is not finite and nonnegative"
+            )));
+        }
+        let frames = note_frames(seconds as f64, SAMPLING_RATE as f64)?;
+        if !input.percussive {
+            let silence = Sound::silence(seconds, SAMPLING_RATE as u32);
+            return Ok((ModData::Sound(silence), Box::new([])));
+        }
+
+        //How many times the LFSR is clocked per frame.
+        let step = match input.pitch {
+            Some(hz) if hz > 0.0 => hz / SAMPLING_RATE,
+            Some(hz) => return Err(StringError(format!("pitch {hz} Hz is not positive"))),
+            None => 1.0,
+        };
+        let mut lfsr: u16 = 1;
+        let mut phase = 0.0_f32;
+        let data = (0..frames)
+            .map(|_| {
+                phase += step;
+                while phase >= 1.0 {
+                    let bit = (lfsr ^ (lfsr >> 1)) & 1;
+                    lfsr = (lfsr >> 1) | (bit << 14);
+                    phase -= 1.0;
+                }
+                let x = match lfsr & 1 {
+                    0 => -AMPLITUDE,
+                    _ => AMPLITUDE,
+                };
+                [x, x]
+            })
+            .collect();
+        Ok((
+            ModData::Sound(Sound::new(data, SAMPLING_RATE as u32)),
+            Box::new([]),
+        ))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resource::JsonArray;
+
+    use super::*;
+
+    fn play(note: ReadyNote) -> Box<Sound> {
+        let (out, _) = Noise()
+            .apply(&ModData::ReadyNote(note), &JsonArray::new(), &[])
+            .unwrap();
+        match out {
+            ModData::Sound(sound) => sound,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn percussive_note_without_pitch_is_played() {
+        let sound = play(ReadyNote {
+            len: 0.1,
+            percussive: true,
+            ..Default::default()
+        });
+        assert_eq!(sound.data().len(), 4800);
+        assert!(sound.data().iter().any(|x| x[0] > 0.0));
+        assert!(sound.data().iter().any(|x| x[0] < 0.0));
+    }
+
+    #[test]
+    fn melodic_note_is_a_rest() {
+        let sound = play(ReadyNote {
+            len: 0.1,
+            pitch: Some(440.0),
+            ..Default::default()
+        });
+        assert!(sound.data().iter().all(|x| *x == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn overly_long_note_is_rejected() {
+        let note = ReadyNote {
+            len: 1.0e9,
+            percussive: true,
+            ..Default::default()
+        };
+        let out = Noise().apply(&ModData::ReadyNote(note), &JsonArray::new(), &[]);
+        assert!(out.is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Preset configs for [`FourOpFm`][super::FourOpFm], loosely modeled after classic
+//! OPN2 instruments.
+//!
+//! They are usable as they are, and also serve as a reference for the layout of
+//! the config: `[algorithm, sawtooth first operator]`, followed by
+//! `[attack, decay, sustain, release, sustain level, total level, multiplier, detune]`
+//! for each of the four operators.
+//!
+//! # Examples
+//!
+//! ```
+//! # use mleml::extra::builtin::{patches, FourOpFm};
+//! # use mleml::resource::Resource;
+//! let piano = patches::create_piano_patch();
+//! assert!(FourOpFm().check_config(&piano).is_ok());
+//! ```
+
+use serde_json::json;
+
+use crate::resource::ResConfig;
+
+/// Parameters of one operator, in the order they appear in the config.
+type Operator = [i64; 8];
+
+fn patch(algorithm: i64, saw: bool, operators: [Operator; 4]) -> ResConfig {
+    let mut values = vec![json!(algorithm), json!(saw)];
+    values.extend(operators.iter().flatten().map(|x| json!(x)));
+    ResConfig::from_values(values).unwrap()
+}
+
+/// Ranges of the values of [`FourOpFm`][super::FourOpFm]'s config, for use with
+/// [`mutate()`][crate::extra::patch::mutate()], taken from its config schema.
+///
+/// Algorithm and the sawtooth flag are fixed, so that mutations stay recognizable.
+#[cfg(feature = "extra")]
+pub fn param_meta() -> Vec<crate::extra::patch::ParamMeta> {
+    use crate::{
+        extra::patch::ParamMeta,
+        resource::{FieldType, Resource},
+    };
+
+    let schema = super::FourOpFm()
+        .config_schema()
+        .expect("FourOpFm describes its config");
+    schema
+        .fields()
+        .iter()
+        .map(|field| match (field.kind, field.min, field.max) {
+            _ if field.name == "alg" => ParamMeta::Fixed,
+            (FieldType::Integer, Some(min), Some(max)) => ParamMeta::Int {
+                min: min as i64,
+                max: max as i64,
+            },
+            (FieldType::Float, Some(min), Some(max)) => ParamMeta::Float { min, max },
+            _ => ParamMeta::Fixed,
+        })
+        .collect()
+}
+
+/// Bright brass: a chain of operators with a sawtooth at the start and a soft attack.
+pub fn create_brass_patch() -> ResConfig {
+    patch(
+        0,
+        true,
+        [
+            [192, 224, 400, 160, 100, 40, 1, 0],
+            [192, 224, 400, 160, 100, 30, 1, 3],
+            [176, 240, 400, 160, 110, 50, 1, -3],
+            [200, 240, 400, 176, 110, 127, 1, 0],
+        ],
+    )
+}
+
+/// Electric piano: two modulated carriers that decay while the key is held.
+pub fn create_piano_patch() -> ResConfig {
+    patch(
+        4,
+        false,
+        [
+            [64, 240, 288, 208, 30, 60, 14, 0],
+            [64, 272, 304, 208, 40, 127, 1, 0],
+            [64, 224, 272, 192, 20, 40, 1, 7],
+            [64, 272, 304, 208, 40, 110, 1, -7],
+        ],
+    )
+}
+
+/// String ensemble: slow attack, long sustain and slightly detuned voices.
+pub fn create_strings_patch() -> ResConfig {
+    patch(
+        6,
+        true,
+        [
+            [240, 256, 400, 240, 110, 20, 1, 0],
+            [240, 256, 400, 240, 120, 100, 1, 5],
+            [248, 256, 400, 240, 120, 100, 1, -5],
+            [256, 256, 400, 256, 120, 90, 2, 0],
+        ],
+    )
+}
+
+/// Synth bass: deep carrier with a plucky modulator.
+pub fn create_bass_patch() -> ResConfig {
+    patch(
+        0,
+        false,
+        [
+            [32, 192, 240, 144, 20, 50, 1, 0],
+            [32, 208, 256, 144, 30, 40, 2, 0],
+            [32, 224, 288, 160, 60, 30, 1, 0],
+            [32, 256, 400, 160, 100, 127, 0, 0],
+        ],
+    )
+}
+
+/// Drawbar organ: four unmodulated sines at harmonic multiples, held at full level.
+pub fn create_organ_patch() -> ResConfig {
+    patch(
+        7,
+        false,
+        [
+            [48, 0, 400, 128, 127, 127, 1, 0],
+            [48, 0, 400, 128, 127, 100, 2, 0],
+            [48, 0, 400, 128, 127, 80, 4, 0],
+            [48, 0, 400, 128, 127, 60, 8, 0],
+        ],
+    )
+}
+
+/// Bell: inharmonic modulators and a long release.
+pub fn create_bell_patch() -> ResConfig {
+    patch(
+        1,
+        false,
+        [
+            [16, 288, 320, 288, 20, 50, 7, 0],
+            [16, 272, 320, 288, 20, 30, 3, 11],
+            [16, 256, 320, 288, 30, 40, 1, 0],
+            [16, 304, 352, 304, 40, 127, 2, 0],
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        extra::builtin::FourOpFm,
+        resource::{Mod, ModData, Resource},
+        types::ReadyNote,
+    };
+
+    use super::*;
+
+    #[test]
+    fn patches_are_valid_and_audible() {
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.25,
+            decay_time: 0.05,
+            pitch: Some(220.0),
+            velocity: 128,
+            ..Default::default()
+        });
+        for conf in [
+            create_brass_patch(),
+            create_piano_patch(),
+            create_strings_patch(),
+            create_bass_patch(),
+            create_organ_patch(),
+            create_bell_patch(),
+        ] {
+            assert_eq!(conf.len(), 34);
+            FourOpFm().check_config(&conf).unwrap();
+            let (out, _) = FourOpFm().apply(&note, &conf, &[]).unwrap();
+            let sound = out.as_sound().unwrap();
+            assert_eq!(sound.data().len(), 14400);
+            assert!(sound.data().iter().any(|x| x[0] != 0.0));
+        }
+    }
+
+    #[cfg(feature = "extra")]
+    #[test]
+    fn mutated_patches_are_valid() {
+        use crate::extra::patch::mutate;
+
+        let meta = param_meta();
+        for seed in 0..50 {
+            mutate(&FourOpFm(), &create_brass_patch(), &meta, 1.0, seed).unwrap();
+        }
+    }
+}
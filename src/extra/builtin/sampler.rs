@@ -0,0 +1,267 @@
+use std::{
+    mem::{discriminant, Discriminant},
+    rc::Rc,
+};
+
+use crate::{
+    extra::rng::Rng,
+    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
+    types::{ReadyNote, Sound},
+};
+
+use super::note_frames;
+
+/// Mod that plays prerecorded samples, for example drum hits.
+///
+/// Percussive and pitched notes play one of the samples at its own sampling rate,
+/// cut or padded with silence to the length of the note together with its decay.
+/// Rests produce silence. Notes longer than 10 minutes at 48000 Hz (in frames of
+/// the sample) are rejected.
+///
+/// Samples are kept in [`Rc`]s, so that many players can share them, for example
+/// ones loaded from WAV files by `SampleBank` from [`storage`][crate::extra::storage].
+///
+/// Config is `[mode, parameter]`, where mode is one of:
+/// - `"fixed"`: always play the sample at index `parameter`
+/// - `"round_robin"`: play the samples in turn, starting from index `parameter`
+/// - `"random"`: play a random sample, using `parameter` as the seed
+///
+/// State is either empty (nothing was played yet) or 12 bytes: the round-robin
+/// counter or the state of the [random number generator][Rng] as little-endian `u64`,
+/// followed by the index of the last played sample as little-endian `u32`.
+pub struct SamplePlayer {
+    samples: Vec<Rc<Sound>>,
+}
+
+/// Length of non-empty state.
+const STATE_LEN: usize = 12;
+
+impl SamplePlayer {
+    /// Create a sample player from a list of samples.
+    pub fn new(samples: Vec<Rc<Sound>>) -> Self {
+        Self { samples }
+    }
+
+    /// Get the samples.
+    pub fn samples(&self) -> &[Rc<Sound>] {
+        &self.samples
+    }
+
+    /// Get the index of the last played sample from player's state.
+    pub fn last_played(state: &ResState) -> Option<usize> {
+        match state.len() {
+            STATE_LEN => Some(u32::from_le_bytes(state[8..12].try_into().unwrap()) as usize),
+            _ => None,
+        }
+    }
+
+    /// Pick the next sample, returning its index and the new counter or RNG state.
+    fn select(&self, mode: &str, parameter: u64, state: &ResState) -> (usize, u64) {
+        let count = self.samples.len() as u64;
+        let counter = match state.len() {
+            STATE_LEN => Some(u64::from_le_bytes(state[0..8].try_into().unwrap())),
+            _ => None,
+        };
+        match mode {
+            "round_robin" => {
+                let index = counter.unwrap_or(parameter) % count;
+                (index as usize, (index + 1) % count)
+            }
+            "random" => {
+                let mut rng = Rng::new(counter.unwrap_or(parameter));
+                let index = rng.below(count);
+                (index as usize, u64::from_le_bytes(rng.to_bytes()))
+            }
+            _ => (parameter as usize, 0),
+        }
+    }
+}
+
+impl Resource for SamplePlayer {
+    fn orig_name(&self) -> &str {
+        "Sample player"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_SAMPLE_PLAYER"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 2 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 2, got {}",
+                conf.len()
+            )));
+        }
+        if self.samples.is_empty() {
+            return Err(StringError("player has no samples".to_string()));
+        }
+        let parameter = conf[1].as_u64().ok_or(StringError(
+            "argument 2 (parameter) is not nonnegative integer".to_string(),
+        ))?;
+        match conf[0].as_str() {
+            Some("fixed") if parameter >= self.samples.len() as u64 => Err(StringError(format!(
+                "sample index {parameter} is out of range, there are {} samples",
+                self.samples.len()
+            ))),
+            Some("fixed") | Some("round_robin") | Some("random") => Ok(()),
+            _ => Err(StringError(
+                "argument 1 (mode) is not \"fixed\", \"round_robin\" or \"random\"".to_string(),
+            )),
+        }
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (state.is_empty() || state.len() == STATE_LEN).then_some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that plays one of the given samples"
+    }
+}
+
+impl Mod for SamplePlayer {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        self.check_state(state)
+            .ok_or(StringError("state is invalid".to_string()))?;
+        let input = input
+            .as_ready_note()
+            .ok_or(StringError("input has to be a ReadyNote".to_string()))?;
+        let seconds = input.len + input.decay_time;
+        if !seconds.is_finite() || (seconds < 0.0) {
+            return Err(StringError(format!(
+                "note length of {seconds} s is not finite and nonnegative"
+            )));
+        }
+
+        let conf = conf.as_slice();
+        let (index, counter) =
+            self.select(conf[0].as_str().unwrap(), conf[1].as_u64().unwrap(), state);
+        let sample = &self.samples[index];
+        let frames = note_frames(seconds as f64, sample.sampling_rate() as f64)?;
+        let mut data = vec![[0.0, 0.0]; frames].into_boxed_slice();
+        if input.percussive || input.pitch.is_some() {
+            let played = frames.min(sample.len_frames());
+            data[..played].copy_from_slice(&sample.data()[..played]);
+        }
+
+        let mut new_state = [0; STATE_LEN];
+        new_state[0..8].copy_from_slice(&counter.to_le_bytes());
+        new_state[8..12].copy_from_slice(&(index as u32).to_le_bytes());
+        Ok((
+            ModData::Sound(Sound::new(data, sample.sampling_rate())),
+            Box::new(new_state),
+        ))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::resource::JsonArray;
+
+    use super::*;
+
+    /// Player with three samples, each filled with its own index.
+    fn player() -> SamplePlayer {
+        SamplePlayer::new(
+            (0..3)
+                .map(|i| {
+                    Rc::from(Sound::new(
+                        vec![[i as f32; 2]; 100].into_boxed_slice(),
+                        48000,
+                    ))
+                })
+                .collect(),
+        )
+    }
+
+    fn play_sequence(mode: &str, parameter: u64, count: usize) -> Vec<usize> {
+        let player = player();
+        let conf = JsonArray::from_values([json!(mode), json!(parameter)]).unwrap();
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.001,
+            percussive: true,
+            ..Default::default()
+        });
+        let mut state: Box<ResState> = Box::new([]);
+        let mut played = Vec::new();
+        for _ in 0..count {
+            let (out, new_state) = player.apply(&note, &conf, &state).unwrap();
+            let index = SamplePlayer::last_played(&new_state).unwrap();
+            assert_eq!(out.as_sound().unwrap().data()[0][0], index as f32);
+            played.push(index);
+            state = new_state;
+        }
+        played
+    }
+
+    #[test]
+    fn round_robin_cycles() {
+        assert_eq!(play_sequence("round_robin", 0, 5), [0, 1, 2, 0, 1]);
+        assert_eq!(play_sequence("round_robin", 2, 3), [2, 0, 1]);
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let sequence = play_sequence("random", 99, 16);
+        assert_eq!(sequence, play_sequence("random", 99, 16));
+        assert!(sequence.iter().all(|x| *x < 3));
+        assert_ne!(sequence, play_sequence("random", 100, 16));
+    }
+
+    #[test]
+    fn fixed_index_is_checked() {
+        assert_eq!(play_sequence("fixed", 1, 3), [1, 1, 1]);
+        let conf = JsonArray::from_values([json!("fixed"), json!(3)]).unwrap();
+        assert!(player().check_config(&conf).is_err());
+    }
+
+    #[test]
+    fn players_share_samples() {
+        let first = player();
+        let second = SamplePlayer::new(first.samples().to_vec());
+        assert!(Rc::ptr_eq(&first.samples()[0], &second.samples()[0]));
+        assert_eq!(Rc::strong_count(&first.samples()[0]), 2);
+    }
+
+    #[test]
+    fn rest_is_silent() {
+        let conf = JsonArray::from_values([json!("fixed"), json!(2)]).unwrap();
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.001,
+            ..Default::default()
+        });
+        let (out, _) = player().apply(&note, &conf, &[]).unwrap();
+        let sound = out.as_sound().unwrap();
+        assert_eq!(sound.data().len(), 48);
+        assert!(sound.data().iter().all(|x| *x == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn overly_long_note_is_rejected() {
+        let conf = JsonArray::from_values([json!("fixed"), json!(0)]).unwrap();
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 1.0e9,
+            percussive: true,
+            ..Default::default()
+        });
+        assert!(player().apply(&note, &conf, &[]).is_err());
+    }
+}
@@ -1,23 +1,30 @@
 use crate::{
-    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
-    types::{ReadyNote, Sound},
+    resource::{
+        validate, ConfigField, ConfigSchema, FieldType, Mod, ModData, ResConfig, ResState,
+        Resource, StringError,
+    },
+    types::{quantize_sample, GlideCurve, ReadyNote, Sound, VelocityCurve},
 };
 use dasp::{
     interpolate::linear::Linear,
-    signal::{self, ConstHz, FromIterator, MulAmp, Saw, Sine, Take, UntilExhausted},
+    signal::{self, ConstHz, FromIterator, Hz, MulAmp, Saw, Sine, Take, UntilExhausted},
     Frame, Signal,
 };
-use serde_json::Value as JsonValue;
+use serde_json::json;
 use std::{
     iter::{self, Chain, FromFn},
     mem::{discriminant, Discriminant},
 };
 
+use super::{note_frames, MAX_FRAMES};
+
 //dasp allows generalising over impl Signal, but I couldn't use that, this
 //enum is used instead.
 enum Wave {
     Sine(Sine<ConstHz>),
     Saw(Saw<ConstHz>),
+    GlideSine(Sine<Hz<FromIterator<Glide>>>),
+    GlideSaw(Saw<Hz<FromIterator<Glide>>>),
 }
 
 impl Signal for Wave {
@@ -27,10 +34,31 @@ impl Signal for Wave {
         match self {
             Wave::Sine(w) => w.next().map(clamp_f64_to_i8),
             Wave::Saw(w) => w.next().map(clamp_f64_to_i8),
+            Wave::GlideSine(w) => w.next().map(clamp_f64_to_i8),
+            Wave::GlideSaw(w) => w.next().map(clamp_f64_to_i8),
         }
     }
 }
 
+//Frequency of a note that glides to its end pitch over its length, and stays there.
+struct Glide {
+    start: f64,
+    end: f64,
+    curve: GlideCurve,
+    len_frames: f64,
+    count: usize,
+}
+
+impl Iterator for Glide {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let progress = self.count as f64 / self.len_frames.max(1.0);
+        self.count += 1;
+        Some(self.curve.frequency(self.start, self.end, progress))
+    }
+}
+
 //Same as Wave
 enum IterSignal<S: Signal> {
     Take(Take<S>),
@@ -48,7 +76,25 @@ impl<S: Signal> Iterator for IterSignal<S> {
     }
 }
 
+/// Sampling rate of the produced sound.
+const SAMPLING_RATE: f64 = 48000.0;
+
 /// Example four-operator FM synthesizer.
+///
+/// Lengths of envelope stages are capped at 10 minutes each. A note that, together
+/// with its decay, is longer than 10 minutes is rejected.
+///
+/// Release starts when the key is released. If the note has a
+/// [release velocity][ReadyNote::release_velocity], the length of the release is
+/// multiplied by `2^((128 - release velocity) / 32)`: 128 keeps the configured rate,
+/// 0 makes the release 16 times longer, and 255 cuts the sound immediately.
+///
+/// If the note has an [end pitch][ReadyNote::end_pitch], every operator glides to it
+/// while the key is held, following the note's [glide curve][ReadyNote::glide_curve].
+///
+/// Config has 34 values, optionally followed by a [velocity curve][VelocityCurve] as
+/// a string, such as `"linear"`, that scales the output by note's velocity. Without
+/// it, velocity is ignored.
 pub struct FourOpFm();
 
 impl Resource for FourOpFm {
@@ -61,26 +107,20 @@ impl Resource for FourOpFm {
     }
 
     fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
-        let conf = conf.as_slice();
         let len = conf.len();
-        if len != 34 {
+        if len != 34 && len != 35 {
             return Err(StringError(format!(
-                "wrong number of values: expected 34, got {len}"
+                "wrong number of values: expected 34 or 35, got {len}"
             )));
         }
-        get_int_value(&conf[0], 0, 7)?;
-        get_bool_value(&conf[1])?;
-        for op in 0..4 {
-            get_int_value(&conf[2 + 8 * op], 0, 511)?;
-            get_int_value(&conf[3 + 8 * op], 0, 511)?;
-            get_int_value(&conf[4 + 8 * op], 0, 511)?;
-            get_int_value(&conf[5 + 8 * op], 0, 511)?;
-            get_int_value(&conf[6 + 8 * op], 0, 127)?;
-            get_int_value(&conf[7 + 8 * op], 0, 127)?;
-            get_int_value(&conf[8 + 8 * op], 0, 31)?;
-            get_int_value(&conf[9 + 8 * op], -511, 511)?;
+        if len == 35 {
+            get_velocity_curve(conf)?;
         }
-        Ok(())
+        let schema = self.config_schema().unwrap();
+        validate(conf, &schema.constraints()).map_err(|e| match e.index() {
+            Some(i) => e.named(&schema.fields()[i].name),
+            None => e.into(),
+        })
     }
 
     fn check_state(&self, _: &ResState) -> Option<()> {
@@ -90,6 +130,45 @@ impl Resource for FourOpFm {
     fn description(&self) -> &str {
         "Simple four operator FM."
     }
+
+    /// Schema of the 34 required values, without the velocity curve. Operator
+    /// parameters are named after the operator, from `ar1` to `dt4`.
+    ///
+    /// Every value has a default: algorithm 7, where all operators are carriers,
+    /// sine waves, and operators that play at their base frequency with a fast attack
+    /// and no decay, but are silent. Setting the algorithm and total levels is
+    /// enough to get a sound.
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        let mut fields = vec![
+            ConfigField::new("alg", FieldType::Integer, "Algorithm that chains operators")
+                .with_range(0.0, 7.0)
+                .with_default(json!(7)),
+            ConfigField::new("saw", FieldType::Bool, "Make the first operator sawtooth")
+                .with_default(json!(false)),
+        ];
+        for op in 1..=4 {
+            let field = |name: &str, doc: &str, min: f64, max: f64, default: i64| {
+                ConfigField::new(
+                    format!("{name}{op}"),
+                    FieldType::Integer,
+                    format!("{doc} of operator {op}"),
+                )
+                .with_range(min, max)
+                .with_default(json!(default))
+            };
+            fields.extend([
+                field("ar", "Attack rate", 0.0, 511.0, 64),
+                field("dr", "Decay rate", 0.0, 511.0, 240),
+                field("sr", "Sustain rate", 0.0, 511.0, 400),
+                field("rr", "Release rate", 0.0, 511.0, 208),
+                field("sl", "Sustain level", 0.0, 127.0, 127),
+                field("tl", "Total level", 0.0, 127.0, 0),
+                field("ml", "Frequency multiplier", 0.0, 31.0, 1),
+                field("dt", "Detune in 1/32 of a cent", -511.0, 511.0, 0),
+            ]);
+        }
+        Some(ConfigSchema::new(fields))
+    }
 }
 
 impl Mod for FourOpFm {
@@ -99,31 +178,42 @@ impl Mod for FourOpFm {
         conf: &ResConfig,
         _: &[u8],
     ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        if input.is_ready_notes() {
+            return Err(StringError(
+                "chords are not supported, play each note on its own channel".to_string(),
+            ));
+        }
         let input = input
             .as_ready_note()
             .ok_or(StringError("input has to be a ReadyNote".to_string()))?;
+        //Number of frames to render.
+        let time = render_frames(input)?;
+        //Percussive notes without pitch are treated as rests too.
         if input.pitch.is_none() {
-            let len = ((input.len + input.decay_time) * 48000.0) as usize;
-            let data: Box<[[f32; 2]]> = vec![[0.0, 0.0]; len].into_boxed_slice();
-            return Ok((ModData::Sound(Sound::new(data, 48000)), Box::new([])));
+            let silence = Sound::silence(input.len + input.decay_time, 48000);
+            return Ok((ModData::Sound(silence), Box::new([])));
         }
 
-        let conf = conf.as_slice();
         //Algorhitm to chain operators. Taken from YM2608 datasheet.
-        let alg = get_int_value(&conf[0], 0, 7)? as i8;
+        let alg = conf.try_i64(0)? as i8;
         //Should the first operator be sawtooth or not
-        let saw = get_bool_value(&conf[1])?;
+        let saw = conf.try_bool(1)?;
         let mut op_params = <[FnParams; 4]>::default();
-        for op in 0..4 {
-            op_params[op].ar = get_int_value(&conf[2 + 8 * op], 0, 511)? as i16;
-            op_params[op].dr = get_int_value(&conf[3 + 8 * op], 0, 511)? as i16;
-            op_params[op].sr = get_int_value(&conf[4 + 8 * op], 0, 511)? as i16;
-            op_params[op].rr = get_int_value(&conf[5 + 8 * op], 0, 511)? as i16;
-            op_params[op].sl = get_int_value(&conf[6 + 8 * op], 0, 127)? as i8;
-            op_params[op].tl = get_int_value(&conf[7 + 8 * op], 0, 127)? as i8;
-            op_params[op].ml = get_int_value(&conf[8 + 8 * op], 0, 31)? as i8;
-            op_params[op].dt = get_int_value(&conf[9 + 8 * op], -511, 511)? as i16;
+        for (op, params) in op_params.iter_mut().enumerate() {
+            params.ar = conf.try_i64(2 + 8 * op)? as i16;
+            params.dr = conf.try_i64(3 + 8 * op)? as i16;
+            params.sr = conf.try_i64(4 + 8 * op)? as i16;
+            params.rr = conf.try_i64(5 + 8 * op)? as i16;
+            params.sl = conf.try_i64(6 + 8 * op)? as i8;
+            params.tl = conf.try_i64(7 + 8 * op)? as i8;
+            params.ml = conf.try_i64(8 + 8 * op)? as i8;
+            params.dt = conf.try_i64(9 + 8 * op)? as i16;
         }
+        let gain = match conf.len() == 35 {
+            true => get_velocity_curve(conf)?.apply(input.velocity) as f64,
+            false => 1.0,
+        };
         let op0 = play_fn_operator(&op_params[0], input, saw);
         let op1 = play_fn_operator(&op_params[1], input, false);
         let op2 = play_fn_operator(&op_params[2], input, false);
@@ -135,75 +225,35 @@ impl Mod for FourOpFm {
                 let op1 = op1.mul_hz(linear(), op0.offset_amp(1.0));
                 let op2 = op2.mul_hz(linear(), op1.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op2.offset_amp(1.0));
-                let out = op3.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(op3, time, gain))
             }
             //Operators 0 and 1 modulate 2, which goes into 3
             1 => {
                 let op2 = op2.mul_hz(linear(), op0.offset_amp(1.0));
                 let op2 = op2.mul_hz(linear(), op1.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op2.offset_amp(1.0));
-                let out = op3.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(op3, time, gain))
             }
             //Operator 1 modulates 2, 0 and 2 go into 3
             2 => {
                 let op2 = op2.mul_hz(linear(), op1.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op0.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op2.offset_amp(1.0));
-                let out = op3.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(op3, time, gain))
             }
             //Operator 0 modulates 1, 1 and 2 go into 3
             3 => {
                 let op1 = op1.mul_hz(linear(), op0.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op1.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op2.offset_amp(1.0));
-                let out = op3.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(op3, time, gain))
             }
             //Two lines (0 into 1, 2 into 3)
             4 => {
                 let op1 = op1.mul_hz(linear(), op0.offset_amp(1.0));
                 let op3 = op3.mul_hz(linear(), op2.offset_amp(1.0));
                 let out = op3.add_amp(op1);
-                let out = out.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(out, time, gain))
             }
             //0 goes into 1, 2 and 3
             5 => {
@@ -217,42 +267,18 @@ impl Mod for FourOpFm {
                 let op2 = op2.mul_hz(linear(), op0_1.scale_amp(0.5).offset_amp(0.5));
                 let op3 = op3.mul_hz(linear(), op0_2.scale_amp(0.5).offset_amp(0.5));
                 let out = op3.add_amp(op1).add_amp(op2).scale_amp(0.333);
-                let out = out.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(out, time, gain))
             }
             //0 goes into 1
             6 => {
                 let op1 = op1.mul_hz(linear(), op0.scale_amp(0.5).offset_amp(0.5));
                 let out = op3.add_amp(op1).add_amp(op2).scale_amp(0.333);
-                let out = out.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(out, time, gain))
             }
             //No modulation
             7 => {
                 let out = op3.add_amp(op1).add_amp(op2).add_amp(op0).scale_amp(0.25);
-                let out = out.map(|x| [x as f32, x as f32]);
-                let time = ((input.len + input.decay_time) * 48000.0) as usize;
-                Ok((
-                    ModData::Sound(Sound::new(
-                        out.take(time).map(clamp_frame_to_i8).collect(),
-                        48000,
-                    )),
-                    Box::new([]),
-                ))
+                Ok(render(out, time, gain))
             }
             _ => unreachable!(),
         }
@@ -265,6 +291,20 @@ impl Mod for FourOpFm {
     fn output_type(&self) -> Discriminant<ModData> {
         discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
     }
+
+    /// Length of the longest release stage among the operators that are not silent.
+    /// A [release velocity][ReadyNote::release_velocity] below 128 makes the release
+    /// longer than this. An invalid config has no tail.
+    fn tail_seconds(&self, conf: &ResConfig) -> f32 {
+        if self.check_config(conf).is_err() {
+            return 0.0;
+        }
+        (0..4)
+            .filter(|op| conf.get_i64(7 + 8 * op) != Some(0))
+            .filter_map(|op| conf.get_i64(5 + 8 * op))
+            .map(|rr| stage_frames(rr as i16) / SAMPLING_RATE)
+            .fold(0.0, f64::max) as f32
+    }
 }
 
 #[derive(Default, Clone)]
@@ -327,21 +367,42 @@ fn play_fn_operator(
 
     //Detune is treated as 1/32 of a cent.
     let detune = 2.0_f64.powf(params.dt as f64 / 3200.0);
-    //Wave's frequency.
-    let native: signal::ConstHz =
-        signal::rate(48000.0).const_hz(note.pitch.unwrap() as f64 * multiplier * detune);
     //Used for envelope calculation.
     let sustain_mul = (127 - params.sl) as f64 / 127.0;
     //Note's length in frames.
     let len_frames = (note.len * 48000.0) as usize;
+    //Wave's frequency.
+    let start = note.pitch.unwrap() as f64 * multiplier * detune;
+    let wave = match note.end_pitch {
+        Some(end) => {
+            let glide = signal::rate(48000.0).hz(signal::from_iter(Glide {
+                start,
+                end: end as f64 * multiplier * detune,
+                curve: note.glide_curve,
+                len_frames: len_frames as f64,
+                count: 0,
+            }));
+            match saw {
+                true => Wave::GlideSaw(glide.saw()),
+                false => Wave::GlideSine(glide.sine()),
+            }
+        }
+        None => {
+            let native: signal::ConstHz = signal::rate(48000.0).const_hz(start);
+            match saw {
+                true => Wave::Saw(native.saw()),
+                false => Wave::Sine(native.sine()),
+            }
+        }
+    };
     //Sound level during sustain.
     let sustain_level = params.sl as f64 / 127.0;
 
     //Lengths of envelope parts.
-    let attack_frames = 2.0_f64.powf(params.ar as f64 / 16.0);
-    let decay_frames = 2.0_f64.powf(params.dr as f64 / 16.0);
-    let sustain_frames = 2.0_f64.powf(params.sr as f64 / 16.0);
-    let release_frames = 2.0_f64.powf(params.rr as f64 / 16.0);
+    let attack_frames = stage_frames(params.ar);
+    let decay_frames = stage_frames(params.dr);
+    let sustain_frames = stage_frames(params.sr);
+    let release_frames = release_frames(params.rr, note.release_velocity);
 
     //Find sound level when release needs to happen.
     let release_level = match len_frames {
@@ -406,7 +467,7 @@ fn play_fn_operator(
     let ads = if ads_len <= len_frames {
         IterSignal::All(signal::from_iter(attack.chain(decay).chain(sustain)).until_exhausted())
     } else {
-        IterSignal::Take(signal::from_iter(attack.chain(decay).chain(sustain)).take(ads_len))
+        IterSignal::Take(signal::from_iter(attack.chain(decay).chain(sustain)).take(len_frames))
     };
     let total_level = params.tl as f64 / 127.0;
     let envelope = signal::from_iter(
@@ -415,44 +476,336 @@ fn play_fn_operator(
             .map(move |x| x * total_level),
     );
 
-    match saw {
-        true => Wave::Saw(native.saw()).mul_amp(envelope),
-        false => Wave::Sine(native.sine()).mul_amp(envelope),
-    }
+    wave.mul_amp(envelope)
 }
 
-fn linear() -> Linear<f64> {
-    Linear::new(0.0, 1.0)
+//Rate of 511 would otherwise give 2^31.9 frames.
+fn stage_frames(rate: i16) -> f64 {
+    2.0_f64.powf(rate as f64 / 16.0).min(MAX_FRAMES)
 }
 
-fn get_int_value(val: &JsonValue, lower: i64, upper: i64) -> Result<i64, StringError> {
-    match val.as_i64() {
-        Some(x) => match x {
-            x if (x < lower) || (x > upper) => Err(StringError(format!(
-                "value {} is outside of range {} - {}",
-                x, lower, upper
-            ))),
-            _ => Ok(x),
-        },
-        None => Err(StringError("extracted value is not integer".to_string())),
+//Release velocity of 128 keeps the release rate, every 32 steps away from it double
+//or halve the length of the release. 255 cuts the sound.
+fn release_frames(rate: i16, release_velocity: Option<u8>) -> f64 {
+    match release_velocity {
+        None => stage_frames(rate),
+        Some(255) => 1.0,
+        Some(v) => {
+            (stage_frames(rate) * 2.0_f64.powf((128.0 - v as f64) / 32.0)).clamp(1.0, MAX_FRAMES)
+        }
     }
 }
 
-fn get_bool_value(val: &JsonValue) -> Result<bool, StringError> {
-    match val.as_bool() {
-        Some(x) => Ok(x),
-        None => Err(StringError("extracted value is not bool".to_string())),
+fn render_frames(note: &ReadyNote) -> Result<usize, StringError> {
+    let seconds = note.len as f64 + note.decay_time as f64;
+    if !seconds.is_finite() || (note.len < 0.0) || (note.decay_time < 0.0) {
+        return Err(StringError(format!(
+            "note length {} s and decay time {} s have to be finite and nonnegative",
+            note.len, note.decay_time
+        )));
     }
+    note_frames(seconds, SAMPLING_RATE)
 }
 
-//Could just divide, truncate, and multiply back
+fn linear() -> Linear<f64> {
+    Linear::new(0.0, 1.0)
+}
+
+fn get_velocity_curve(conf: &ResConfig) -> Result<VelocityCurve, StringError> {
+    conf.try_str(34)
+        .map_err(|e| e.named("velocity curve"))?
+        .parse()
+}
+
+//Scales the sample so that i8 covers -0.25..0.25, truncates it to an integer
+//and scales it back. NaN becomes silence.
 fn clamp_f64_to_i8(f: f64) -> f64 {
-    ((f * 512.0) as i8) as f64 / 512.0
+    quantize_sample(f * 4.0, 8) / 4.0
 }
 
-fn clamp_frame_to_i8(f: [f32; 2]) -> [f32; 2] {
-    [
-        ((f[0] * 512.0) as i8) as f32 / 512.0,
-        ((f[1] * 512.0) as i8) as f32 / 512.0,
-    ]
+/// Take `time` samples of the operator output, scaled by `gain`, as a mono sound.
+fn render(out: impl Signal<Frame = f64>, time: usize, gain: f64) -> (ModData, Box<ResState>) {
+    let frames = out.map(|x| {
+        let x = clamp_f64_to_i8((x * gain) as f32 as f64) as f32;
+        [x, x]
+    });
+    (
+        ModData::Sound(Sound::from_signal(frames, time, 48000)),
+        Box::new([]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::VersionedFm;
+
+    use super::*;
+
+    fn config(rate: i64) -> ResConfig {
+        let mut conf = vec![json!(7), json!(false)];
+        for _ in 0..4 {
+            conf.extend([rate, rate, rate, rate, 100, 127, 1, 0].map(|x| json!(x)));
+        }
+        ResConfig::from_values(conf).unwrap()
+    }
+
+    fn note(len: f32) -> ModData {
+        ModData::ReadyNote(ReadyNote {
+            len,
+            decay_time: 0.0,
+            pitch: Some(440.0),
+            velocity: 128,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn slowest_envelope_is_capped() {
+        let conf = config(511);
+        FourOpFm().check_config(&conf).unwrap();
+        let (out, _) = FourOpFm().apply(&note(0.5), &conf, &[]).unwrap();
+        assert_eq!(out.as_sound().unwrap().data().len(), 24000);
+    }
+
+    #[test]
+    fn overly_long_note_is_rejected() {
+        let conf = config(0);
+        assert!(FourOpFm().apply(&note(1.0e9), &conf, &[]).is_err());
+        assert!(FourOpFm().apply(&note(f32::INFINITY), &conf, &[]).is_err());
+        assert!(FourOpFm().apply(&note(-1.0), &conf, &[]).is_err());
+    }
+
+    #[test]
+    fn percussive_note_without_pitch_is_silent() {
+        let drum = ModData::ReadyNote(ReadyNote {
+            len: 0.1,
+            percussive: true,
+            ..Default::default()
+        });
+        let (out, _) = FourOpFm().apply(&drum, &config(0), &[]).unwrap();
+        let out = out.as_sound().unwrap();
+        assert!(!out.data().is_empty());
+        assert!(out.data().iter().all(|x| *x == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn velocity_curve_scales_output() {
+        let play = |conf: &ResConfig, velocity: u8| {
+            let note = ModData::ReadyNote(ReadyNote {
+                len: 0.1,
+                pitch: Some(440.0),
+                velocity,
+                ..Default::default()
+            });
+            let (out, _) = FourOpFm().apply(&note, conf, &[]).unwrap();
+            out.as_sound().unwrap().to_owned_sound()
+        };
+        let plain = crate::extra::builtin::patches::create_piano_patch();
+        let mut curved = plain.clone();
+        curved.push(json!("exponential 2")).unwrap();
+        FourOpFm().check_config(&curved).unwrap();
+
+        // Without a curve, velocity is ignored
+        assert_eq!(play(&plain, 0), play(&plain, 255));
+        assert!(play(&curved, 0).data().iter().all(|x| *x == [0.0, 0.0]));
+        assert_eq!(play(&curved, 255), play(&plain, 255));
+        assert!(play(&curved, 128).peak() < play(&plain, 128).peak());
+
+        let mut bad = plain.clone();
+        bad.push(json!("cubic")).unwrap();
+        assert!(FourOpFm().check_config(&bad).is_err());
+    }
+
+    #[test]
+    fn old_configs_are_migrated() {
+        let old = crate::extra::builtin::patches::create_piano_patch();
+        assert!(VersionedFm.check_config(&old).is_err());
+
+        let res: Box<dyn Resource> = Box::new(VersionedFm);
+        let new = res.migrate_config(1, old.clone()).unwrap();
+        res.check_config(&new).unwrap();
+        assert_eq!(new.get_str(34), Some("linear"));
+        assert_eq!(res.migrate_config(2, new.clone()).unwrap(), new);
+        assert!(res.migrate_config(3, old).is_err());
+    }
+
+    #[test]
+    fn out_of_range_values_are_named() {
+        let mut conf = config(0).into_inner();
+        conf[0] = json!(99);
+        let conf = ResConfig::from_value(conf).unwrap();
+        let err = FourOpFm().check_config(&conf).unwrap_err();
+        assert_eq!(err.0, "argument 1 (alg) is outside of its range");
+
+        let mut conf = config(0).into_inner();
+        conf[10] = json!(512);
+        let conf = ResConfig::from_value(conf).unwrap();
+        let err = FourOpFm().check_config(&conf).unwrap_err();
+        assert_eq!(err.0, "argument 11 (ar2) is outside of its range");
+    }
+
+    #[cfg(feature = "extra")]
+    #[test]
+    fn config_schema_describes_patches() {
+        use crate::extra::config_builder::ConfigBuilder;
+
+        let schema = FourOpFm().config_schema().unwrap();
+        let piano = crate::extra::builtin::patches::create_piano_patch();
+        schema.check(&piano).unwrap();
+        assert_eq!(schema.position("dt4"), Some(33));
+
+        // Filling the values by name in reverse order gives the same config
+        let mut builder = ConfigBuilder::with_schema(&schema);
+        for (field, value) in schema.fields().iter().zip(piano.as_slice()).rev() {
+            builder.set_by_name(&field.name, value).unwrap();
+        }
+        match builder {
+            ConfigBuilder::Config(conf) => assert_eq!(conf, piano),
+            ConfigBuilder::Builder(_) => panic!("config is not finished"),
+        }
+    }
+
+    #[cfg(feature = "extra")]
+    #[test]
+    fn defaults_need_only_algorithm_and_levels() {
+        use crate::extra::config_builder::ConfigBuilder;
+
+        let schema = FourOpFm().config_schema().unwrap();
+        let mut builder = ConfigBuilder::with_schema(&schema);
+        builder.set_by_name("alg", &json!(4)).unwrap();
+        builder.set_by_name("tl2", &json!(100)).unwrap();
+        builder.set_by_name("tl4", &json!(100)).unwrap();
+        let conf = builder.finish_with_defaults().unwrap();
+        FourOpFm().check_config(&conf).unwrap();
+        let (out, _) = FourOpFm().apply(&note(0.1), &conf, &[]).unwrap();
+        assert!(out.as_sound().unwrap().peak() > 0.01);
+    }
+
+    #[test]
+    fn glide_reaches_end_pitch() {
+        let glide = Glide {
+            start: 440.0,
+            end: 880.0,
+            curve: GlideCurve::Linear,
+            len_frames: 4.0,
+            count: 0,
+        };
+        let freqs: Vec<f64> = glide.take(6).collect();
+        assert_eq!(freqs, [440.0, 550.0, 660.0, 770.0, 880.0, 880.0]);
+
+        let play = |end_pitch: Option<f32>| {
+            let note = ModData::ReadyNote(ReadyNote {
+                len: 0.1,
+                pitch: Some(440.0),
+                end_pitch,
+                ..Default::default()
+            });
+            let piano = crate::extra::builtin::patches::create_piano_patch();
+            let (out, _) = FourOpFm().apply(&note, &piano, &[]).unwrap();
+            out.as_sound().unwrap().to_owned_sound()
+        };
+        assert_eq!(play(None), play(Some(440.0)));
+        assert_ne!(play(None), play(Some(880.0)));
+    }
+
+    /// Number of frames after key release until the sound becomes silent.
+    fn time_to_silence(release_velocity: u8) -> usize {
+        let mut conf = vec![json!(7), json!(false)];
+        for _ in 0..4 {
+            conf.extend([0, 0, 400, 208, 127, 127, 1, 0].map(|x| json!(x)));
+        }
+        let conf = ResConfig::from_values(conf).unwrap();
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.1,
+            decay_time: 2.0,
+            pitch: Some(440.0),
+            velocity: 128,
+            release_velocity: Some(release_velocity),
+            ..Default::default()
+        });
+        let (out, _) = FourOpFm().apply(&note, &conf, &[]).unwrap();
+        let data = out.as_sound().unwrap().data();
+        let last = data.iter().rposition(|x| x[0] != 0.0).unwrap();
+        last.saturating_sub(4800)
+    }
+
+    #[test]
+    fn release_velocity_orders_release_time() {
+        let slow = time_to_silence(0);
+        let normal = time_to_silence(128);
+        let cut = time_to_silence(255);
+        assert!(slow > normal);
+        assert!(normal > cut);
+        // 2^(208 / 16) frames, minus the quiet tail
+        assert!(normal <= 8192);
+        assert!(cut < 10);
+    }
+
+    #[test]
+    fn tail_covers_the_release() {
+        let conf = config(208);
+        // 2^(208 / 16) frames
+        assert_eq!(FourOpFm().tail_seconds(&conf), (8192.0 / 48000.0) as f32);
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.1,
+            decay_time: 1.0,
+            pitch: Some(440.0),
+            velocity: 128,
+            ..Default::default()
+        });
+        let (out, _) = FourOpFm().apply(&note, &conf, &[]).unwrap();
+        let last = out
+            .as_sound()
+            .unwrap()
+            .data()
+            .iter()
+            .rposition(|x| x[0] != 0.0)
+            .unwrap();
+        assert!((4800..4800 + 8192).contains(&last), "sound ends at {last}");
+
+        // Silent operators do not count
+        let mut quiet = conf.as_slice().to_vec();
+        quiet[5] = json!(511);
+        quiet[7] = json!(0);
+        let quiet = ResConfig::from_values(quiet).unwrap();
+        assert_eq!(FourOpFm().tail_seconds(&quiet), (8192.0 / 48000.0) as f32);
+        let mut silent = conf.as_slice().to_vec();
+        (0..4).for_each(|op| silent[7 + 8 * op] = json!(0));
+        let silent = ResConfig::from_values(silent).unwrap();
+        assert_eq!(FourOpFm().tail_seconds(&silent), 0.0);
+        assert_eq!(FourOpFm().tail_seconds(&ResConfig::new()), 0.0);
+    }
+
+    #[test]
+    fn release_starts_at_note_length() {
+        // Sustain alone outlasts the note, release is almost instant
+        let mut conf = vec![json!(7), json!(false)];
+        for _ in 0..4 {
+            conf.extend([0, 0, 511, 0, 100, 127, 1, 0].map(|x| json!(x)));
+        }
+        let conf = ResConfig::from_values(conf).unwrap();
+        let note = ModData::ReadyNote(ReadyNote {
+            len: 0.1,
+            decay_time: 1.0,
+            pitch: Some(440.0),
+            velocity: 128,
+            ..Default::default()
+        });
+        let (out, _) = FourOpFm().apply(&note, &conf, &[]).unwrap();
+        let data = out.as_sound().unwrap().data();
+        assert_eq!(data.len(), 52800);
+        let last = data.iter().rposition(|x| x[0] != 0.0).unwrap();
+        assert!((4700..4810).contains(&last), "sound ends at {last}");
+    }
+
+    #[test]
+    fn clamping_saturates() {
+        assert_eq!(clamp_f64_to_i8(1.0), 127.0 / 512.0);
+        assert_eq!(clamp_f64_to_i8(-1.0), -128.0 / 512.0);
+        assert_eq!(clamp_f64_to_i8(f64::NAN), 0.0);
+        assert_eq!(
+            clamp_f64_to_i8(0.1),
+            ((0.1_f64 * 512.0) as i8) as f64 / 512.0
+        );
+    }
 }
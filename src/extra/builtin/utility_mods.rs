@@ -1,13 +1,74 @@
 use std::mem::{discriminant, Discriminant};
 
+use serde_json::json;
+
 use crate::{
-    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
-    types::{Note, ReadyNote},
+    resource::{
+        ConfigField, ConfigSchema, FieldType, JsonArray, Mod, ModData, ResConfig, ResState,
+        Resource, StringError,
+    },
+    types::{KeySignature, Note, NoteConversionParams, ReadyNote, Sound},
 };
 
 /// Mod to convert Note into ResNote.
+///
+/// Config is `[frequency of C-1, length of one tick, octave, length of sound post key
+/// release, added cents]`, optionally followed by maximum length of the note in seconds.
+/// The note is converted with [`ReadyNote::from_note()`].
+///
+/// Length of one tick has to be positive and at most [`MAX_TICK_LENGTH`][ConvertNote::MAX_TICK_LENGTH]. Notes that,
+/// together with their decay, are longer than the maximum length (by default,
+/// [`DEFAULT_MAX_NOTE_LENGTH`][ConvertNote::DEFAULT_MAX_NOTE_LENGTH]) are rejected.
 pub struct ConvertNote();
 
+impl ConvertNote {
+    /// Maximum length of one tick, in seconds.
+    pub const MAX_TICK_LENGTH: f64 = 60.0;
+
+    /// Default maximum length of a converted note together with its decay, in seconds.
+    pub const DEFAULT_MAX_NOTE_LENGTH: f64 = 600.0;
+
+    /// Construct the config from named parameters.
+    ///
+    /// Same as [`ConvertNoteParams::into_config()`].
+    pub fn config_from(params: ConvertNoteParams) -> ResConfig {
+        params.into_config()
+    }
+}
+
+/// Parameters of [`ConvertNote`], in the order they appear in its config.
+pub type ConvertNoteParams = NoteConversionParams;
+
+impl NoteConversionParams {
+    /// Construct the config for [`ConvertNote`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::extra::builtin::{ConvertNote, ConvertNoteParams};
+    /// # use mleml::resource::Resource;
+    /// let conf = ConvertNoteParams {
+    ///     c_neg1_hz: 8.1758,
+    ///     tick_length: 0.125,
+    ///     octave: 4,
+    ///     post_release_ticks: 1,
+    ///     added_cents: 0,
+    /// }
+    /// .into_config();
+    /// assert!(ConvertNote().check_config(&conf).is_ok());
+    /// ```
+    pub fn into_config(self) -> ResConfig {
+        JsonArray::from_values([
+            json!(self.c_neg1_hz),
+            json!(self.tick_length),
+            json!(self.octave),
+            json!(self.post_release_ticks),
+            json!(self.added_cents),
+        ])
+        .unwrap()
+    }
+}
+
 impl Resource for ConvertNote {
     fn orig_name(&self) -> &str {
         "Prepare note for playing"
@@ -28,29 +89,36 @@ impl Resource for ConvertNote {
             }
         }
 
-        let conf = conf.as_slice();
-
-        to_result(conf.len() == 5, "incorrect config length".to_string())?;
         to_result(
-            conf[0].is_f64(),
-            "argument 1 (frequency of C-1) is not float".to_string(),
+            (conf.len() == 5) || (conf.len() == 6),
+            "incorrect config length".to_string(),
         )?;
+        conf.try_f64(0).map_err(|e| e.named("frequency of C-1"))?;
+        let tick_length = conf.try_f64(1).map_err(|e| e.named("length of one tick"))?;
         to_result(
-            conf[1].is_f64(),
-            "argument 2 (length of one tick) is not float".to_string(),
+            tick_length.is_finite()
+                && (tick_length > 0.0)
+                && (tick_length <= Self::MAX_TICK_LENGTH),
+            format!(
+                "argument 2 (length of one tick) is {tick_length} s, \
+                which is outside of range (0, {}]",
+                Self::MAX_TICK_LENGTH
+            ),
         )?;
+        let octave = conf.try_i64(2).map_err(|e| e.named("octave"))?;
         to_result(
-            conf[2].is_i64() && conf[2].as_i64().unwrap() >= 0,
+            octave >= 0,
             "argument 3 (octave) is not nonnegative integer".to_string(),
         )?;
-        to_result(
-            conf[3].is_i64(),
-            "argument 4 (length of sound post key release) is not integer".to_string(),
-        )?;
-        to_result(
-            conf[4].is_i64(),
-            "argument 5 (added cents) is not integer".to_string(),
-        )?;
+        conf.try_i64(3)
+            .map_err(|e| e.named("length of sound post key release"))?;
+        conf.try_i64(4).map_err(|e| e.named("added cents"))?;
+        if conf.len() == 6 {
+            to_result(
+                conf.get_f64(5).is_some_and(|x| x.is_finite() && x > 0.0),
+                "argument 6 (maximum note length) is not positive float".to_string(),
+            )?;
+        }
         Ok(())
     }
 
@@ -62,6 +130,31 @@ impl Resource for ConvertNote {
     fn description(&self) -> &str {
         "Built-in mod to prepare the note for playing"
     }
+
+    /// Schema of the required values, without the maximum note length.
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        Some(ConfigSchema::new(vec![
+            ConfigField::new("c_neg1_hz", FieldType::Float, "Frequency of C-1 in Hz").with_min(0.0),
+            ConfigField::new(
+                "tick_length",
+                FieldType::Float,
+                "Length of one tick in seconds",
+            )
+            .with_range(0.0, Self::MAX_TICK_LENGTH),
+            ConfigField::new("octave", FieldType::Integer, "Number of octaves above C-1")
+                .with_min(0.0),
+            ConfigField::new(
+                "post_release_ticks",
+                FieldType::Integer,
+                "Length of sound after key release, in ticks",
+            ),
+            ConfigField::new(
+                "added_cents",
+                FieldType::Integer,
+                "Cents added to every note",
+            ),
+        ]))
+    }
 }
 
 //TODO: verify
@@ -76,32 +169,26 @@ impl Mod for ConvertNote {
         if discriminant(input) != self.input_type() {
             Err(StringError("incorrect type provided".to_string()))
         } else {
-            let conf = conf.as_slice();
             let input = input.as_note().unwrap();
-            let tick_length = conf[1].as_f64().unwrap();
-
-            let len = (input
-                .len
-                .ok_or(StringError("length of the note is unspecified".to_string()))?
-                .get() as f64
-                * tick_length) as f32;
-            let decay_time = (conf[3].as_i64().unwrap() as f64 * tick_length) as f32;
-            let pitch = input.pitch.map(|semitones| {
-                conf[0].as_f64().unwrap() as f32
-                    * 2.0_f32.powf(
-                        1.0 + (semitones.get() as f32) / 12.0
-                            + (conf[4].as_i64().unwrap() as f32) / 1200.0
-                            + conf[2].as_i64().unwrap() as f32,
-                    )
-            });
-            let velocity = input.velocity;
-
-            let out = ReadyNote {
-                len,
-                decay_time,
-                pitch,
-                velocity,
+            if input.len.is_none() {
+                return Err(StringError("length of the note is unspecified".to_string()));
+            }
+            let params = NoteConversionParams {
+                c_neg1_hz: conf.try_f64(0)?,
+                tick_length: conf.try_f64(1)?,
+                octave: conf.try_i64(2)? as u64,
+                post_release_ticks: conf.try_i64(3)?,
+                added_cents: conf.try_i64(4)?,
             };
+            let out = ReadyNote::from_note(input, &params);
+
+            let max_length = conf.get_f64(5).unwrap_or(Self::DEFAULT_MAX_NOTE_LENGTH);
+            let total = out.len as f64 + out.decay_time as f64;
+            if total > max_length {
+                return Err(StringError(format!(
+                    "note lasts {total} s, which is longer than the maximum of {max_length} s"
+                )));
+            }
             Ok((ModData::ReadyNote(out), Box::new([])))
         }
     }
@@ -114,3 +201,964 @@ impl Mod for ConvertNote {
         discriminant(&ModData::ReadyNote(ReadyNote::default()))
     }
 }
+
+/// Mod to convert Notes into ReadyNotes, for playing chords.
+///
+/// Every note is converted by [`ConvertNote`], config is the same as its config.
+pub struct ConvertChord();
+
+impl Resource for ConvertChord {
+    fn orig_name(&self) -> &str {
+        "Prepare chord for playing"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_CONVERT_CHORD"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        ConvertNote().check_config(conf)
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod to prepare all notes of a chord for playing"
+    }
+
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        ConvertNote().config_schema()
+    }
+}
+
+impl Mod for ConvertChord {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let input = input
+            .as_notes()
+            .ok_or(StringError("input has to be Notes".to_string()))?;
+        let out = input
+            .iter()
+            .enumerate()
+            .map(
+                |(i, note)| match ConvertNote().apply(&ModData::Note(note.clone()), conf, &[]) {
+                    Ok((ModData::ReadyNote(x), _)) => Ok(x),
+                    Ok(_) => unreachable!(),
+                    Err(e) => Err(StringError(format!("note {i}: {}", e.0))),
+                },
+            )
+            .collect::<Result<_, _>>()?;
+        Ok((ModData::ReadyNotes(out), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Notes(Vec::new()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNotes(Vec::new()))
+    }
+}
+
+/// Mod to scale note's velocity by the channel volume.
+///
+/// Config is `[maximum volume, curve]`. Maximum volume is an integer in range
+/// [1, 255], in platform's units, and curve is either `"linear"` or `"quadratic"`.
+///
+/// State is one byte with the current volume, which cannot be higher than the
+/// maximum volume. Empty state means maximum volume.
+///
+/// Velocity is multiplied by `volume / maximum volume` with the linear curve, or by
+/// its square with the quadratic curve, and rounded to the nearest integer.
+pub struct ChannelVolume();
+
+impl ChannelVolume {
+    fn gain(volume: u8, max_volume: u8, curve: &str) -> f32 {
+        let ratio = volume as f32 / max_volume as f32;
+        match curve {
+            "quadratic" => ratio * ratio,
+            _ => ratio,
+        }
+    }
+}
+
+impl Resource for ChannelVolume {
+    fn orig_name(&self) -> &str {
+        "Channel volume"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_CHANNEL_VOLUME"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 2 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 2, got {}",
+                conf.len()
+            )));
+        }
+        if !conf[0].as_i64().is_some_and(|x| (1..=255).contains(&x)) {
+            return Err(StringError(
+                "argument 1 (maximum volume) is not integer in range [1, 255]".to_string(),
+            ));
+        }
+        match conf[1].as_str() {
+            Some("linear") | Some("quadratic") => Ok(()),
+            _ => Err(StringError(
+                "argument 2 (curve) is neither \"linear\" nor \"quadratic\"".to_string(),
+            )),
+        }
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (state.len() <= 1).then_some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that applies channel volume to note's velocity"
+    }
+}
+
+impl Mod for ChannelVolume {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        self.check_state(state)
+            .ok_or(StringError("state is longer than one byte".to_string()))?;
+        let input = input
+            .as_note()
+            .ok_or(StringError("input has to be a Note".to_string()))?;
+        let conf = conf.as_slice();
+        let max_volume = conf[0].as_i64().unwrap() as u8;
+        let volume = state.first().copied().unwrap_or(max_volume);
+        if volume > max_volume {
+            return Err(StringError(format!(
+                "volume {volume} is higher than the maximum of {max_volume}"
+            )));
+        }
+        let gain = Self::gain(volume, max_volume, conf[1].as_str().unwrap());
+        let out = Note {
+            velocity: (input.velocity as f32 * gain).round() as u8,
+            ..input.clone()
+        };
+        Ok((ModData::Note(out), state.into()))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+}
+
+/// Mod to apply a [key signature][KeySignature] to notes.
+///
+/// Config is seven integers in range [-1, 1], the accidentals of C, D, E, F, G, A
+/// and B. Notes marked as [natural][Note::natural] are not changed.
+pub struct KeySignatureMod();
+
+impl KeySignatureMod {
+    /// Construct the config from a key signature.
+    ///
+    /// Returns None if any of the accidentals is outside of range [-1, 1].
+    pub fn config_from(key: KeySignature) -> Option<ResConfig> {
+        key.accidentals
+            .iter()
+            .all(|x| (-1..=1).contains(x))
+            .then(|| JsonArray::from_values(key.accidentals.map(|x| json!(x))).unwrap())
+    }
+}
+
+impl Resource for KeySignatureMod {
+    fn orig_name(&self) -> &str {
+        "Key signature"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_KEY_SIGNATURE"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 7 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 7, got {}",
+                conf.len()
+            )));
+        }
+        for (i, x) in conf.iter().enumerate() {
+            if !x.as_i64().is_some_and(|x| (-1..=1).contains(&x)) {
+                return Err(StringError(format!(
+                    "argument {} (accidental of {}) is not integer in range [-1, 1]",
+                    i + 1,
+                    ["C", "D", "E", "F", "G", "A", "B"][i]
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that applies key signature to notes"
+    }
+}
+
+impl Mod for KeySignatureMod {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_note()
+            .ok_or(StringError("input has to be a Note".to_string()))?;
+        let mut accidentals = [0; 7];
+        for (out, x) in accidentals.iter_mut().zip(conf.as_slice()) {
+            *out = x.as_i64().unwrap() as i8;
+        }
+        let out = KeySignature::new(accidentals).apply(input);
+        Ok((ModData::Note(out), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+}
+
+/// Mod to glide from the pitch of the previous note to the pitch of the current one.
+///
+/// Every pitched note starts at the pitch of the note before it and ends at its own
+/// [pitch][Note::pitch], which is set as the [end pitch][Note::end_pitch]. The first
+/// note, and notes that already have an end pitch, are left unchanged. Rests and
+/// unpitched notes are left unchanged and do not interrupt the glide.
+///
+/// Config is empty. State is the pitch of the previous note, if there was one.
+pub struct Portamento();
+
+impl Resource for Portamento {
+    fn orig_name(&self) -> &str {
+        "Portamento"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_PORTAMENTO"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        match conf.as_slice().len() {
+            0 => Ok(()),
+            x => Err(StringError(format!(
+                "wrong number of values: expected 0, got {x}"
+            ))),
+        }
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (state.len() <= 1).then_some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that glides from the previous note's pitch"
+    }
+}
+
+impl Mod for Portamento {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        self.check_state(state)
+            .ok_or(StringError("state is longer than one byte".to_string()))?;
+        let input = input
+            .as_note()
+            .ok_or(StringError("input has to be a Note".to_string()))?;
+        let previous = state.first().map(|x| *x as i8);
+        let Some(pitch) = input.pitch else {
+            return Ok((ModData::Note(input.clone()), state.into()));
+        };
+        let out = match (previous, input.end_pitch) {
+            (Some(previous), None) if previous != pitch => Note {
+                pitch: Some(previous),
+                end_pitch: Some(pitch),
+                ..input.clone()
+            },
+            _ => input.clone(),
+        };
+        let last = input.end_pitch.unwrap_or(pitch);
+        Ok((ModData::Note(out), Box::new([last as u8])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+}
+
+/// Mod to set note's velocity from its length, so that shorter notes are louder.
+///
+/// Config is `[exponent, maximum length]`: exponent is a positive float, and maximum
+/// length is a positive float, in ticks. Velocity is set to
+/// `255 * (1 - len / maximum length)^exponent`, rounded to the nearest integer, so
+/// notes that are at least as long as the maximum length get velocity 0.
+///
+/// Notes with unspecified length are left unchanged.
+pub struct AutoVelocity();
+
+impl Resource for AutoVelocity {
+    fn orig_name(&self) -> &str {
+        "Automatic velocity"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_AUTO_VELOCITY"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 2 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 2, got {}",
+                conf.len()
+            )));
+        }
+        let positive = |x: &serde_json::Value| x.as_f64().is_some_and(|x| x.is_finite() && x > 0.0);
+        if !positive(&conf[0]) {
+            return Err(StringError(
+                "argument 1 (exponent) is not positive float".to_string(),
+            ));
+        }
+        if !positive(&conf[1]) {
+            return Err(StringError(
+                "argument 2 (maximum length) is not positive float".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that makes shorter notes louder"
+    }
+}
+
+impl Mod for AutoVelocity {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_note()
+            .ok_or(StringError("input has to be a Note".to_string()))?;
+        let conf = conf.as_slice();
+        let exponent = conf[0].as_f64().unwrap();
+        let max_len = conf[1].as_f64().unwrap();
+        let velocity = match input.len {
+            Some(len) => {
+                let shortness = 1.0 - (len.ticks() as f64 / max_len).min(1.0);
+                (255.0 * shortness.powf(exponent)).round() as u8
+            }
+            None => input.velocity,
+        };
+        let out = Note {
+            velocity,
+            ..input.clone()
+        };
+        Ok((ModData::Note(out), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Note(Note::default()))
+    }
+}
+
+/// Mod to set release velocity of a ReadyNote.
+///
+/// Config is `[release velocity]`, which is either an integer in range [0, 255] that
+/// is set on every note, or `null`, in which case release velocity is derived from
+/// note's gate (the fraction of the sound during which the key is held):
+/// `round(255 * len / (len + decay_time))`. A note without decay time is cut
+/// immediately, and the longer the decay is, the slower the release.
+pub struct ReleaseShaper();
+
+impl Resource for ReleaseShaper {
+    fn orig_name(&self) -> &str {
+        "Release shaper"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_RELEASE_SHAPER"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 1 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 1, got {}",
+                conf.len()
+            )));
+        }
+        match conf[0].is_null() || conf[0].as_u64().is_some_and(|x| x <= 255) {
+            true => Ok(()),
+            false => Err(StringError(
+                "argument 1 (release velocity) is neither null nor integer in range [0, 255]"
+                    .to_string(),
+            )),
+        }
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that sets release velocity of the note"
+    }
+}
+
+impl Mod for ReleaseShaper {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_ready_note()
+            .ok_or(StringError("input has to be a ReadyNote".to_string()))?;
+        let release_velocity = match conf.as_slice()[0].as_u64() {
+            Some(x) => x as u8,
+            None => {
+                let total = input.len + input.decay_time;
+                match total > 0.0 {
+                    true => (255.0 * (input.len / total).clamp(0.0, 1.0)).round() as u8,
+                    false => 255,
+                }
+            }
+        };
+        let out = ReadyNote {
+            release_velocity: Some(release_velocity),
+            ..input.clone()
+        };
+        Ok((ModData::ReadyNote(out), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+}
+
+/// Mod to convert a string with frequency into ReadyNote, for playing drone notes.
+///
+/// Accepted strings are either frequency in Hz, like `"440.0"`, or note name with
+/// octave in scientific pitch notation, like `"A4"`, `"C#5"`, `"Bb3"` or `"C-1"`.
+///
+/// Config is `[duration in seconds, decay in seconds, frequency of A4]`, all floats.
+pub struct FrequencyParser();
+
+impl FrequencyParser {
+    fn parse(input: &str, a4: f64) -> Result<f64, StringError> {
+        let input = input.trim();
+        if let Ok(hz) = input.parse::<f64>() {
+            return match hz.is_finite() && hz > 0.0 {
+                true => Ok(hz),
+                false => Err(StringError(format!("frequency {hz} is not positive"))),
+            };
+        }
+
+        let bad_name = || StringError(format!("\"{input}\" is neither a frequency nor a note"));
+        let mut chars = input.chars();
+        //Semitones relative to A
+        let letter = match chars.next().ok_or_else(bad_name)?.to_ascii_uppercase() {
+            'C' => -9,
+            'D' => -7,
+            'E' => -5,
+            'F' => -4,
+            'G' => -2,
+            'A' => 0,
+            'B' => 2,
+            _ => return Err(bad_name()),
+        };
+        let rest = chars.as_str();
+        let (accidental, octave) = match rest.chars().next() {
+            Some('#') => (1, &rest[1..]),
+            Some('b') => (-1, &rest[1..]),
+            _ => (0, rest),
+        };
+        let octave: i32 = octave.parse().map_err(|_| bad_name())?;
+        let out_of_range = || StringError(format!("octave of \"{input}\" is out of range"));
+        let semitones = octave
+            .checked_sub(4)
+            .and_then(|x| x.checked_mul(12))
+            .and_then(|x| x.checked_add(letter + accidental))
+            .ok_or_else(out_of_range)?;
+        let hz = a4 * 2.0_f64.powf(semitones as f64 / 12.0);
+        match hz.is_normal() {
+            true => Ok(hz),
+            false => Err(out_of_range()),
+        }
+    }
+}
+
+impl Resource for FrequencyParser {
+    fn orig_name(&self) -> &str {
+        "Frequency parser"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_FREQUENCY_PARSER"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        fn to_result(input: bool, msg: String) -> Result<(), StringError> {
+            match input {
+                true => Ok(()),
+                false => Err(StringError(msg)),
+            }
+        }
+
+        let conf = conf.as_slice();
+
+        to_result(conf.len() == 3, "incorrect config length".to_string())?;
+        to_result(
+            conf[0].as_f64().is_some_and(|x| x >= 0.0),
+            "argument 1 (duration) is not nonnegative float".to_string(),
+        )?;
+        to_result(
+            conf[1].as_f64().is_some_and(|x| x >= 0.0),
+            "argument 2 (decay) is not nonnegative float".to_string(),
+        )?;
+        to_result(
+            conf[2].as_f64().is_some_and(|x| x > 0.0),
+            "argument 3 (frequency of A4) is not positive float".to_string(),
+        )?;
+        Ok(())
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod to create a note from frequency or note name"
+    }
+}
+
+impl Mod for FrequencyParser {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_string()
+            .ok_or(StringError("input has to be a String".to_string()))?;
+        let conf = conf.as_slice();
+        let pitch = Self::parse(input, conf[2].as_f64().unwrap())?;
+
+        let out = ReadyNote {
+            len: conf[0].as_f64().unwrap() as f32,
+            decay_time: conf[1].as_f64().unwrap() as f32,
+            pitch: Some(pitch as f32),
+            velocity: 128,
+            ..Default::default()
+        };
+        Ok((ModData::ReadyNote(out), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::String(String::new()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+}
+
+/// Mod to quantize a sound to the resolution of a chip's DAC.
+///
+/// Config is `[bit depth]`, an integer in range [2, 32]. See [`Sound::quantize`].
+pub struct Quantize();
+
+impl Resource for Quantize {
+    fn orig_name(&self) -> &str {
+        "Quantize"
+    }
+
+    fn id(&self) -> &str {
+        "BUILTIN_QUANTIZE"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        if conf.len() != 1 {
+            return Err(StringError(format!(
+                "wrong number of values: expected 1, got {}",
+                conf.len()
+            )));
+        }
+        match conf[0].as_u64().is_some_and(|x| (2..=32).contains(&x)) {
+            true => Ok(()),
+            false => Err(StringError(
+                "argument 1 (bit depth) is not integer in range [2, 32]".to_string(),
+            )),
+        }
+    }
+
+    //No state
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Built-in mod that quantizes sound to the given bit depth"
+    }
+}
+
+impl Mod for Quantize {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        self.check_config(conf)?;
+        let input = input
+            .as_sound()
+            .ok_or(StringError("input has to be a Sound".to_string()))?;
+        let bits = conf.as_slice()[0].as_u64().unwrap() as u8;
+        Ok((ModData::Sound(input.quantize(bits)?), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::TickDuration;
+
+    use super::*;
+
+    fn convert(ticks: u32, conf: serde_json::Value) -> Result<ReadyNote, StringError> {
+        let note = Note {
+            len: TickDuration::new(ticks),
+            ..Default::default()
+        };
+        let conf = JsonArray::from_value(conf).unwrap();
+        let (out, _) = ConvertNote().apply(&ModData::Note(note), &conf, &[])?;
+        Ok(out.as_ready_note().unwrap().clone())
+    }
+
+    #[test]
+    fn convert_note_params_keep_the_order() {
+        let conf = ConvertNote::config_from(ConvertNoteParams {
+            c_neg1_hz: 8.1758,
+            tick_length: 0.125,
+            octave: 4,
+            post_release_ticks: 2,
+            added_cents: -5,
+        });
+        assert_eq!(conf.as_byte_vec(), br#"[8.1758,0.125,4,2,-5]"#);
+        assert!(ConvertNote().check_config(&conf).is_ok());
+    }
+
+    #[test]
+    fn convert_note_maps_semitones_from_c() {
+        let conf = JsonArray::from_value(json!([8.1758, 0.125, 4, 0, 0])).unwrap();
+        let hz = |pitch: Option<i8>| {
+            let note = Note {
+                len: TickDuration::new(1),
+                pitch,
+                ..Default::default()
+            };
+            let (out, _) = ConvertNote()
+                .apply(&ModData::Note(note), &conf, &[])
+                .unwrap();
+            out.as_ready_note().unwrap().pitch
+        };
+        let close = |pitch: i8, expected: f32| {
+            let x = hz(Some(pitch)).unwrap();
+            assert!((x - expected).abs() < 0.01, "{pitch}: {x} Hz");
+        };
+        // C4, the reference note itself
+        close(0, 261.63);
+        close(9, 440.0);
+        close(12, 523.25);
+        close(-1, 246.94);
+        close(-12, 130.81);
+        close(-48, 16.35);
+        assert_eq!(hz(None), None);
+    }
+
+    #[test]
+    fn convert_note_names_bad_arguments() {
+        let err = convert(1, json!([8.1758, "fast", 4, 0, 0])).unwrap_err();
+        assert_eq!(
+            err.0,
+            "argument 2 (length of one tick) is string, expected number"
+        );
+        let err = convert(1, json!([8.1758, 0.125, 4.5, 0, 0])).unwrap_err();
+        assert_eq!(err.0, "argument 3 (octave) is not an integer");
+    }
+
+    #[test]
+    fn convert_note_rejects_huge_tick_length() {
+        assert!(convert(1, json!([8.1758, 1.0e30, 4, 0, 0])).is_err());
+        assert!(convert(1, json!([8.1758, f64::MAX, 4, 0, 0])).is_err());
+        assert!(convert(1, json!([8.1758, -0.5, 4, 0, 0])).is_err());
+    }
+
+    #[test]
+    fn convert_note_plays_dotted_whole_note() {
+        let dotted = TickDuration::from_fraction(192, 1, 1).unwrap();
+        assert_eq!(dotted.ticks(), 288);
+        // 128 ticks per second
+        let note = convert(dotted.ticks(), json!([8.1758, 0.0078125, 4, 0, 0])).unwrap();
+        assert_eq!(note.len, 2.25);
+    }
+
+    #[test]
+    fn convert_note_limits_note_length() {
+        // 60 ticks of 10 seconds and 1 tick of decay are longer than 10 minutes
+        let err = convert(60, json!([8.1758, 10.0, 4, 1, 0])).unwrap_err();
+        assert!(err.0.contains("610"));
+        // Unless the limit is raised
+        let pad = convert(60, json!([8.1758, 10.0, 4, 1, 0, 1200.0])).unwrap();
+        assert_eq!(pad.len, 600.0);
+        assert_eq!(pad.decay_time, 10.0);
+    }
+
+    fn volume(curve: &str, state: &ResState) -> Result<u8, StringError> {
+        let note = Note {
+            velocity: 200,
+            ..Default::default()
+        };
+        let conf = JsonArray::from_values([json!(15), json!(curve)]).unwrap();
+        let (out, new_state) = ChannelVolume().apply(&ModData::Note(note), &conf, state)?;
+        assert_eq!(&*new_state, state);
+        Ok(out.as_note().unwrap().velocity)
+    }
+
+    #[test]
+    fn channel_volume_scales_velocity() {
+        assert_eq!(volume("linear", &[]).unwrap(), 200);
+        assert_eq!(volume("linear", &[15]).unwrap(), 200);
+        assert_eq!(volume("linear", &[6]).unwrap(), 80);
+        assert_eq!(volume("quadratic", &[6]).unwrap(), 32);
+        assert_eq!(volume("linear", &[0]).unwrap(), 0);
+        assert!(volume("linear", &[16]).is_err());
+        assert!(volume("cubic", &[6]).is_err());
+    }
+
+    fn auto_velocity(exponent: f64, len: u8) -> u8 {
+        let note = Note {
+            len: TickDuration::new(len as u32),
+            velocity: 77,
+            ..Default::default()
+        };
+        let conf = JsonArray::from_values([json!(exponent), json!(16.0)]).unwrap();
+        let (out, _) = AutoVelocity()
+            .apply(&ModData::Note(note), &conf, &[])
+            .unwrap();
+        out.as_note().unwrap().velocity
+    }
+
+    #[test]
+    fn auto_velocity_makes_short_notes_louder() {
+        assert_eq!(auto_velocity(1.0, 4), 191);
+        assert_eq!(auto_velocity(2.0, 4), 143);
+        assert_eq!(auto_velocity(1.0, 16), 0);
+        assert_eq!(auto_velocity(1.0, 32), 0);
+        assert!(auto_velocity(1.0, 1) > auto_velocity(1.0, 2));
+        // Unknown length keeps the velocity
+        assert_eq!(auto_velocity(1.0, 0), 77);
+    }
+
+    fn shape(conf: serde_json::Value, len: f32, decay_time: f32) -> Option<u8> {
+        let note = ReadyNote {
+            len,
+            decay_time,
+            ..Default::default()
+        };
+        let conf = JsonArray::from_values([conf]).unwrap();
+        let (out, _) = ReleaseShaper()
+            .apply(&ModData::ReadyNote(note), &conf, &[])
+            .unwrap();
+        out.as_ready_note().unwrap().release_velocity
+    }
+
+    #[test]
+    fn release_shaper_sets_release_velocity() {
+        assert_eq!(shape(json!(17), 1.0, 1.0), Some(17));
+        assert_eq!(shape(json!(null), 1.0, 0.0), Some(255));
+        assert_eq!(shape(json!(null), 1.0, 1.0), Some(128));
+        assert_eq!(shape(json!(null), 0.0, 1.0), Some(0));
+        assert_eq!(shape(json!(null), 0.0, 0.0), Some(255));
+        let conf = JsonArray::from_values([json!(256)]).unwrap();
+        assert!(ReleaseShaper().check_config(&conf).is_err());
+    }
+
+    fn parse(input: &str) -> Result<f32, StringError> {
+        let conf = JsonArray::from_values([json!(1.0), json!(0.5), json!(440.0)]).unwrap();
+        let (out, _) = FrequencyParser().apply(&ModData::String(input.to_string()), &conf, &[])?;
+        Ok(out.as_ready_note().unwrap().pitch.unwrap())
+    }
+
+    #[test]
+    fn frequency_parser_accepts_frequencies() {
+        assert_eq!(parse("261.5").unwrap(), 261.5);
+        assert!(parse("-5").is_err());
+    }
+
+    #[test]
+    fn frequency_parser_accepts_note_names() {
+        assert_eq!(parse("A4").unwrap(), 440.0);
+        assert_eq!(parse("A5").unwrap(), 880.0);
+        assert!((parse("C4").unwrap() - 261.6256).abs() < 0.001);
+        assert!((parse("C#4").unwrap() - 277.1826).abs() < 0.001);
+        assert!((parse("Db4").unwrap() - 277.1826).abs() < 0.001);
+        assert!((parse("C-1").unwrap() - 8.1758).abs() < 0.001);
+    }
+
+    #[test]
+    fn frequency_parser_rejects_garbage() {
+        assert!(parse("H4").is_err());
+        assert!(parse("A").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn frequency_parser_rejects_huge_octaves() {
+        assert!(parse("A999999999").is_err());
+        assert!(parse("C-2147483648").is_err());
+        assert!(parse("A1100").is_err());
+    }
+
+    #[test]
+    fn quantize_reduces_resolution() {
+        let sound = Sound::new(Box::new([[0.3, -0.3], [1.0, -1.0]]), 48000);
+        let conf = JsonArray::from_value(json!([2])).unwrap();
+        let (out, _) = Quantize()
+            .apply(&ModData::Sound(sound), &conf, &[])
+            .unwrap();
+        assert_eq!(out.as_sound().unwrap().data(), &[[0.0, 0.0], [0.5, -1.0]]);
+        for bits in [json!([1]), json!([33]), json!([8.5]), json!([])] {
+            let conf = JsonArray::from_value(bits).unwrap();
+            assert!(Quantize().check_config(&conf).is_err());
+        }
+    }
+
+    #[test]
+    fn key_signature_mod_applies_accidentals() {
+        let conf = KeySignatureMod::config_from(KeySignature::from_sharps_flats(2)).unwrap();
+        assert_eq!(conf.as_byte_vec(), br#"[1,0,0,1,0,0,0]"#);
+        let play = |note: Note| {
+            let (out, _) = KeySignatureMod()
+                .apply(&ModData::Note(note), &conf, &[])
+                .unwrap();
+            out.as_note().unwrap().pitch
+        };
+        let c = Note {
+            pitch: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(play(c.clone()), Some(1));
+        assert_eq!(
+            play(Note {
+                natural: true,
+                ..c.clone()
+            }),
+            Some(0)
+        );
+        assert_eq!(play(Note::rest(None)), None);
+
+        assert!(KeySignatureMod::config_from(KeySignature::new([2, 0, 0, 0, 0, 0, 0])).is_none());
+        for bad in [json!([0, 0, 0, 0, 0, 0]), json!([0, 0, 0, 0, 0, 0, -2])] {
+            let conf = JsonArray::from_value(bad).unwrap();
+            assert!(KeySignatureMod().check_config(&conf).is_err());
+        }
+    }
+
+    #[test]
+    fn portamento_glides_from_previous_pitch() {
+        let conf = JsonArray::new();
+        let mut state: Box<ResState> = Box::new([]);
+        let mut play = |note: Note| {
+            let (out, new_state) = Portamento()
+                .apply(&ModData::Note(note), &conf, &state)
+                .unwrap();
+            state = new_state;
+            let out = out.as_note().unwrap().clone();
+            (out.pitch, out.end_pitch)
+        };
+        let note = |pitch| Note {
+            pitch,
+            ..Default::default()
+        };
+        assert_eq!(play(note(Some(0))), (Some(0), None));
+        assert_eq!(play(note(Some(7))), (Some(0), Some(7)));
+        assert_eq!(play(note(None)), (None, None));
+        assert_eq!(play(note(Some(-5))), (Some(7), Some(-5)));
+        assert_eq!(play(note(Some(-5))), (Some(-5), None));
+        assert!(Portamento()
+            .apply(&ModData::Note(note(Some(0))), &conf, &[0, 0])
+            .is_err());
+    }
+}
@@ -1,40 +1,80 @@
 //! Builder for configurations, represented as flat [JSON arrays][crate::resource::JsonArray],
 //! that uses a schema.
 
-use std::mem::{discriminant, Discriminant};
+use std::{borrow::Borrow, collections::BTreeMap};
 
 use thiserror::Error;
 
-use crate::resource::{JsonValue, ResConfig};
+use crate::resource::{ConfigError, ConfigSchema, JsonType, JsonValue, ResConfig};
 
 /// Errors that [`ConfigBuilder`] can produce.
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum ConfigBuilderError {
-    //TODO: change from displaying discriminant to displaying a type
     /// Provided type does not match the type defined in the schema.
-    #[error("type mismatch at {0}: expected {1:?}, got {2:?}")]
-    TypeMismatch(usize, Discriminant<JsonValue>, Discriminant<JsonValue>),
+    #[error("type mismatch at {0}: expected {1}, got {2}")]
+    TypeMismatch(usize, JsonType, JsonType),
 
     /// Extra value is supplied to a configuration that is already fully built.
     #[error("value outside schema")]
     ValueOutsideSchema,
+
+    /// There is no value with this name in the schema, or the schema has no names.
+    #[error("unknown value name: {0}")]
+    UnknownName(String),
+
+    /// Configuration is not finished. Contains the expected types of the values that
+    /// are missing.
+    #[error("configuration is unfinished: {} values are missing ({})", .0.len(), .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Unfinished(Vec<JsonType>),
+
+    /// A value is not set and the schema has no default for it.
+    #[error("no value or default at {0}")]
+    NoDefault(usize),
+
+    /// A value without a default is not given, reported by name.
+    #[error("missing value: {0}")]
+    MissingName(String),
+
+    /// Text could not be parsed into named values.
+    #[error("could not parse the config: {0}")]
+    Parse(String),
+
+    /// Value does not fit the [named schema][ConfigSchema].
+    #[error(transparent)]
+    Schema(#[from] ConfigError),
+}
+
+/// Result of [`ConfigBuilder::inject()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InjectOutcome {
+    /// Number of values that were appended to the configuration.
+    pub appended: usize,
+
+    /// Whether the configuration is fully built.
+    pub finished: bool,
 }
 
 /// State of [`ConfigBuilder`] in which the config is not fully built yet.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConfBuilding<'a> {
     /// Schema against which the configuration is being built.
     schema: &'a ResConfig,
 
+    /// Named schema that gives the values names and ranges, if there is one.
+    named: Option<&'a ConfigSchema>,
+
     /// Configuration that is being built.
     config: ResConfig,
+
+    /// Values that were set by name ahead of the end of the configuration.
+    pending: BTreeMap<usize, JsonValue>,
 }
 
 /// Configuration builder.
 ///
 /// Validates all provided values and their count against the schema, making sure
 /// that the types match.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ConfigBuilder<'a> {
     /// Configuration is still being built.
     Builder(ConfBuilding<'a>),
@@ -51,16 +91,85 @@ impl<'a> ConfigBuilder<'a> {
         } else {
             return ConfigBuilder::Builder(ConfBuilding {
                 schema,
+                named: None,
                 config: ResConfig::new(),
+                pending: BTreeMap::new(),
             });
         }
     }
 
+    /// Create new [`ConfigBuilder`] from a named schema.
+    ///
+    /// Besides types, values are checked against the ranges of the schema, and
+    /// can be set by name with [`set_by_name()`][ConfigBuilder::set_by_name()].
+    pub fn with_schema(schema: &'a ConfigSchema) -> ConfigBuilder<'a> {
+        match ConfigBuilder::new(schema.positional()) {
+            ConfigBuilder::Builder(build) => ConfigBuilder::Builder(ConfBuilding {
+                named: Some(schema),
+                ..build
+            }),
+            conf => conf,
+        }
+    }
+
+    /// Set the value called `name`, in any order, and return whether the
+    /// configuration is finished. Values that were already set are replaced.
+    ///
+    /// # Errors
+    ///
+    /// If the configuration had already been built,
+    /// [`ValueOutsideSchema`][ConfigBuilderError::ValueOutsideSchema] is returned.
+    ///
+    /// If the builder was not created [with a named schema][ConfigBuilder::with_schema()]
+    /// or the schema has no such value,
+    /// [`UnknownName`][ConfigBuilderError::UnknownName] is returned.
+    ///
+    /// If the value has a wrong type or is outside of its range,
+    /// [`Schema`][ConfigBuilderError::Schema] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::resource::{ConfigField, ConfigSchema, FieldType};
+    /// # fn main() -> Result<(), ConfigBuilderError> {
+    /// let schema = ConfigSchema::new(vec![
+    ///     ConfigField::new("octave", FieldType::Integer, "").with_range(0.0, 10.0),
+    ///     ConfigField::new("name", FieldType::String, ""),
+    /// ]);
+    /// let mut builder = ConfigBuilder::with_schema(&schema);
+    /// assert!(!builder.set_by_name("name", &json!("lead"))?);
+    /// assert!(builder.set_by_name("octave", &json!(11)).is_err());
+    /// assert!(builder.set_by_name("octave", &json!(4))?);
+    /// assert!(builder.is_config());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_by_name(
+        &mut self,
+        name: &str,
+        value: &JsonValue,
+    ) -> Result<bool, ConfigBuilderError> {
+        let ConfigBuilder::Builder(build) = self else {
+            return Err(ConfigBuilderError::ValueOutsideSchema);
+        };
+        let finished = build.set_by_name(name, value)?;
+        if finished {
+            *self = ConfigBuilder::Config(std::mem::take(&mut build.config));
+        }
+        Ok(finished)
+    }
+
     /// Append items from a given source of JSON values to the configuration that is being built
-    /// and returns the number of appended values.
+    /// and returns how many values were appended and whether the configuration is finished.
     ///
     /// The function finishes when the configuration is finished building, all items
-    /// were used, or an error occurs.
+    /// were used, or an error occurs. Items after the one that finished the
+    /// configuration are not used, and their number is the length of `values`
+    /// minus [`appended`][InjectOutcome::appended]. Use
+    /// [`inject_owned()`][ConfigBuilder::inject_owned()] to get them back, or
+    /// [`inject_exact()`][ConfigBuilder::inject_exact()] to treat them as an error.
     ///
     /// # Errors
     ///
@@ -77,16 +186,17 @@ impl<'a> ConfigBuilder<'a> {
     ///
     /// ```
     /// # use serde_json::{json, Value};
-    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError, InjectOutcome};
     /// # use mleml::resource::{ResConfig, JsonArray};
     /// # fn main() -> Result<(), ConfigBuilderError> {
     /// let schema: ResConfig = ResConfig::from_value(json!([5, "six"])).expect("failed to create resource config");
     /// let mut builder: ConfigBuilder = ConfigBuilder::new(&schema);
-    /// let source: JsonArray = JsonArray::from_value(json!([12, "lime"])).expect("failed to create JSON array");
+    /// let source: JsonArray = JsonArray::from_value(json!([12, "lime", "extra"])).expect("failed to create JSON array");
     ///
-    /// // Number of values that were taken from the source
-    /// let taken: usize = builder.inject(source.as_slice())?;
-    /// assert_eq!(taken, 2);
+    /// // Two values were taken from the source and the config is finished,
+    /// // so the third value was not used
+    /// let outcome: InjectOutcome = builder.inject(source.as_slice())?;
+    /// assert_eq!(outcome, InjectOutcome { appended: 2, finished: true });
     ///
     /// // Finished config is taken from the builder
     /// let config: ResConfig = match builder {
@@ -97,33 +207,117 @@ impl<'a> ConfigBuilder<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    //TODO: current approach silently discards values that did not fit but
-    //returns error on attempt to append to a finished config.
-    //Should it return error on extra values always? Or should it return Ok(0)?
-    pub fn inject<T>(&mut self, values: T) -> Result<usize, ConfigBuilderError>
+    pub fn inject<T>(&mut self, values: T) -> Result<InjectOutcome, ConfigBuilderError>
     where
         T: AsRef<[JsonValue]>,
+    {
+        self.inject_iter(&mut values.as_ref().iter())
+    }
+
+    /// Append all items to the configuration, like [`inject()`][ConfigBuilder::inject()],
+    /// but fail if any of them are left over after the configuration is finished.
+    ///
+    /// The number of items is checked against [`remaining()`][ConfigBuilder::remaining()]
+    /// before anything is appended.
+    ///
+    /// # Errors
+    ///
+    /// If there are more items than the configuration has room for,
+    /// [`ValueOutsideSchema`][crate::extra::config_builder::ConfigBuilderError::ValueOutsideSchema]
+    /// is returned and the builder is not changed. Other errors are the same as in
+    /// [`inject()`][ConfigBuilder::inject()].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::resource::ResConfig;
+    /// let schema = ResConfig::from_value(json!([5, "six"])).expect("failed to create resource config");
+    /// let mut builder = ConfigBuilder::new(&schema);
+    /// let result = builder.inject_exact([json!(12), json!("lime"), json!("extra")]);
+    /// assert_eq!(result, Err(ConfigBuilderError::ValueOutsideSchema));
+    /// assert!(builder.is_builder());
+    /// assert!(builder.inject_exact([json!(12), json!("lime")]).is_ok());
+    /// ```
+    pub fn inject_exact<T>(&mut self, values: T) -> Result<InjectOutcome, ConfigBuilderError>
+    where
+        T: AsRef<[JsonValue]>,
+    {
+        let values = values.as_ref();
+        if values.len() > self.remaining() {
+            return Err(ConfigBuilderError::ValueOutsideSchema);
+        }
+        self.inject(values)
+    }
+
+    /// Append items from an iterator, like [`inject()`][ConfigBuilder::inject()], and
+    /// return the iterator with the items that were not used.
+    ///
+    /// This allows building several configurations from one stream of values.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`inject()`][ConfigBuilder::inject()].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::resource::ResConfig;
+    /// # fn main() -> Result<(), ConfigBuilderError> {
+    /// let first_schema = ResConfig::from_value(json!([5, "six"])).expect("failed to create resource config");
+    /// let second_schema = ResConfig::from_value(json!([true])).expect("failed to create resource config");
+    /// let mut first = ConfigBuilder::new(&first_schema);
+    /// let mut second = ConfigBuilder::new(&second_schema);
+    ///
+    /// let stream = vec![json!(1), json!("two"), json!(false)];
+    /// let (_, rest) = first.inject_owned(stream)?;
+    /// let (outcome, mut rest) = second.inject_owned(rest)?;
+    /// assert!(first.is_config() && second.is_config());
+    /// assert_eq!(outcome.appended, 1);
+    /// assert!(rest.next().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inject_owned<I>(
+        &mut self,
+        values: I,
+    ) -> Result<(InjectOutcome, I::IntoIter), ConfigBuilderError>
+    where
+        I: IntoIterator<Item = JsonValue>,
+    {
+        let mut values = values.into_iter();
+        let outcome = self.inject_iter(&mut values)?;
+        Ok((outcome, values))
+    }
+
+    fn inject_iter<V, I>(&mut self, values: &mut I) -> Result<InjectOutcome, ConfigBuilderError>
+    where
+        V: Borrow<JsonValue>,
+        I: Iterator<Item = V>,
     {
         if let ConfigBuilder::Config(_) = self {
             return Err(ConfigBuilderError::ValueOutsideSchema);
         }
-        let mut values = values.as_ref().iter();
-        let mut count = 0;
+        let mut appended = 0;
         while let ConfigBuilder::Builder(build) = self {
-            let val = values.next();
-            match val.is_none() {
-                true => return Ok(count),
-                false => {
-                    count += 1;
-                    match build.append(val.unwrap())? {
-                        //TODO: figure out if this is expensive
-                        true => *self = ConfigBuilder::Config(build.config.to_owned()),
-                        false => continue,
-                    }
-                }
+            let Some(val) = values.next() else {
+                return Ok(InjectOutcome {
+                    appended,
+                    finished: false,
+                });
+            };
+            appended += 1;
+            if build.append(val.borrow())? {
+                *self = ConfigBuilder::Config(std::mem::take(&mut build.config));
             }
         }
-        Ok(count)
+        Ok(InjectOutcome {
+            appended,
+            finished: true,
+        })
     }
 
     /// If the configuration is unfinished, checks and appends one item to it.
@@ -165,6 +359,110 @@ impl<'a> ConfigBuilder<'a> {
         }
     }
 
+    /// Finish the configuration, filling the values that were not set with the
+    /// [defaults][crate::resource::ConfigField::default] of the named schema.
+    ///
+    /// # Errors
+    ///
+    /// If a value is not set and has no default (which is always the case if the
+    /// builder was not created [with a named schema][ConfigBuilder::with_schema()]),
+    /// [`NoDefault`][ConfigBuilderError::NoDefault] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::resource::{ConfigField, ConfigSchema, FieldType};
+    /// # fn main() -> Result<(), ConfigBuilderError> {
+    /// let schema = ConfigSchema::new(vec![
+    ///     ConfigField::new("octave", FieldType::Integer, "").with_default(json!(4)),
+    ///     ConfigField::new("name", FieldType::String, ""),
+    /// ]);
+    /// let mut builder = ConfigBuilder::with_schema(&schema);
+    /// builder.set_by_name("name", &json!("lead"))?;
+    /// let conf = builder.finish_with_defaults()?;
+    /// assert_eq!(conf.as_slice(), [json!(4), json!("lead")]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn finish_with_defaults(self) -> Result<ResConfig, ConfigBuilderError> {
+        let mut build = match self {
+            ConfigBuilder::Config(conf) => return Ok(conf),
+            ConfigBuilder::Builder(build) => build,
+        };
+        while !build.is_finished() {
+            let position = build.config.len();
+            let value = build
+                .named
+                .and_then(|x| x.fields()[position].default.clone())
+                .ok_or(ConfigBuilderError::NoDefault(position))?;
+            build.config.push(value).unwrap();
+            build.take_pending();
+        }
+        Ok(build.config)
+    }
+
+    /// Take the finished configuration.
+    ///
+    /// # Errors
+    ///
+    /// If the configuration is not finished,
+    /// [`Unfinished`][ConfigBuilderError::Unfinished] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::extra::config_builder::{ConfigBuilder, ConfigBuilderError};
+    /// # use mleml::resource::{JsonType, ResConfig};
+    /// let schema = ResConfig::from_value(json!([5, "six"])).expect("failed to create resource config");
+    /// let mut builder = ConfigBuilder::new(&schema);
+    /// builder.append(&json!(12)).expect("value does not match the schema");
+    /// assert_eq!(builder.remaining(), 1);
+    /// let err = builder.try_finish().unwrap_err();
+    /// assert_eq!(err, ConfigBuilderError::Unfinished(vec![JsonType::String]));
+    /// ```
+    pub fn try_finish(self) -> Result<ResConfig, ConfigBuilderError> {
+        match self {
+            ConfigBuilder::Config(conf) => Ok(conf),
+            //append() leaves a finished configuration in the builder
+            ConfigBuilder::Builder(build) if build.is_finished() => Ok(build.config),
+            ConfigBuilder::Builder(build) => Err(ConfigBuilderError::Unfinished(
+                (build.config.len()..build.schema.len())
+                    .filter(|x| !build.pending.contains_key(x))
+                    .map(|x| JsonType::from(&build.schema.as_slice()[x]))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Get the number of values that are needed to finish the configuration.
+    pub fn remaining(&self) -> usize {
+        match self {
+            ConfigBuilder::Config(_) => 0,
+            ConfigBuilder::Builder(build) => {
+                build.schema.len() - build.config.len() - build.pending.len()
+            }
+        }
+    }
+
+    /// Remove all values and start building the configuration again, keeping the
+    /// allocated memory.
+    ///
+    /// Finished configuration does not keep its schema, so it can not be reset.
+    /// Returns `false` if the configuration was finished, leaving it unchanged.
+    pub fn reset(&mut self) -> bool {
+        match self {
+            ConfigBuilder::Config(_) => false,
+            ConfigBuilder::Builder(build) => {
+                build.config.clear();
+                build.pending.clear();
+                true
+            }
+        }
+    }
+
     /// Returns `true` if the config builder is [`Builder`].
     ///
     /// [`Builder`]: ConfigBuilder::Builder
@@ -188,24 +486,129 @@ impl<'a> ConfBuilding<'a> {
             return Err(ConfigBuilderError::ValueOutsideSchema);
         }
         let position = self.config.as_slice().len();
-        let current_type = discriminant(&self.schema.as_slice()[position]);
-        let given_type = discriminant(value);
-        if current_type != given_type {
+        let current_type = JsonType::from(&self.schema.as_slice()[position]);
+        if current_type != JsonType::from(value) {
             return Err(ConfigBuilderError::TypeMismatch(
                 position,
                 current_type,
-                given_type,
+                JsonType::from(value),
             ));
         };
+        if let Some(named) = self.named {
+            named.check_value(position, value)?;
+        }
         self.config.push(value.clone()).unwrap();
-        if position == self.schema.as_slice().len() - 1 {
-            Ok(true)
-        } else {
-            Ok(false)
+        self.take_pending();
+        Ok(self.is_finished())
+    }
+
+    fn set_by_name(&mut self, name: &str, value: &JsonValue) -> Result<bool, ConfigBuilderError> {
+        let named = self
+            .named
+            .ok_or(ConfigBuilderError::UnknownName(name.to_string()))?;
+        let position = named
+            .position(name)
+            .ok_or(ConfigBuilderError::UnknownName(name.to_string()))?;
+        named.check_value(position, value)?;
+        match position.cmp(&self.config.len()) {
+            std::cmp::Ordering::Less => {
+                self.config.remove(position);
+                self.config.insert(position, value.clone()).unwrap();
+            }
+            std::cmp::Ordering::Equal => {
+                self.config.push(value.clone()).unwrap();
+                self.take_pending();
+            }
+            std::cmp::Ordering::Greater => {
+                self.pending.insert(position, value.clone());
+            }
         }
+        Ok(self.is_finished())
+    }
+
+    /// Move values that were set by name into the configuration, as long as they
+    /// directly follow its end.
+    fn take_pending(&mut self) {
+        while let Some(value) = self.pending.remove(&self.config.len()) {
+            self.config.push(value).unwrap();
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.config.len() == self.schema.len()
     }
 }
 
+/// Build a config from a TOML table that maps value names to values.
+///
+/// Values that are not in the table are taken from the defaults of the schema.
+///
+/// # Errors
+///
+/// Returns [`Parse`][ConfigBuilderError::Parse] if the text is not a TOML table,
+/// [`UnknownName`][ConfigBuilderError::UnknownName] for a key that is not in the
+/// schema, [`MissingName`][ConfigBuilderError::MissingName] for a value that is
+/// neither in the table nor has a default, and
+/// [`Schema`][ConfigBuilderError::Schema] for a value that does not fit the schema.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::config_builder::from_toml_table;
+/// # use mleml::resource::{ConfigField, ConfigSchema, FieldType};
+/// # use serde_json::json;
+/// let schema = ConfigSchema::new(vec![
+///     ConfigField::new("octave", FieldType::Integer, "").with_default(json!(4)),
+///     ConfigField::new("name", FieldType::String, ""),
+/// ]);
+/// let conf = from_toml_table(&schema, r#"name = "lead""#).unwrap();
+/// assert_eq!(conf.as_slice(), [json!(4), json!("lead")]);
+/// ```
+#[cfg(feature = "config-formats")]
+pub fn from_toml_table(
+    schema: &ConfigSchema,
+    table: &str,
+) -> Result<ResConfig, ConfigBuilderError> {
+    let table: serde_json::Map<String, JsonValue> =
+        toml::from_str(table).map_err(|e| ConfigBuilderError::Parse(e.to_string()))?;
+    from_named_values(schema, table)
+}
+
+/// Build a config from a YAML mapping of value names to values.
+///
+/// # Errors
+///
+/// Same as [`from_toml_table()`].
+#[cfg(feature = "config-formats")]
+pub fn from_yaml_mapping(
+    schema: &ConfigSchema,
+    mapping: &str,
+) -> Result<ResConfig, ConfigBuilderError> {
+    let mapping: serde_json::Map<String, JsonValue> =
+        serde_norway::from_str(mapping).map_err(|e| ConfigBuilderError::Parse(e.to_string()))?;
+    from_named_values(schema, mapping)
+}
+
+#[cfg(feature = "config-formats")]
+fn from_named_values(
+    schema: &ConfigSchema,
+    values: serde_json::Map<String, JsonValue>,
+) -> Result<ResConfig, ConfigBuilderError> {
+    let mut builder = ConfigBuilder::with_schema(schema);
+    for (name, value) in values.iter() {
+        if schema.position(name).is_none() {
+            return Err(ConfigBuilderError::UnknownName(name.clone()));
+        }
+        builder.set_by_name(name, value)?;
+    }
+    builder.finish_with_defaults().map_err(|e| match e {
+        ConfigBuilderError::NoDefault(i) => {
+            ConfigBuilderError::MissingName(schema.fields()[i].name.clone())
+        }
+        e => e,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -264,7 +667,9 @@ mod tests {
         let schema = example_json_array();
         let mut conf_building = ConfBuilding {
             schema: &schema,
+            named: None,
             config: JsonArray::new(),
+            pending: BTreeMap::new(),
         };
         //Correct type is Number, and this is not the last element
         assert!(conf_building.append(&json!(30.3)).is_ok_and(|x| !x));
@@ -297,11 +702,15 @@ mod tests {
         let schema = example_json_array();
         let mut conf_builder = ConfigBuilder::new(&schema);
 
-        let given_disc = discriminant(&json!("a"));
-        let expected_disc = discriminant(&json!(8));
-        assert!(conf_builder
-            .append(&json!("teehee"))
-            .is_err_and(|x| x == ConfigBuilderError::TypeMismatch(0, expected_disc, given_disc)));
+        let err = conf_builder.append(&json!("teehee")).unwrap_err();
+        assert_eq!(
+            err,
+            ConfigBuilderError::TypeMismatch(0, JsonType::Number, JsonType::String)
+        );
+        assert_eq!(
+            err.to_string(),
+            "type mismatch at 0: expected number, got string"
+        );
     }
 
     #[test]
@@ -311,10 +720,11 @@ mod tests {
         let items = vec![json!(2500), json!("merged"), json!(false)];
 
         match conf_build.inject(items) {
-            Ok(count) => {
+            Ok(outcome) => {
                 //Count has to be three because 3 items were inserted
-                assert_eq!(count, 3);
+                assert_eq!(outcome.appended, 3);
                 //Builder has to be finished
+                assert!(outcome.finished);
                 assert!(conf_build.is_config())
             }
             //Provided items match the schema, so Err(_) is impossible
@@ -329,11 +739,20 @@ mod tests {
         //There are more items than needed
         let items = vec![json!(2500), json!("merged"), json!(false), json!("extra")];
 
+        //Strict injection does not drop the last one
+        let mut strict = ConfigBuilder::new(&schema);
+        assert_eq!(
+            strict.inject_exact(&items),
+            Err(ConfigBuilderError::ValueOutsideSchema)
+        );
+        assert!(strict.is_builder());
+
         match conf_build.inject(items) {
-            Ok(count) => {
+            Ok(outcome) => {
                 //Count has to be three because 3 items were inserted
-                assert_eq!(count, 3);
+                assert_eq!(outcome.appended, 3);
                 //Builder has to be finished
+                assert!(outcome.finished);
                 assert!(conf_build.is_config())
             }
             //Provided items match the schema (the last one is dropped), so Err(_) is impossible
@@ -350,8 +769,14 @@ mod tests {
         let it2 = vec![json!(false), json!("extra")];
 
         match conf_build.inject(it1) {
-            Ok(count) => {
-                assert_eq!(count, 2);
+            Ok(outcome) => {
+                assert_eq!(
+                    outcome,
+                    InjectOutcome {
+                        appended: 2,
+                        finished: false
+                    }
+                );
                 //Builder has to be unfinished
                 assert!(conf_build.is_builder())
             }
@@ -359,10 +784,16 @@ mod tests {
         }
 
         match conf_build.inject(it2) {
-            Ok(count) => {
+            Ok(outcome) => {
                 //Schema is of length 3 and two items were inserted earlier, only one
                 //needs to be taken.
-                assert_eq!(count, 1);
+                assert_eq!(
+                    outcome,
+                    InjectOutcome {
+                        appended: 1,
+                        finished: true
+                    }
+                );
                 assert!(conf_build.is_config())
             }
             Err(_) => unreachable!(),
@@ -375,8 +806,6 @@ mod tests {
         let mut conf_build = ConfigBuilder::new(&schema);
         //Second value is not a string
         let items = vec![json!(7), json!(0xF00F), json!(false)];
-        let given_disc = discriminant(&json!(0xF00F));
-        let expected_disc = discriminant(&json!("bee"));
 
         match conf_build.inject(items) {
             Ok(_) => panic!("config builder created a config that does not match the schema"),
@@ -385,12 +814,40 @@ mod tests {
             Err(e) => {
                 assert_eq!(
                     e,
-                    ConfigBuilderError::TypeMismatch(1, expected_disc, given_disc)
+                    ConfigBuilderError::TypeMismatch(1, JsonType::String, JsonType::Number)
                 );
             }
         }
     }
 
+    #[test]
+    fn config_builder_inject_exact_in_parts() {
+        let schema = example_json_array();
+        let mut conf_build = ConfigBuilder::new(&schema);
+        let outcome = conf_build.inject_exact([json!(2500)]).unwrap();
+        assert_eq!(
+            outcome,
+            InjectOutcome {
+                appended: 1,
+                finished: false
+            }
+        );
+        //Too many values are rejected before anything is appended
+        assert_eq!(
+            conf_build.inject_exact([json!("merged"), json!(false), json!(1)]),
+            Err(ConfigBuilderError::ValueOutsideSchema)
+        );
+        assert_eq!(conf_build.remaining(), 2);
+        let outcome = conf_build
+            .inject_exact([json!("merged"), json!(false)])
+            .unwrap();
+        assert!(outcome.finished);
+        assert_eq!(
+            conf_build.inject_exact([json!(1)]),
+            Err(ConfigBuilderError::ValueOutsideSchema)
+        );
+    }
+
     #[test]
     fn config_builder_inject_into_full() {
         let schema = example_json_array();
@@ -405,4 +862,165 @@ mod tests {
             Err(e) => assert_eq!(e, ConfigBuilderError::ValueOutsideSchema),
         }
     }
+
+    #[test]
+    fn config_builder_inject_owned_chains_builders() {
+        let first_schema = example_json_array();
+        let second_schema = JsonArray::from_values([json!(1), json!("two")]).unwrap();
+        let mut first = ConfigBuilder::new(&first_schema);
+        let mut second = ConfigBuilder::new(&second_schema);
+        let stream = vec![
+            json!(2500),
+            json!("merged"),
+            json!(false),
+            json!(3),
+            json!("next"),
+            json!("left over"),
+        ];
+
+        let (outcome, rest) = first.inject_owned(stream).unwrap();
+        assert_eq!(outcome.appended, 3);
+        let (outcome, mut rest) = second.inject_owned(rest).unwrap();
+        assert_eq!(
+            outcome,
+            InjectOutcome {
+                appended: 2,
+                finished: true
+            }
+        );
+        assert_eq!(rest.next(), Some(json!("left over")));
+        match second {
+            ConfigBuilder::Config(conf) => assert_eq!(conf.as_slice(), &[json!(3), json!("next")]),
+            ConfigBuilder::Builder(_) => panic!("second config is not finished"),
+        }
+    }
+
+    #[test]
+    fn config_builder_set_by_name_fills_out_of_order() {
+        use crate::resource::{ConfigField, FieldType};
+
+        let schema = ConfigSchema::new(vec![
+            ConfigField::new("rate", FieldType::Integer, "").with_range(0.0, 511.0),
+            ConfigField::new("name", FieldType::String, ""),
+            ConfigField::new("saw", FieldType::Bool, ""),
+        ]);
+        let mut conf_build = ConfigBuilder::with_schema(&schema);
+        assert_eq!(conf_build.set_by_name("saw", &json!(true)), Ok(false));
+        assert_eq!(
+            conf_build.set_by_name("rate", &json!(512)),
+            Err(ConfigBuilderError::Schema(ConfigError::OutOfRange(0)))
+        );
+        assert_eq!(
+            conf_build.set_by_name("volume", &json!(1)),
+            Err(ConfigBuilderError::UnknownName("volume".to_string()))
+        );
+        assert_eq!(conf_build.set_by_name("rate", &json!(3)), Ok(false));
+        assert_eq!(conf_build.set_by_name("rate", &json!(4)), Ok(false));
+        // Appending fills the gap, after which the value set by name follows
+        assert_eq!(conf_build.append(&json!("lead")), Ok(true));
+        assert!(conf_build.append(&json!(false)).is_err());
+
+        let mut unnamed = ConfigBuilder::new(schema.positional());
+        assert!(unnamed.set_by_name("rate", &json!(3)).is_err());
+    }
+
+    #[test]
+    fn config_builder_finishes_with_defaults() {
+        use crate::resource::{ConfigField, FieldType};
+
+        let schema = ConfigSchema::new(vec![
+            ConfigField::new("rate", FieldType::Integer, "").with_default(json!(3)),
+            ConfigField::new("name", FieldType::String, ""),
+            ConfigField::new("saw", FieldType::Bool, "").with_default(json!(false)),
+        ]);
+        let mut conf_build = ConfigBuilder::with_schema(&schema);
+        conf_build.set_by_name("name", &json!("lead")).unwrap();
+        let conf = conf_build.finish_with_defaults().unwrap();
+        assert_eq!(conf.as_slice(), &[json!(3), json!("lead"), json!(false)]);
+
+        let conf_build = ConfigBuilder::with_schema(&schema);
+        assert_eq!(
+            conf_build.finish_with_defaults(),
+            Err(ConfigBuilderError::NoDefault(1))
+        );
+        let unnamed = ConfigBuilder::new(schema.positional());
+        assert_eq!(
+            unnamed.finish_with_defaults(),
+            Err(ConfigBuilderError::NoDefault(0))
+        );
+    }
+
+    #[test]
+    fn config_builder_moves_finished_config() {
+        let schema = example_json_array();
+        let mut conf_build = ConfigBuilder::new(&schema);
+        //Reserve the whole config up front, so that appending never reallocates
+        let data = match &mut conf_build {
+            ConfigBuilder::Builder(build) => {
+                build.config =
+                    JsonArray::from_value(JsonValue::Array(Vec::with_capacity(3))).unwrap();
+                build.config.as_slice().as_ptr()
+            }
+            ConfigBuilder::Config(_) => panic!("config finished early"),
+        };
+        let outcome = conf_build
+            .inject([json!(1), json!("one"), json!(true)])
+            .unwrap();
+        assert!(outcome.finished);
+        match conf_build {
+            //The same allocation means that the config was not cloned
+            ConfigBuilder::Config(conf) => assert_eq!(conf.as_slice().as_ptr(), data),
+            ConfigBuilder::Builder(_) => panic!("config is not finished"),
+        }
+    }
+
+    #[test]
+    fn config_builder_try_finish() {
+        let empty = JsonArray::new();
+        let conf_build = ConfigBuilder::new(&empty);
+        assert_eq!(conf_build.remaining(), 0);
+        assert_eq!(conf_build.try_finish(), Ok(JsonArray::new()));
+
+        let schema = example_json_array();
+        let mut conf_build = ConfigBuilder::new(&schema);
+        assert_eq!(conf_build.remaining(), 3);
+        conf_build.inject([json!(1)]).unwrap();
+        assert_eq!(conf_build.remaining(), 2);
+        let err = conf_build.clone().try_finish().unwrap_err();
+        assert_eq!(
+            err,
+            ConfigBuilderError::Unfinished(vec![JsonType::String, JsonType::Bool])
+        );
+        assert_eq!(
+            err.to_string(),
+            "configuration is unfinished: 2 values are missing (string, bool)"
+        );
+
+        conf_build.inject([json!("two"), json!(true)]).unwrap();
+        assert_eq!(conf_build.remaining(), 0);
+        let conf = conf_build.try_finish().unwrap();
+        assert_eq!(conf.as_slice(), &[json!(1), json!("two"), json!(true)]);
+
+        //Values that were appended one by one finish the config too
+        let mut conf_build = ConfigBuilder::new(&schema);
+        for x in [json!(1), json!("two"), json!(true)] {
+            conf_build.append(&x).unwrap();
+        }
+        assert!(conf_build.try_finish().is_ok());
+    }
+
+    #[test]
+    fn config_builder_reset_starts_over() {
+        let schema = example_json_array();
+        let mut conf_build = ConfigBuilder::new(&schema);
+        conf_build.inject([json!(1), json!("two")]).unwrap();
+        assert!(conf_build.reset());
+        assert_eq!(conf_build.remaining(), 3);
+        let outcome = conf_build
+            .inject([json!(4), json!("five"), json!(false)])
+            .unwrap();
+        assert_eq!(outcome.appended, 3);
+        assert!(!conf_build.reset());
+        assert!(conf_build.is_config());
+    }
 }
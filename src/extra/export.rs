@@ -0,0 +1,370 @@
+//! Conversion of sounds to and from raw PCM bytes.
+//!
+//! Integer formats map `[-1, 1)` onto the full range of the sample type: a sample is
+//! multiplied by `2^(bits - 1)`, rounded and clamped, so 1.0 becomes the highest
+//! value and -1.0 the lowest. Unsigned 8-bit samples are offset by 128.
+
+use crate::{
+    extra::rng::Rng,
+    resource::StringError,
+    types::{quantize_to_int, Sound, DITHER_SEED},
+};
+
+/// Type of a single PCM sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+    /// Unsigned 8-bit, silence is 128.
+    U8,
+    /// Signed 8-bit.
+    S8,
+    /// Signed 16-bit, little-endian.
+    S16Le,
+    /// Signed 24-bit packed into 3 bytes, little-endian.
+    S24Le,
+    /// 32-bit float, little-endian.
+    F32Le,
+}
+
+impl SampleFormat {
+    /// Size of one sample in bytes.
+    pub fn bytes(self) -> usize {
+        match self {
+            SampleFormat::U8 | SampleFormat::S8 => 1,
+            SampleFormat::S16Le => 2,
+            SampleFormat::S24Le => 3,
+            SampleFormat::F32Le => 4,
+        }
+    }
+
+    /// Number of bits of integer formats, None for floats.
+    fn bits(self) -> Option<u32> {
+        match self {
+            SampleFormat::F32Le => None,
+            _ => Some(self.bytes() as u32 * 8),
+        }
+    }
+}
+
+/// Layout of PCM data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PcmFormat {
+    /// Type of the samples.
+    pub sample: SampleFormat,
+
+    /// If true, left and right samples are interleaved. Otherwise, both sides are
+    /// averaged into one sample.
+    pub stereo: bool,
+}
+
+impl PcmFormat {
+    /// Size of one frame in bytes.
+    pub fn frame_bytes(self) -> usize {
+        self.sample.bytes() * if self.stereo { 2 } else { 1 }
+    }
+}
+
+/// Convert a sound into PCM bytes.
+///
+/// If `dither` is true, triangular noise of one least significant bit is added to
+/// integer samples before rounding, which turns quantization distortion of quiet
+/// sounds into noise. It has no effect on floats.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::export::{to_pcm, PcmFormat, SampleFormat};
+/// # use mleml::types::Sound;
+/// let sound = Sound::new(Box::new([[1.0, -1.0]]), 48000);
+/// let format = PcmFormat { sample: SampleFormat::S8, stereo: true };
+/// assert_eq!(to_pcm(&sound, format, false), [127, 128]);
+/// ```
+pub fn to_pcm(sound: &Sound, format: PcmFormat, dither: bool) -> Vec<u8> {
+    let mut rng = Rng::new(DITHER_SEED);
+    let mut out = Vec::with_capacity(sound.len_frames() * format.frame_bytes());
+    let mut push = |x: f32| match format.sample.bits() {
+        None => out.extend(x.to_le_bytes()),
+        Some(bits) => {
+            let noise = match dither {
+                true => rng.next_f64() - rng.next_f64(),
+                false => 0.0,
+            };
+            let value = quantize_to_int(x, bits, noise);
+            match format.sample {
+                SampleFormat::U8 => out.push((value + 128) as u8),
+                _ => out.extend(&value.to_le_bytes()[..format.sample.bytes()]),
+            }
+        }
+    };
+    for frame in sound.data() {
+        match format.stereo {
+            true => {
+                push(frame[0]);
+                push(frame[1]);
+            }
+            false => push((frame[0] + frame[1]) / 2.0),
+        }
+    }
+    out
+}
+
+/// Convert PCM bytes into a sound. Mono data is played on both sides.
+///
+/// # Errors
+///
+/// Returns an error if the length of the data is not a whole number of frames.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::export::{from_pcm, PcmFormat, SampleFormat};
+/// let format = PcmFormat { sample: SampleFormat::U8, stereo: false };
+/// let sound = from_pcm(&[0, 128], format, 8000).expect("data is not whole frames");
+/// assert_eq!(sound.data(), &[[-1.0, -1.0], [0.0, 0.0]]);
+/// ```
+pub fn from_pcm(
+    data: &[u8],
+    format: PcmFormat,
+    sampling_rate: u32,
+) -> Result<Box<Sound>, StringError> {
+    if !data.len().is_multiple_of(format.frame_bytes()) {
+        return Err(StringError(format!(
+            "{} bytes is not a whole number of {}-byte frames",
+            data.len(),
+            format.frame_bytes()
+        )));
+    }
+    let samples: Vec<f32> = data
+        .chunks(format.sample.bytes())
+        .map(|x| match format.sample {
+            SampleFormat::U8 => (x[0] as f32 - 128.0) / 128.0,
+            SampleFormat::S8 => x[0] as i8 as f32 / 128.0,
+            SampleFormat::S16Le => i16::from_le_bytes([x[0], x[1]]) as f32 / 32768.0,
+            // Place the sample into the upper bytes to extend the sign
+            SampleFormat::S24Le => {
+                (i32::from_le_bytes([0, x[0], x[1], x[2]]) >> 8) as f32 / 8388608.0
+            }
+            SampleFormat::F32Le => f32::from_le_bytes([x[0], x[1], x[2], x[3]]),
+        })
+        .collect();
+    let frames = match format.stereo {
+        true => samples.chunks(2).map(|x| [x[0], x[1]]).collect(),
+        false => samples.iter().map(|x| [*x, *x]).collect(),
+    };
+    Ok(Sound::new(frames, sampling_rate))
+}
+
+/// Loudness that [`normalize()`] brings a sound to.
+///
+/// More targets may be added later, so matching on it needs a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum NormalizeTarget {
+    /// Highest absolute sample, in decibels relative to full scale.
+    Peak(f32),
+}
+
+/// Number of points between two frames at which true peak is measured.
+const OVERSAMPLING: usize = 4;
+
+/// Number of frames on each side of a point that are used to interpolate it.
+const SINC_TAPS: isize = 16;
+
+/// Get the highest absolute value of the sound on either side.
+///
+/// If `true_peak` is true, the sound is also measured between the frames, using
+/// windowed sinc interpolation at 4 times the sampling rate. This finds peaks that
+/// the signal reaches after conversion to analog, which may be higher than any
+/// frame.
+pub fn peak(sound: &Sound, true_peak: bool) -> f32 {
+    let data = sound.data();
+    let sample_peak = sound.peak();
+    if !true_peak {
+        return sample_peak;
+    }
+    let sinc = |x: f64| match x == 0.0 {
+        true => 1.0,
+        false => (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x),
+    };
+    // Hann window over the taps
+    let window = |x: f64| 0.5 + 0.5 * (std::f64::consts::PI * x / (SINC_TAPS + 1) as f64).cos();
+    let mut peak = sample_peak;
+    for i in 0..data.len() {
+        for step in 1..OVERSAMPLING {
+            let t = step as f64 / OVERSAMPLING as f64;
+            let mut sum = [0.0_f64; 2];
+            for m in (1 - SINC_TAPS)..=SINC_TAPS {
+                let Some(frame) = data.get(i.wrapping_add_signed(m)) else {
+                    continue;
+                };
+                let weight = sinc(t - m as f64) * window(t - m as f64);
+                sum[0] += frame[0] as f64 * weight;
+                sum[1] += frame[1] as f64 * weight;
+            }
+            peak = peak.max(sum[0].abs() as f32).max(sum[1].abs() as f32);
+        }
+    }
+    peak
+}
+
+/// Scale the sound so that it reaches the target loudness.
+///
+/// `true_peak` selects how the [peak][peak()] is measured. Silent sounds are returned
+/// unchanged.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::export::{normalize, peak, NormalizeTarget};
+/// # use mleml::types::Sound;
+/// let sound = Sound::new(Box::new([[0.25, -0.1]]), 48000);
+/// let loud = normalize(&sound, NormalizeTarget::Peak(0.0), false);
+/// assert_eq!(peak(&loud, false), 1.0);
+/// ```
+pub fn normalize(sound: &Sound, target: NormalizeTarget, true_peak: bool) -> Box<Sound> {
+    match target {
+        NormalizeTarget::Peak(dbfs) => {
+            let target = 10.0_f32.powf(dbfs / 20.0);
+            // Sound::normalize() scales by the sample peak, so the target is moved by
+            // the ratio between it and the measured peak. Silent sounds are left as
+            // they are.
+            sound.normalize(target * sound.peak() / peak(sound, true_peak))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [SampleFormat; 5] = [
+        SampleFormat::U8,
+        SampleFormat::S8,
+        SampleFormat::S16Le,
+        SampleFormat::S24Le,
+        SampleFormat::F32Le,
+    ];
+
+    #[test]
+    fn s16_round_trips() {
+        let format = PcmFormat {
+            sample: SampleFormat::S16Le,
+            stereo: true,
+        };
+        let bytes: Vec<u8> = [i16::MIN, -1, 0, 1, 12345, i16::MAX]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let sound = from_pcm(&bytes, format, 44100).unwrap();
+        assert_eq!(sound.sampling_rate(), 44100);
+        assert_eq!(sound.data().len(), 3);
+        assert_eq!(to_pcm(&sound, format, false), bytes);
+    }
+
+    #[test]
+    fn s8_full_scale_does_not_overflow() {
+        let format = PcmFormat {
+            sample: SampleFormat::S8,
+            stereo: false,
+        };
+        let sound = Sound::new(
+            Box::new([
+                [1.0, 1.0],
+                [-1.0, -1.0],
+                [4.0, 4.0],
+                [-4.0, -4.0],
+                [f32::NAN; 2],
+            ]),
+            48000,
+        );
+        let bytes: Vec<i8> = to_pcm(&sound, format, false)
+            .into_iter()
+            .map(|x| x as i8)
+            .collect();
+        assert_eq!(bytes, [127, -128, 127, -128, 0]);
+    }
+
+    #[test]
+    fn every_format_round_trips_within_one_step() {
+        let sound = Sound::new(Box::new([[0.5, -0.25], [0.0, 0.999], [-1.0, 0.125]]), 48000);
+        for sample in ALL {
+            let format = PcmFormat {
+                sample,
+                stereo: true,
+            };
+            let bytes = to_pcm(&sound, format, false);
+            assert_eq!(bytes.len(), 3 * format.frame_bytes());
+            let back = from_pcm(&bytes, format, 48000).unwrap();
+            let step = match sample.bits() {
+                Some(bits) => 1.0 / (1 << (bits - 1)) as f32,
+                None => 0.0,
+            };
+            for (a, b) in sound.data().iter().zip(back.data()) {
+                assert!((a[0] - b[0]).abs() <= step && (a[1] - b[1]).abs() <= step);
+            }
+        }
+    }
+
+    #[test]
+    fn peak_normalization_hits_target() {
+        let data = (0..4800)
+            .map(|i| {
+                let x = (i as f32 * 0.05).sin() * 0.3;
+                [x, x * 0.5]
+            })
+            .collect();
+        let sound = Sound::new(data, 48000);
+        for true_peak in [false, true] {
+            let loud = normalize(&sound, NormalizeTarget::Peak(-1.0), true_peak);
+            let db = 20.0 * peak(&loud, true_peak).log10();
+            assert!((db + 1.0).abs() < 0.05, "{db}");
+        }
+        let silence = Sound::silence(0.1, 48000);
+        assert_eq!(
+            normalize(&silence, NormalizeTarget::Peak(0.0), true),
+            silence
+        );
+    }
+
+    #[test]
+    fn true_peak_finds_inter_sample_overs() {
+        // Sine at a quarter of the sampling rate, sampled 45 degrees off its peaks
+        let data = (0..480)
+            .map(|i| {
+                let x =
+                    (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin();
+                [x, x]
+            })
+            .collect();
+        let sound = Sound::new(data, 48000);
+        let sample_peak = peak(&sound, false);
+        let true_peak = peak(&sound, true);
+        assert!((sample_peak - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.001);
+        assert!(true_peak > 0.95, "{true_peak}");
+    }
+
+    #[test]
+    fn mono_averages_sides() {
+        let format = PcmFormat {
+            sample: SampleFormat::F32Le,
+            stereo: false,
+        };
+        let sound = Sound::new(Box::new([[0.5, 0.25]]), 48000);
+        assert_eq!(to_pcm(&sound, format, false), 0.375_f32.to_le_bytes());
+        assert!(from_pcm(&[0; 3], format, 48000).is_err());
+    }
+
+    #[test]
+    fn interleaved_accessors_match_pcm() {
+        let format = PcmFormat {
+            sample: SampleFormat::S16Le,
+            stereo: true,
+        };
+        let sound = Sound::new(Box::new([[0.3, -0.7], [1e-5, 2.0]]), 48000);
+        for dither in [false, true] {
+            assert_eq!(
+                sound.to_interleaved_i16_le(dither),
+                to_pcm(&sound, format, dither)
+            );
+        }
+    }
+}
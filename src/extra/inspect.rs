@@ -0,0 +1,194 @@
+//! Runtime introspection of mods.
+
+use std::mem::Discriminant;
+
+use crate::resource::{ConfigSchema, Mod, ModData, ResConfig, ResState, Resource, StringError};
+
+/// Wrapper around a mod that logs every call to [`apply()`][Mod::apply()] to stderr.
+///
+/// All calls are delegated to the inner mod. Inputs and outputs of every
+/// [`ModData`] variant are logged, sounds are summarized instead of being printed
+/// in full.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "builtin")] {
+/// # use mleml::extra::{builtin::Noise, inspect::Inspector};
+/// # use mleml::resource::{JsonArray, Mod, ModData};
+/// # use mleml::types::ReadyNote;
+/// let noise = Inspector::new(Noise());
+/// let note = ModData::ReadyNote(ReadyNote::default());
+/// // Prints the note, the config, and a summary of the produced sound
+/// noise.apply(&note, &JsonArray::new(), &[]).unwrap();
+/// # }
+/// ```
+pub struct Inspector<M: Mod> {
+    inner: M,
+}
+
+impl<M: Mod> Inspector<M> {
+    /// Wrap a mod.
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+
+    /// Get the wrapped mod.
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Unwrap the mod.
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M: Mod> Resource for Inspector<M> {
+    fn orig_name(&self) -> &str {
+        self.inner.orig_name()
+    }
+
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        self.inner.check_config(conf)
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        self.inner.check_state(state)
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn schema_version(&self) -> u32 {
+        self.inner.schema_version()
+    }
+
+    fn migrate_config(&self, old_version: u32, conf: ResConfig) -> Result<ResConfig, StringError> {
+        self.inner.migrate_config(old_version, conf)
+    }
+
+    fn migrate_state(
+        &self,
+        old_version: u32,
+        state: Box<ResState>,
+    ) -> Result<Box<ResState>, StringError> {
+        self.inner.migrate_state(old_version, state)
+    }
+
+    fn initial_state(&self) -> Box<ResState> {
+        self.inner.initial_state()
+    }
+
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        self.inner.config_schema()
+    }
+}
+
+impl<M: Mod> Mod for Inspector<M> {
+    fn apply(
+        &self,
+        input: &ModData,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        eprintln!(
+            "[{}] input: {}, config: {}, state: {:?}",
+            self.name(),
+            describe(input),
+            String::from_utf8_lossy(&conf.as_byte_vec()),
+            state
+        );
+        let out = self.inner.apply(input, conf, state);
+        match &out {
+            Ok((data, state)) => eprintln!(
+                "[{}] output: {}, state: {:?}",
+                self.name(),
+                describe(data),
+                state
+            ),
+            Err(e) => eprintln!("[{}] error: {}", self.name(), e.0),
+        }
+        out
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        self.inner.input_type()
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        self.inner.output_type()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn tail_seconds(&self, conf: &ResConfig) -> f32 {
+        self.inner.tail_seconds(conf)
+    }
+}
+
+/// Short, human-readable description of the data.
+fn describe(data: &ModData) -> String {
+    match data {
+        ModData::String(s) => format!("String({s:?})"),
+        ModData::Note(note) => format!("{note:?}"),
+        ModData::ReadyNote(note) => format!("{note:?}"),
+        ModData::Notes(notes) => format!("{notes:?}"),
+        ModData::ReadyNotes(notes) => format!("{notes:?}"),
+        ModData::Sound(sound) => {
+            let peak = sound.peak();
+            format!(
+                "Sound {{ frames: {}, sampling_rate: {}, peak: {} }}",
+                sound.len_frames(),
+                sound.sampling_rate(),
+                peak
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{resource::JsonArray, test_util::Double, types::Sound};
+
+    use super::*;
+
+    #[test]
+    fn calls_are_delegated() {
+        let inspector = Inspector::new(Double());
+        assert_eq!(inspector.id(), "TEST_DOUBLE");
+        assert_eq!(inspector.input_type(), Double().input_type());
+        let (out, state) = inspector
+            .apply(
+                &ModData::String("ab".to_string()),
+                &JsonArray::new(),
+                &[1, 2],
+            )
+            .unwrap();
+        assert_eq!(out.as_string(), Some("abab"));
+        assert_eq!(&*state, &[1, 2]);
+        assert!(inspector
+            .apply(
+                &ModData::Sound(Sound::new(Box::new([]), 48000)),
+                &JsonArray::new(),
+                &[]
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn sound_is_summarized() {
+        let sound = ModData::Sound(Sound::new(Box::new([[0.5, -0.75]; 3]), 48000));
+        assert_eq!(
+            describe(&sound),
+            "Sound { frames: 3, sampling_rate: 48000, peak: 0.75 }"
+        );
+    }
+}
@@ -3,7 +3,18 @@
 #[cfg(feature = "extra")]
 pub mod config_builder;
 #[cfg(feature = "extra")]
+pub mod export;
+#[cfg(feature = "inspect")]
+pub mod inspect;
+#[cfg(feature = "extra")]
+pub mod patch;
+#[cfg(feature = "extra")]
+pub mod preview;
+pub mod rng;
+#[cfg(feature = "extra")]
 pub mod storage;
+#[cfg(feature = "io")]
+pub mod wav;
 
 #[cfg(feature = "builtin")]
 pub mod builtin;
@@ -0,0 +1,225 @@
+//! Randomized editing of configs, for exploring the sounds a mod can make.
+//!
+//! Both [`mutate()`] and [`crossover()`] are seeded, so the same inputs always
+//! produce the same config.
+
+use serde_json::json;
+
+use crate::{
+    extra::rng::Rng,
+    resource::{ResConfig, Resource, StringError},
+};
+
+/// Range of values that a slot of a config accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamMeta {
+    /// Slot that is never changed, for example a string or a bool.
+    Fixed,
+
+    /// Integer in range `[min, max]`.
+    Int {
+        /// Lowest allowed value.
+        min: i64,
+        /// Highest allowed value.
+        max: i64,
+    },
+
+    /// Float in range `[min, max]`.
+    Float {
+        /// Lowest allowed value.
+        min: f64,
+        /// Highest allowed value.
+        max: f64,
+    },
+}
+
+/// Randomly move every numeric slot of `conf` by up to `amount` times the width of
+/// its range, keeping it inside the range.
+///
+/// `amount` of 0 returns the config unchanged, 1 can move a slot across its whole range.
+///
+/// # Errors
+///
+/// Returns an error if `amount` is not in range [0, 1], if `meta` does not describe
+/// every slot of the config, if a numeric slot holds a value of a different type,
+/// or if `target` does not accept the resulting config.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "builtin")] {
+/// # use mleml::extra::builtin::{patches, FourOpFm};
+/// # use mleml::extra::patch::mutate;
+/// let piano = patches::create_piano_patch();
+/// let meta = patches::param_meta();
+/// let mutated = mutate(&FourOpFm(), &piano, &meta, 0.1, 7).expect("mutated patch is invalid");
+/// assert_eq!(mutated, mutate(&FourOpFm(), &piano, &meta, 0.1, 7).unwrap());
+/// # }
+/// ```
+pub fn mutate(
+    target: &dyn Resource,
+    conf: &ResConfig,
+    meta: &[ParamMeta],
+    amount: f64,
+    seed: u64,
+) -> Result<ResConfig, StringError> {
+    if !(0.0..=1.0).contains(&amount) {
+        return Err(StringError(format!(
+            "amount {amount} is not in range 0 - 1"
+        )));
+    }
+    if meta.len() != conf.len() {
+        return Err(StringError(format!(
+            "metadata describes {} values, config has {}",
+            meta.len(),
+            conf.len()
+        )));
+    }
+    if amount == 0.0 {
+        return Ok(conf.clone());
+    }
+    let mut rng = Rng::new(seed);
+    let values = conf
+        .as_slice()
+        .iter()
+        .zip(meta)
+        .enumerate()
+        .map(|(i, (value, meta))| {
+            let not_number = || StringError(format!("value {} is not a number", i + 1));
+            let shift = (rng.next_f64() * 2.0 - 1.0) * amount;
+            match *meta {
+                ParamMeta::Fixed => Ok(value.clone()),
+                ParamMeta::Int { min, max } => {
+                    let old = value.as_i64().ok_or_else(not_number)?;
+                    let new = old as f64 + shift * (max - min) as f64;
+                    Ok(json!((new.round() as i64).clamp(min, max)))
+                }
+                ParamMeta::Float { min, max } => {
+                    let old = value.as_f64().ok_or_else(not_number)?;
+                    Ok(json!((old + shift * (max - min)).clamp(min, max)))
+                }
+            }
+        })
+        .collect::<Result<Vec<_>, StringError>>()?;
+    checked(target, values)
+}
+
+/// Make a config that takes every slot from either `a` or `b`, at random.
+///
+/// # Errors
+///
+/// Returns an error if the configs have different lengths, or if `target` does not
+/// accept the resulting config.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "builtin")] {
+/// # use mleml::extra::builtin::{patches, FourOpFm};
+/// # use mleml::extra::patch::crossover;
+/// let piano = patches::create_piano_patch();
+/// let bell = patches::create_bell_patch();
+/// let child = crossover(&FourOpFm(), &piano, &bell, 3).expect("child patch is invalid");
+/// for (i, value) in child.as_slice().iter().enumerate() {
+///     assert!(*value == piano.as_slice()[i] || *value == bell.as_slice()[i]);
+/// }
+/// # }
+/// ```
+pub fn crossover(
+    target: &dyn Resource,
+    a: &ResConfig,
+    b: &ResConfig,
+    seed: u64,
+) -> Result<ResConfig, StringError> {
+    if a.len() != b.len() {
+        return Err(StringError(format!(
+            "configs have different lengths: {} and {}",
+            a.len(),
+            b.len()
+        )));
+    }
+    let mut rng = Rng::new(seed);
+    let values = a
+        .as_slice()
+        .iter()
+        .zip(b.as_slice())
+        .map(|(a, b)| match rng.below(2) {
+            0 => a.clone(),
+            _ => b.clone(),
+        })
+        .collect::<Vec<_>>();
+    checked(target, values)
+}
+
+fn checked(
+    target: &dyn Resource,
+    values: Vec<serde_json::Value>,
+) -> Result<ResConfig, StringError> {
+    let conf = ResConfig::from_values(values)
+        .ok_or(StringError("resulting config is not flat".to_string()))?;
+    target.check_config(&conf)?;
+    Ok(conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{resource::JsonArray, test_util::Ranged};
+
+    use super::*;
+
+    const META: [ParamMeta; 3] = [
+        ParamMeta::Int { min: 0, max: 10 },
+        ParamMeta::Float { min: 0.0, max: 1.0 },
+        ParamMeta::Fixed,
+    ];
+
+    fn base() -> ResConfig {
+        JsonArray::from_values([json!(9), json!(0.5), json!("name")]).unwrap()
+    }
+
+    #[test]
+    fn mutations_stay_in_range() {
+        for seed in 0..200 {
+            let mutated = mutate(&Ranged(), &base(), &META, 1.0, seed).unwrap();
+            assert_eq!(mutated.as_slice()[2], json!("name"));
+        }
+    }
+
+    #[test]
+    fn zero_amount_changes_nothing() {
+        assert_eq!(mutate(&Ranged(), &base(), &META, 0.0, 5).unwrap(), base());
+        // Integers in float slots stay integers
+        let whole = JsonArray::from_values([json!(9), json!(1), json!("name")]).unwrap();
+        assert_eq!(mutate(&Ranged(), &whole, &META, 0.0, 5).unwrap(), whole);
+    }
+
+    #[test]
+    fn same_seed_same_mutation() {
+        let a = mutate(&Ranged(), &base(), &META, 0.5, 11).unwrap();
+        assert_eq!(a, mutate(&Ranged(), &base(), &META, 0.5, 11).unwrap());
+        assert_ne!(a, mutate(&Ranged(), &base(), &META, 0.5, 12).unwrap());
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(mutate(&Ranged(), &base(), &META, 1.5, 0).is_err());
+        assert!(mutate(&Ranged(), &base(), &META[..2], 0.5, 0).is_err());
+        let other = JsonArray::from_values([json!(1), json!(0.25)]).unwrap();
+        assert!(crossover(&Ranged(), &base(), &other, 0).is_err());
+    }
+
+    #[test]
+    fn crossover_takes_slots_from_parents() {
+        let other = JsonArray::from_values([json!(2), json!(0.75), json!("other")]).unwrap();
+        let children: Vec<_> = (0..16)
+            .map(|seed| crossover(&Ranged(), &base(), &other, seed).unwrap())
+            .collect();
+        for child in &children {
+            for i in 0..3 {
+                let value = &child.as_slice()[i];
+                assert!(*value == base().as_slice()[i] || *value == other.as_slice()[i]);
+            }
+        }
+        assert!(children.iter().any(|x| *x != base() && *x != other));
+    }
+}
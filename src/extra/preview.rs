@@ -0,0 +1,210 @@
+//! Quick rendering of a single note, for auditioning patches.
+
+use crate::{
+    resource::{Mod, ModData, ResConfig, ResState, StringError},
+    types::{ReadyNote, Sound},
+};
+
+/// Length of the fade at the end of a preview, in seconds.
+const FADE_SECONDS: f32 = 0.005;
+
+/// Settings of a preview.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviewOptions {
+    /// Pitch of the note in Hz.
+    pub pitch_hz: f32,
+
+    /// Length of the resulting sound in seconds.
+    pub seconds: f32,
+
+    /// Velocity of the note.
+    pub velocity: u8,
+
+    /// Sampling rate of the resulting sound. `None` keeps the mod's own rate.
+    pub sample_rate: Option<u32>,
+}
+
+impl Default for PreviewOptions {
+    /// Middle C for half a second.
+    fn default() -> Self {
+        PreviewOptions {
+            pitch_hz: 261.63,
+            seconds: 0.5,
+            velocity: 128,
+            sample_rate: None,
+        }
+    }
+}
+
+/// Play one note with a mod that takes a [`ReadyNote`] and produces a [`Sound`].
+///
+/// The note is held for the whole preview. The sound is
+/// [resampled][Sound::resample] if a sampling rate is requested, cut or padded with
+/// silence to the requested length, and faded out over its last 5 ms to avoid a click.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the mod does not turn a
+/// [`ReadyNote`] into a [`Sound`], if the mod fails, or if the sound can not be
+/// resampled.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "builtin")] {
+/// # use mleml::extra::builtin::{patches, FourOpFm};
+/// # use mleml::extra::preview::{preview_mod, PreviewOptions};
+/// let piano = patches::create_piano_patch();
+/// let sound = preview_mod(&FourOpFm(), &piano, &[], PreviewOptions::default())
+///     .expect("patch can not be previewed");
+/// assert_eq!(sound.data().len(), 24000);
+/// # }
+/// ```
+pub fn preview_mod(
+    target: &dyn Mod,
+    conf: &ResConfig,
+    state: &ResState,
+    opts: PreviewOptions,
+) -> Result<Box<Sound>, StringError> {
+    if !(opts.pitch_hz.is_finite() && opts.pitch_hz > 0.0) {
+        return Err(StringError(format!(
+            "pitch {} Hz has to be finite and positive",
+            opts.pitch_hz
+        )));
+    }
+    if !(opts.seconds.is_finite() && opts.seconds >= 0.0) {
+        return Err(StringError(format!(
+            "length {} s has to be finite and nonnegative",
+            opts.seconds
+        )));
+    }
+    let note = ModData::ReadyNote(ReadyNote {
+        len: opts.seconds,
+        decay_time: 0.0,
+        pitch: Some(opts.pitch_hz),
+        percussive: false,
+        velocity: opts.velocity,
+        ..Default::default()
+    });
+    if target.input_type() != std::mem::discriminant(&note) {
+        return Err(StringError(format!(
+            "[{}] mod does not take a ReadyNote",
+            target.id()
+        )));
+    }
+    let (out, _) = target
+        .apply(&note, conf, state)
+        .map_err(|e| StringError(format!("[{}] {}", target.id(), e.0)))?;
+    let sound = out.as_sound().ok_or(StringError(format!(
+        "[{}] mod did not produce a sound",
+        target.id()
+    )))?;
+    let resampled;
+    let sound = match opts.sample_rate {
+        Some(rate) if rate != sound.sampling_rate() => {
+            resampled = sound.resample(rate)?;
+            resampled.as_ref()
+        }
+        _ => sound,
+    };
+
+    let rate = sound.sampling_rate();
+    let frames = (opts.seconds * rate as f32) as usize;
+    let fade = ((FADE_SECONDS * rate as f32) as usize).min(frames);
+    let data = sound
+        .data()
+        .iter()
+        .copied()
+        .chain(std::iter::repeat([0.0, 0.0]))
+        .take(frames)
+        .enumerate()
+        .map(|(i, x)| match frames - i <= fade {
+            true => {
+                let gain = (frames - i - 1) as f32 / fade as f32;
+                [x[0] * gain, x[1] * gain]
+            }
+            false => x,
+        });
+    Ok(Sound::from_frames(data, rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{resource::JsonArray, test_util::Constant};
+
+    use super::*;
+
+    #[test]
+    fn preview_fades_out() {
+        let opts = PreviewOptions {
+            seconds: 0.1,
+            ..Default::default()
+        };
+        let sound = preview_mod(&Constant(), &JsonArray::new(), &[], opts).unwrap();
+        assert_eq!(sound.sampling_rate(), 1000);
+        assert_eq!(sound.data().len(), 100);
+        assert_eq!(sound.data()[94], [1.0, 1.0]);
+        assert_eq!(sound.data()[97], [0.4, 0.4]);
+        assert_eq!(sound.data()[99], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn preview_is_resampled() {
+        let opts = PreviewOptions {
+            seconds: 0.1,
+            sample_rate: Some(2000),
+            ..Default::default()
+        };
+        let sound = preview_mod(&Constant(), &JsonArray::new(), &[], opts).unwrap();
+        assert_eq!(sound.sampling_rate(), 2000);
+        assert_eq!(sound.data().len(), 200);
+        assert_eq!(sound.data()[180], [1.0, 1.0]);
+
+        let opts = PreviewOptions {
+            sample_rate: Some(0),
+            ..opts
+        };
+        assert!(preview_mod(&Constant(), &JsonArray::new(), &[], opts).is_err());
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        let conf = JsonArray::new();
+        for opts in [
+            PreviewOptions {
+                pitch_hz: 0.0,
+                ..Default::default()
+            },
+            PreviewOptions {
+                seconds: f32::NAN,
+                ..Default::default()
+            },
+        ] {
+            assert!(preview_mod(&Constant(), &conf, &[], opts).is_err());
+        }
+    }
+
+    #[cfg(feature = "builtin")]
+    #[test]
+    fn fm_preview_is_audible() {
+        use crate::extra::builtin::{patches, ConvertNote, FourOpFm};
+
+        let piano = patches::create_piano_patch();
+        let opts = PreviewOptions::default();
+        let sound = preview_mod(&FourOpFm(), &piano, &[], opts).unwrap();
+        assert_eq!(sound.data().len(), 24000);
+        assert!(sound.data().iter().any(|x| x[0].abs() > 0.01));
+
+        let at_44100 = PreviewOptions {
+            sample_rate: Some(44100),
+            ..opts
+        };
+        let sound = preview_mod(&FourOpFm(), &piano, &[], at_44100).unwrap();
+        assert_eq!(sound.sampling_rate(), 44100);
+        assert_eq!(sound.data().len(), 22050);
+        assert!(sound.data().iter().any(|x| x[0].abs() > 0.01));
+
+        let err = preview_mod(&ConvertNote(), &piano, &[], opts).unwrap_err();
+        assert!(err.0.starts_with("[BUILTIN_CONVERT_NOTE]"), "{}", err.0);
+    }
+}
@@ -0,0 +1,95 @@
+//! Small seeded pseudorandom number generator.
+//!
+//! Resources that need randomness keep the generator's state in their
+//! [state][crate::resource::ResState], so that the output is deterministic and playback
+//! can be resumed from any point.
+
+/// SplitMix64 pseudorandom number generator.
+///
+/// Every `u64` is a valid state, so it can be restored from any 8 bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::extra::rng::Rng;
+/// let mut a = Rng::new(42);
+/// let mut b = Rng::new(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+///
+/// // Continue from the saved state
+/// let mut c = Rng::from_bytes(a.to_bytes());
+/// assert_eq!(c.next_u64(), b.next_u64());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Restore a generator from its state.
+    pub fn from_bytes(state: [u8; 8]) -> Self {
+        Self(u64::from_le_bytes(state))
+    }
+
+    /// Get the state of the generator.
+    pub fn to_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Get the next random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number in range [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// Get a random number in range [0, `bound`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bound` is 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "bound is 0");
+        // Reject the values that would make some results more likely than others
+        let zone = u64::MAX - (u64::MAX - bound + 1) % bound;
+        loop {
+            let x = self.next_u64();
+            if x <= zone {
+                return x % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_sequence() {
+        // Reference values of SplitMix64 with seed 1234567
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+    }
+
+    #[test]
+    fn ranges_are_respected() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+            assert!(rng.below(3) < 3);
+        }
+        assert_eq!(rng.below(1), 0);
+    }
+}
@@ -1,13 +1,22 @@
 //! Extras aimed at storing common items.
-use std::collections::HashSet;
-use std::{hash::Hash, rc::Rc};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+#[cfg(feature = "io")]
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use std::{hash::Hash, ops::Deref, rc::Rc};
 
 use dasp::frame::Stereo;
 use ordered_float::OrderedFloat;
 use sealed::sealed;
 use slice_dst::SliceWithHeader;
 
-use crate::types::Sound;
+use crate::{
+    resource::{ResConfig, Resource, StringError},
+    types::Sound,
+};
 
 /// Trait for sets that contain [`Rc<T>`].
 ///
@@ -97,7 +106,14 @@ impl<T: ?Sized + Eq + Hash> SetRc<T> for HashSet<Rc<T>> {
 /// You won't probably need to use this type directly, see [`wrap_sound()`][SetRcSound::wrap_sound()]
 #[derive(Debug, Hash, Eq, PartialEq)]
 #[repr(transparent)]
-pub struct OrderedSound(SliceWithHeader<usize, Stereo<OrderedFloat<f32>>>);
+pub struct OrderedSound(SliceWithHeader<u32, Stereo<OrderedFloat<f32>>>);
+
+// Layout requirements for casting between `Sound` and `OrderedSound`, see `wrap_sound()`.
+const _: () = {
+    assert!(size_of::<OrderedFloat<f32>>() == size_of::<f32>());
+    assert!(align_of::<OrderedFloat<f32>>() == align_of::<f32>());
+    assert!(size_of::<Stereo<OrderedFloat<f32>>>() == size_of::<Stereo<f32>>());
+};
 
 /// Trait defined for `HashSet<Rc<OrderedSound>>` to allow using it to store [`Sound`] data.
 #[sealed]
@@ -138,10 +154,20 @@ pub trait SetRcSound {
 #[sealed]
 impl SetRcSound for HashSet<Rc<OrderedSound>> {
     fn wrap_sound(&mut self, value: Box<Sound>) -> Rc<Sound> {
-        // SAFETY: OrderedSound and Sound are transparent wrappers around
-        // SliceWithHeader<usize, T>, where T is a pair of f32 in one case and a
-        // 2x transparent wrapper around f32 in another, meaning that T has identical layout.
-        // SliceWithHeader has a defined layout, and thus both types have identical layout.
+        // SAFETY: the cast is sound because both types have identical layout:
+        // - `Sound` and `OrderedSound` are `#[repr(transparent)]` wrappers around
+        //   `SliceWithHeader<u32, Stereo<f32>>` and
+        //   `SliceWithHeader<u32, Stereo<OrderedFloat<f32>>>` respectively, so each has
+        //   the layout of the wrapped type.
+        // - `OrderedFloat<T>` is `#[repr(transparent)]` over `T`, so it has the same size,
+        //   alignment and bit validity as `f32` (asserted at compile time above), and
+        //   `Stereo<T>` is `[T; 2]`, so the items have identical layout as well.
+        // - `SliceWithHeader` is `#[repr(C)]` (length, header, then the items), so its
+        //   layout is fully determined by the layouts of the header and the item types,
+        //   and does not depend on the compiler version. Both types use `u32` as header.
+        // Every bit pattern of `f32` is a valid `OrderedFloat<f32>` and vice versa, and
+        // the metadata (slice length) is kept by the pointer casts, so the allocation is
+        // freed with the layout it was allocated with.
         unsafe {
             //convert to OrderedSound
             let new = Box::from_raw(Box::into_raw(value) as *mut OrderedSound);
@@ -153,9 +179,235 @@ impl SetRcSound for HashSet<Rc<OrderedSound>> {
     }
 }
 
+/// Config that was accepted by a resource.
+///
+/// It can only be made by [`ValidationCache::validate()`], so a function that takes
+/// it does not need to check the config again.
+#[derive(Debug, Clone)]
+pub struct ValidatedConfig(Rc<ResConfig>);
+
+impl ValidatedConfig {
+    /// Get the config.
+    pub fn config(&self) -> &Rc<ResConfig> {
+        &self.0
+    }
+}
+
+impl Deref for ValidatedConfig {
+    type Target = ResConfig;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Remembers which configs were accepted by which resources, so that
+/// [`check_config()`][Resource::check_config()] is called once per config.
+///
+/// Configs are told apart by their [`Rc`], and since they are immutable, a cached
+/// result never becomes stale. The cache keeps the configs alive.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "builtin")] {
+/// # use std::rc::Rc;
+/// # use mleml::extra::storage::ValidationCache;
+/// # use mleml::extra::builtin::ConvertNote;
+/// # use mleml::resource::JsonArray;
+/// let mut cache = ValidationCache::new();
+/// let conf = Rc::new(JsonArray::new());
+/// assert!(cache.validate(&ConvertNote(), conf).is_err());
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ValidationCache {
+    validated: HashMap<(String, *const ResConfig), Rc<ResConfig>>,
+}
+
+impl ValidationCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the config with the resource, unless it was already accepted by a
+    /// resource with the same ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`check_config()`][Resource::check_config()], prefixed
+    /// with the resource's ID. Rejected configs are not remembered.
+    pub fn validate(
+        &mut self,
+        resource: &dyn Resource,
+        conf: Rc<ResConfig>,
+    ) -> Result<ValidatedConfig, StringError> {
+        let key = (resource.id().to_string(), Rc::as_ptr(&conf));
+        if let Entry::Vacant(entry) = self.validated.entry(key) {
+            resource
+                .check_config(&conf)
+                .map_err(|e| StringError(format!("[{}] {}", resource.id(), e.0)))?;
+            entry.insert(conf.clone());
+        }
+        Ok(ValidatedConfig(conf))
+    }
+
+    /// Forget configs that are not used outside of the cache.
+    pub fn trim(&mut self) {
+        self.validated.retain(|_, conf| Rc::strong_count(conf) > 1);
+    }
+
+    /// Number of remembered configs.
+    pub fn len(&self) -> usize {
+        self.validated.len()
+    }
+
+    /// Returns `true` if no configs are remembered.
+    pub fn is_empty(&self) -> bool {
+        self.validated.is_empty()
+    }
+}
+
+/// Sample loaded by [`SampleBank`].
+#[cfg(feature = "io")]
+#[derive(Debug)]
+struct BankEntry {
+    sound: Rc<Sound>,
+    modified: Option<SystemTime>,
+    last_used: u64,
+}
+
+#[cfg(feature = "io")]
+impl BankEntry {
+    fn bytes(&self) -> usize {
+        std::mem::size_of_val(self.sound.data())
+    }
+}
+
+/// Lazily loaded WAV samples, shared as [`Rc<Sound>`] between
+/// [sample players][crate::extra::builtin::SamplePlayer].
+///
+/// A file is read on the first [`get()`][SampleBank::get()] and kept while it is not
+/// modified. When the loaded samples take more bytes than the budget, the least
+/// recently used samples that are not referenced outside of the bank are dropped.
+/// Samples that are in use are never dropped, so the bank can go over the budget.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use mleml::extra::storage::SampleBank;
+/// let mut bank = SampleBank::new(64 << 20);
+/// let kick = bank.get("drums/kick.wav").expect("can not load the sample");
+/// // Loaded once, shared afterwards
+/// assert!(std::rc::Rc::ptr_eq(&kick, &bank.get("drums/kick.wav").unwrap()));
+/// ```
+#[cfg(feature = "io")]
+#[derive(Debug)]
+pub struct SampleBank {
+    budget: usize,
+    samples: HashMap<PathBuf, BankEntry>,
+    clock: u64,
+}
+
+#[cfg(feature = "io")]
+impl SampleBank {
+    /// Create an empty bank that keeps up to `budget` bytes of unused samples.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            samples: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Get the sample from the file at `path`, reading it if it is not loaded or if
+    /// the file was modified since it was read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the path if the file can not be read or is not
+    /// a supported WAV file.
+    pub fn get(&mut self, path: impl AsRef<Path>) -> Result<Rc<Sound>, StringError> {
+        let path = path.as_ref();
+        let error = |e: &dyn std::fmt::Display| StringError(format!("{}: {e}", path.display()));
+        let modified = fs::metadata(path).map_err(|e| error(&e))?.modified().ok();
+        self.clock += 1;
+        if let Some(entry) = self.samples.get_mut(path) {
+            if entry.modified.is_some() && entry.modified == modified {
+                entry.last_used = self.clock;
+                return Ok(entry.sound.clone());
+            }
+        }
+        let file = fs::File::open(path).map_err(|e| error(&e))?;
+        let sound: Rc<Sound> = Sound::read_wav(io::BufReader::new(file))
+            .map_err(|e| error(&e))?
+            .into();
+        self.samples.insert(
+            path.to_path_buf(),
+            BankEntry {
+                sound: sound.clone(),
+                modified,
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+        Ok(sound)
+    }
+
+    /// Forget the sample from `path`, so that the next [`get()`][SampleBank::get()]
+    /// reads the file again. Returns `true` if the sample was loaded.
+    ///
+    /// Players that hold the old sample keep it.
+    pub fn invalidate(&mut self, path: impl AsRef<Path>) -> bool {
+        self.samples.remove(path.as_ref()).is_some()
+    }
+
+    /// Returns `true` if the sample from `path` is loaded.
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        self.samples.contains_key(path.as_ref())
+    }
+
+    /// Number of bytes that loaded samples take.
+    pub fn bytes(&self) -> usize {
+        self.samples.values().map(BankEntry::bytes).sum()
+    }
+
+    /// Number of loaded samples.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if no samples are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Drop least recently used unreferenced samples until the bank fits the budget.
+    fn evict(&mut self) {
+        let mut bytes = self.bytes();
+        while bytes > self.budget {
+            let Some(path) = self
+                .samples
+                .iter()
+                .filter(|(_, x)| Rc::strong_count(&x.sound) == 1)
+                .min_by_key(|(_, x)| x.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            bytes -= self.samples.remove(&path).unwrap().bytes();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::ptr;
+    use std::{cell::Cell, ptr};
+
+    use serde_json::json;
+
+    use crate::{resource::JsonArray, test_util::Counted};
 
     use super::*;
 
@@ -186,6 +438,23 @@ mod tests {
         //Only rc1, rc3 and rc5 remain as their strong count is 2
         assert_eq!(set.len(), 3);
     }
+    #[test]
+    fn wrapped_sound_is_bit_identical() {
+        let data: Box<[Stereo<f32>]> = Box::new([[0.5, -0.25], [f32::MIN_POSITIVE, -0.0]]);
+        let bits =
+            |x: &[Stereo<f32>]| -> Vec<u32> { x.iter().flatten().map(|x| x.to_bits()).collect() };
+        let mut set: HashSet<Rc<OrderedSound>> = HashSet::new();
+        let wrapped = set.wrap_sound(Sound::new(data.clone(), 44100));
+        assert_eq!(wrapped.sampling_rate(), 44100);
+        assert_eq!(bits(wrapped.data()), bits(&data));
+        // The stored OrderedSound sees the same header and items
+        let stored = set.iter().next().unwrap();
+        assert_eq!(stored.0.header, 44100);
+        let stored_data: Vec<Stereo<f32>> =
+            stored.0.slice.iter().map(|x| [x[0].0, x[1].0]).collect();
+        assert_eq!(bits(&stored_data), bits(&data));
+    }
+
     #[test]
     fn wrapping_does_not_insert_duplicate_data() {
         let s1: &str = "One";
@@ -207,4 +476,132 @@ mod tests {
         //The RC has to have 3 instances (r1, r2 and in the set)
         assert_eq!(Rc::strong_count(&r2), 3);
     }
+
+    #[test]
+    fn canonical_configs_are_deduplicated() {
+        let written = [json!([1, 1000, 0.5, "a"]), json!([1.0, 1e3, 0.5, "a"])];
+        let mut set: HashSet<Rc<JsonArray>> = HashSet::new();
+        let rcs: Vec<Rc<JsonArray>> = written
+            .into_iter()
+            .map(|x| {
+                let mut conf = JsonArray::from_value(x).unwrap();
+                conf.canonicalize();
+                set.wrap(Box::new(conf))
+            })
+            .collect();
+        assert_eq!(set.len(), 1);
+        assert!(Rc::ptr_eq(&rcs[0], &rcs[1]));
+        assert_eq!(rcs[0].get_f64(2), Some(0.5));
+    }
+
+    #[test]
+    fn config_is_checked_once() {
+        let res = Counted(Cell::new(0));
+        let mut cache = ValidationCache::new();
+        let conf = Rc::new(JsonArray::from_values([json!(3)]).unwrap());
+        for _ in 0..100 {
+            let validated = cache.validate(&res, conf.clone()).unwrap();
+            assert_eq!(*validated, *conf);
+        }
+        assert_eq!(res.0.get(), 1);
+        // Equal config in a different Rc is checked again
+        let copy = Rc::new(JsonArray::from_values([json!(3)]).unwrap());
+        cache.validate(&res, copy).unwrap();
+        assert_eq!(res.0.get(), 2);
+        assert_eq!(cache.len(), 2);
+        cache.trim();
+        assert_eq!(cache.len(), 1);
+    }
+
+    /// Directory for WAV files of one test.
+    #[cfg(feature = "io")]
+    fn sample_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mleml-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(feature = "io")]
+    fn write_sample(path: &Path, value: f32, len: usize) {
+        use crate::extra::wav::WavSampleFormat;
+
+        let sound = Sound::new(vec![[value, value]; len].into_boxed_slice(), 48000);
+        let file = std::fs::File::create(path).unwrap();
+        sound.write_wav(file, WavSampleFormat::F32).unwrap();
+    }
+
+    #[cfg(all(feature = "io", feature = "builtin"))]
+    #[test]
+    fn sample_players_share_bank_samples() {
+        use crate::extra::builtin::SamplePlayer;
+
+        let dir = sample_dir("share");
+        let path = dir.join("kick.wav");
+        write_sample(&path, 0.5, 100);
+        let mut bank = SampleBank::new(1 << 20);
+        let a = SamplePlayer::new(vec![bank.get(&path).unwrap()]);
+        let b = SamplePlayer::new(vec![bank.get(&path).unwrap()]);
+        assert!(Rc::ptr_eq(&a.samples()[0], &b.samples()[0]));
+        assert_eq!(a.samples()[0].data(), [[0.5, 0.5]; 100]);
+        assert_eq!(bank.len(), 1);
+        assert!(bank.get(dir.join("missing.wav")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn bank_evicts_least_recently_used_unreferenced_sample() {
+        let dir = sample_dir("evict");
+        let paths: Vec<_> = ["a", "b", "c", "d"]
+            .map(|x| dir.join(format!("{x}.wav")))
+            .into();
+        for path in &paths {
+            write_sample(path, 0.25, 100);
+        }
+        // 100 stereo f32 frames take 800 bytes, so two samples fit
+        let mut bank = SampleBank::new(1600);
+        bank.get(&paths[0]).unwrap();
+        bank.get(&paths[1]).unwrap();
+        bank.get(&paths[0]).unwrap();
+        bank.get(&paths[2]).unwrap();
+        assert!(bank.contains(&paths[0]));
+        assert!(!bank.contains(&paths[1]));
+        assert!(bank.contains(&paths[2]));
+
+        // Samples in use are kept even over the budget
+        let _held = [bank.get(&paths[0]).unwrap(), bank.get(&paths[2]).unwrap()];
+        bank.get(&paths[3]).unwrap();
+        assert_eq!(bank.len(), 3);
+        assert_eq!(bank.bytes(), 2400);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn invalidated_sample_is_read_again() {
+        let dir = sample_dir("invalidate");
+        let path = dir.join("snare.wav");
+        write_sample(&path, 0.5, 10);
+        let mut bank = SampleBank::new(1 << 20);
+        let old = bank.get(&path).unwrap();
+        write_sample(&path, -0.5, 20);
+        assert!(bank.invalidate(&path));
+        assert!(!bank.invalidate(&path));
+        let new = bank.get(&path).unwrap();
+        assert_eq!(old.data(), [[0.5, 0.5]; 10]);
+        assert_eq!(new.data(), [[-0.5, -0.5]; 20]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_config_is_rejected_with_id() {
+        let res = Counted(Cell::new(0));
+        let mut cache = ValidationCache::new();
+        let conf = Rc::new(JsonArray::from_values([json!("three")]).unwrap());
+        let err = cache.validate(&res, conf.clone()).unwrap_err();
+        assert_eq!(err.0, "[COUNTED] expected one integer");
+        assert!(cache.is_empty());
+        assert!(cache.validate(&res, conf).is_err());
+        assert_eq!(res.0.get(), 2);
+    }
 }
@@ -0,0 +1,273 @@
+//! Reading and writing sounds as WAV files.
+
+use std::io::{self, Read, Write};
+
+use thiserror::Error;
+
+use super::export::{from_pcm, to_pcm, PcmFormat, SampleFormat};
+use crate::types::Sound;
+
+/// Error of reading a WAV file.
+#[derive(Error, Debug)]
+pub enum SoundIoError {
+    /// Reading failed.
+    #[error("could not read the file: {0}")]
+    Io(#[from] io::Error),
+
+    /// The file is not a valid WAV file.
+    #[error("not a valid WAV file: {0}")]
+    Malformed(String),
+
+    /// The file is valid, but its format is not supported.
+    #[error("unsupported format: {0}")]
+    Unsupported(String),
+}
+
+/// Type of samples in a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WavSampleFormat {
+    /// Signed 16-bit integer.
+    I16,
+    /// 32-bit float.
+    F32,
+}
+
+impl WavSampleFormat {
+    /// Sample format of the PCM data.
+    fn sample(self) -> SampleFormat {
+        match self {
+            WavSampleFormat::I16 => SampleFormat::S16Le,
+            WavSampleFormat::F32 => SampleFormat::F32Le,
+        }
+    }
+
+    /// Format tag of the `fmt ` chunk.
+    fn tag(self) -> u16 {
+        match self {
+            WavSampleFormat::I16 => 1,
+            WavSampleFormat::F32 => 3,
+        }
+    }
+}
+
+/// Number of channels that are written.
+const CHANNELS: u16 = 2;
+
+impl Sound {
+    /// Write the sound as a stereo WAV file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails, or if the sound is too long for WAV's
+    /// 32-bit sizes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::extra::wav::WavSampleFormat;
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.5, -0.5]; 10]), 48000);
+    /// let mut file = Vec::new();
+    /// sound.write_wav(&mut file, WavSampleFormat::I16).expect("can not write the sound");
+    /// assert_eq!(file.len(), 44 + 10 * 4);
+    /// ```
+    pub fn write_wav<W: Write>(&self, mut writer: W, format: WavSampleFormat) -> io::Result<()> {
+        let pcm_format = PcmFormat {
+            sample: format.sample(),
+            stereo: true,
+        };
+        let data = to_pcm(self, pcm_format, false);
+        let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "sound is too long for WAV");
+        let data_len = u32::try_from(data.len()).map_err(|_| too_long())?;
+        let riff_len = data_len.checked_add(36).ok_or_else(too_long)?;
+        let block_align = pcm_format.frame_bytes() as u16;
+        let bits = format.sample().bytes() as u16 * 8;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&riff_len.to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&16_u32.to_le_bytes())?;
+        writer.write_all(&format.tag().to_le_bytes())?;
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&self.sampling_rate().to_le_bytes())?;
+        let byte_rate = self.sampling_rate() * block_align as u32;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&bits.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&data_len.to_le_bytes())?;
+        writer.write_all(&data)
+    }
+
+    /// Read a WAV file with 16-bit integer or 32-bit float samples.
+    ///
+    /// Mono files are played on both sides. Chunks other than `fmt ` and `data` are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, if the file is not a valid WAV file, or if
+    /// it has other sample types or more than two channels.
+    pub fn read_wav<R: Read>(mut reader: R) -> Result<Box<Sound>, SoundIoError> {
+        let malformed = |what: &str| SoundIoError::Malformed(what.to_string());
+        let mut file = Vec::new();
+        reader.read_to_end(&mut file)?;
+        if file.len() < 12 || &file[0..4] != b"RIFF" || &file[8..12] != b"WAVE" {
+            return Err(malformed("no RIFF/WAVE header"));
+        }
+        let mut format = None;
+        let mut rest = &file[12..];
+        while rest.len() >= 8 {
+            let (id, len) = (&rest[0..4], u32_at(rest, 4) as usize);
+            let body = rest
+                .get(8..8 + len)
+                .ok_or_else(|| malformed("chunk is longer than the file"))?;
+            match id {
+                b"fmt " => format = Some(read_format(body)?),
+                b"data" => {
+                    let (pcm_format, rate) =
+                        format.ok_or_else(|| malformed("data chunk is before fmt chunk"))?;
+                    return from_pcm(body, pcm_format, rate)
+                        .map_err(|e| SoundIoError::Malformed(e.0));
+                }
+                _ => (),
+            }
+            // Chunks are padded to an even length
+            rest = rest.get(8 + len + len % 2..).unwrap_or_default();
+        }
+        Err(malformed("no data chunk"))
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Parse the body of `fmt ` chunk into the format of samples and sampling rate.
+fn read_format(body: &[u8]) -> Result<(PcmFormat, u32), SoundIoError> {
+    if body.len() < 16 {
+        return Err(SoundIoError::Malformed(
+            "fmt chunk is too short".to_string(),
+        ));
+    }
+    let (tag, channels, rate, bits) = (
+        u16_at(body, 0),
+        u16_at(body, 2),
+        u32_at(body, 4),
+        u16_at(body, 14),
+    );
+    let sample = match (tag, bits) {
+        (1, 16) => SampleFormat::S16Le,
+        (3, 32) => SampleFormat::F32Le,
+        _ => {
+            return Err(SoundIoError::Unsupported(format!(
+                "format tag {tag} with {bits}-bit samples"
+            )))
+        }
+    };
+    let stereo = match channels {
+        1 => false,
+        2 => true,
+        _ => return Err(SoundIoError::Unsupported(format!("{channels} channels"))),
+    };
+    Ok((PcmFormat { sample, stereo }, rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_describes_data() {
+        let sound = Sound::new(Box::new([[0.25, -0.25]; 100]), 44100);
+        for (format, tag, bits) in [(WavSampleFormat::I16, 1, 16), (WavSampleFormat::F32, 3, 32)] {
+            let mut file = Vec::new();
+            sound.write_wav(&mut file, format).unwrap();
+            let data_len = 100 * 2 * bits as usize / 8;
+            assert_eq!(&file[0..4], b"RIFF");
+            assert_eq!(u32_at(&file, 4) as usize, file.len() - 8);
+            assert_eq!(&file[8..16], b"WAVEfmt ");
+            assert_eq!(u32_at(&file, 16), 16);
+            assert_eq!(u16_at(&file, 20), tag);
+            assert_eq!(u16_at(&file, 22), 2);
+            assert_eq!(u32_at(&file, 24), 44100);
+            assert_eq!(u32_at(&file, 28), 44100 * 2 * bits as u32 / 8);
+            assert_eq!(u16_at(&file, 32), 2 * bits / 8);
+            assert_eq!(u16_at(&file, 34), bits);
+            assert_eq!(&file[36..40], b"data");
+            assert_eq!(u32_at(&file, 40) as usize, data_len);
+            assert_eq!(file.len(), 44 + data_len);
+        }
+    }
+
+    #[test]
+    fn wav_round_trips() {
+        let sound = Sound::new(Box::new([[0.25, -0.5], [1.0, 0.0], [-1.0, 0.125]]), 22050);
+        let mut file = Vec::new();
+        sound.write_wav(&mut file, WavSampleFormat::F32).unwrap();
+        assert_eq!(*Sound::read_wav(file.as_slice()).unwrap(), *sound);
+
+        let mut file = Vec::new();
+        sound.write_wav(&mut file, WavSampleFormat::I16).unwrap();
+        let back = Sound::read_wav(file.as_slice()).unwrap();
+        assert_eq!(back.sampling_rate(), 22050);
+        for (a, b) in sound.data().iter().zip(back.data()) {
+            assert!((a[0] - b[0]).abs() <= 1.0 / 32768.0 && (a[1] - b[1]).abs() <= 1.0 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn mono_is_duplicated_and_chunks_are_skipped() {
+        let mut file = Vec::new();
+        file.extend(b"RIFF");
+        file.extend(0_u32.to_le_bytes());
+        file.extend(b"WAVEfmt ");
+        file.extend(16_u32.to_le_bytes());
+        for x in [1_u16, 1] {
+            file.extend(x.to_le_bytes());
+        }
+        file.extend(8000_u32.to_le_bytes());
+        file.extend(16000_u32.to_le_bytes());
+        for x in [2_u16, 16] {
+            file.extend(x.to_le_bytes());
+        }
+        // Odd-sized chunk with padding
+        file.extend(b"LIST");
+        file.extend(3_u32.to_le_bytes());
+        file.extend([0; 4]);
+        file.extend(b"data");
+        file.extend(4_u32.to_le_bytes());
+        for x in [16384_i16, -32768] {
+            file.extend(x.to_le_bytes());
+        }
+        let sound = Sound::read_wav(file.as_slice()).unwrap();
+        assert_eq!(sound.sampling_rate(), 8000);
+        assert_eq!(sound.data(), &[[0.5, 0.5], [-1.0, -1.0]]);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let sound = Sound::new(Box::new([[0.0, 0.0]]), 48000);
+        let mut file = Vec::new();
+        sound.write_wav(&mut file, WavSampleFormat::I16).unwrap();
+        // 8-bit samples
+        let mut unsupported = file.clone();
+        unsupported[34] = 8;
+        assert!(matches!(
+            Sound::read_wav(unsupported.as_slice()),
+            Err(SoundIoError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Sound::read_wav(&file[..30]),
+            Err(SoundIoError::Malformed(_))
+        ));
+        assert!(matches!(
+            Sound::read_wav(&b"RIFF\0\0\0\0AVI "[..]),
+            Err(SoundIoError::Malformed(_))
+        ));
+    }
+}
@@ -43,8 +43,12 @@
 // #![feature(rustdoc_missing_doc_code_examples)]
 // #![warn(rustdoc::missing_doc_code_examples)]
 
+pub mod prelude;
 pub mod resource;
 pub mod types;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
 //Feature-gating is in extra/mod.rs
 pub mod extra;
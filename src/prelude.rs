@@ -0,0 +1,26 @@
+//! Commonly used traits and types.
+//!
+//! Importing the prelude is enough to write a simple program:
+//!
+//! ```
+//! use mleml::prelude::*;
+//! ```
+//!
+//! Items that are re-exported here are considered stable: they are not removed or
+//! renamed without a major version bump. Items from [`extra`][crate::extra] are
+//! included only if their features are enabled.
+
+pub use crate::resource::{
+    Channel, ChannelCommand, JsonArray, LeftoverSound, MixInput, MixOutput, Mixer, Mod, ModData,
+    Pipeline, PipelineStateChanges, PremixBuilder, PremixedSound, ResConfig, ResState, Resource,
+    StringError,
+};
+pub use crate::types::{Note, ReadyNote, Sound};
+
+#[cfg(feature = "extra")]
+pub use crate::extra::config_builder::ConfigBuilder;
+
+#[cfg(feature = "builtin")]
+pub use crate::extra::builtin::{
+    sum_channels, ConvertNote, ConvertNoteParams, FourOpFm, SimpleChannel, SimpleMixer, SimpleMod,
+};
@@ -1,17 +1,25 @@
 //! This module provides Mod and Mixer traits.
 
 use crate::types::{Note, ReadyNote, Sound};
-use dasp::frame::Stereo;
 use sealed::sealed;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, to_vec};
 use std::{
+    fmt,
     hash::{Hash, Hasher},
     mem::Discriminant,
     rc::Rc,
 };
 use thiserror::Error;
 
+pub mod mixing;
+pub mod schema;
+
+pub use mixing::{
+    LeftoverSound, MixInput, MixOutput, PipelineStateChanges, PremixBuilder, PremixedSound,
+};
+pub use schema::{validate, ConfigField, ConfigSchema, FieldType, ValueConstraint};
+
 pub(crate) type JsonValue = serde_json::Value;
 
 ///Flat JSON array of arbitrary values.
@@ -50,14 +58,8 @@ impl JsonArray {
     /// let conf: JsonArray = JsonArray::from_values(vec).expect("Vector contains an array or an object");
     /// ```
     pub fn from_values<I: AsRef<[JsonValue]>>(items: I) -> Option<Self> {
-        match items
-            .as_ref()
-            .iter()
-            .any(|x| (x.is_array() | x.is_object()))
-        {
-            false => Some(Self(items.as_ref().into())),
-            true => None,
-        }
+        let items = items.as_ref();
+        is_flat(items).then(|| Self(items.into()))
     }
 
     /// Wrap JSON value as JsonArray as long as it is an array with no nested arrays
@@ -73,14 +75,7 @@ impl JsonArray {
     /// ```
     //TODO: accept borrowed and to_owned() them
     pub fn from_value(item: JsonValue) -> Option<Self> {
-        match item
-            .as_array()?
-            .iter()
-            .any(|x| (x.is_array() | x.is_object()))
-        {
-            false => Some(Self(item)),
-            true => None,
-        }
+        is_flat(item.as_array()?).then_some(Self(item))
     }
 
     /// Returns a slice of contained JSON values.
@@ -88,6 +83,49 @@ impl JsonArray {
         self.0.as_array().unwrap().as_slice()
     }
 
+    /// Returns an iterator over contained JSON values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::resource::JsonArray;
+    /// let conf = JsonArray::from_value(json!([1, 2, 3])).unwrap();
+    /// assert_eq!(conf.iter().filter_map(|x| x.as_i64()).sum::<i64>(), 6);
+    /// ```
+    pub fn iter(&self) -> std::slice::Iter<'_, JsonValue> {
+        self.as_slice().iter()
+    }
+
+    /// Collect JSON values into a JSON array, as long as no value is an array or
+    /// an object.
+    ///
+    /// This takes the place of a [`FromIterator`] implementation, which can not
+    /// return a `Result` for a type defined in this crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns the index of the first value that is an array or an object.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::resource::JsonArray;
+    /// let conf = JsonArray::try_from_iter((1..4).map(|x| json!(x))).unwrap();
+    /// assert_eq!(conf.len(), 3);
+    ///
+    /// let items = vec![json!(1), json!([2]), json!(3)];
+    /// assert_eq!(JsonArray::try_from_iter(items), Err(1));
+    /// ```
+    pub fn try_from_iter<I: IntoIterator<Item = JsonValue>>(iter: I) -> Result<Self, usize> {
+        let mut out = Self::new();
+        for (index, item) in iter.into_iter().enumerate() {
+            out.push(item).ok_or(index)?;
+        }
+        Ok(out)
+    }
+
     /// Get array's length.
     pub fn len(&self) -> usize {
         self.0.as_array().unwrap().len()
@@ -153,6 +191,11 @@ impl JsonArray {
         Some(())
     }
 
+    /// Calls [`Vec::clear()`], keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.0.as_array_mut().unwrap().clear()
+    }
+
     /// Calls [`Vec::remove()`].
     pub fn remove(&mut self, index: usize) -> JsonValue {
         self.0.as_array_mut().unwrap().remove(index)
@@ -172,23 +215,295 @@ impl JsonArray {
     where
         T: AsRef<[JsonValue]>,
     {
-        let target = self.0.as_array_mut().unwrap();
-        let source = items.as_ref().iter();
-        let source_len = source.len().clone();
-        for (index, item) in source.enumerate() {
-            if item.is_array() | item.is_object() {
-                return Err(index);
-            } else {
-                target.push(item.clone())
+        let items = items.as_ref();
+        for (index, item) in items.iter().enumerate() {
+            self.push(item.clone()).ok_or(index)?;
+        }
+        Ok(items.len())
+    }
+
+    /// Rewrite floats that hold an integer value as integers, so that arrays that
+    /// differ only in how their numbers were written compare and hash as equal.
+    ///
+    /// Floats with a fractional part and floats outside of the range of `i64` are
+    /// kept. `-0.0` becomes `0`.
+    ///
+    /// Equality and hashing of `JsonArray` do not do this on their own, so configs
+    /// should be canonicalized before they are stored with
+    /// [`SetRc::wrap()`][crate::extra::storage::SetRc::wrap] if they may come from
+    /// different sources.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::resource::JsonArray;
+    /// let mut a = JsonArray::from_value(json!([1.0, 1e3, 0.5])).unwrap();
+    /// let b = JsonArray::from_value(json!([1, 1000, 0.5])).unwrap();
+    /// assert_ne!(a, b);
+    /// a.canonicalize();
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn canonicalize(&mut self) {
+        for value in self.0.as_array_mut().unwrap() {
+            let Some(x) = value.as_f64() else { continue };
+            if value.is_f64() && x.fract() == 0.0 && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+                *value = json!(x as i64);
             }
         }
-        Ok(source_len)
     }
 
     /// Consumes the `JsonArray` and returns inner [`Value`][serde_json::Value].
     pub fn into_inner(self) -> JsonValue {
         self.0
     }
+
+    /// Find the values that differ between the array and `other`, as pairs of an
+    /// index and the value of `other`.
+    ///
+    /// Values of `other` past the end of the array are included, so applying the
+    /// result with [`apply_patch()`][JsonArray::apply_patch] turns the array into
+    /// `other`, unless `other` is shorter: patches can not remove values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::resource::JsonArray;
+    /// let mut old = JsonArray::from_value(json!([7, false, 3])).unwrap();
+    /// let new = JsonArray::from_value(json!([7, false, -2])).unwrap();
+    /// let patch = old.diff(&new);
+    /// assert_eq!(patch, [(2, json!(-2))]);
+    /// old.apply_patch(&patch).unwrap();
+    /// assert_eq!(old, new);
+    /// ```
+    pub fn diff(&self, other: &JsonArray) -> Vec<(usize, JsonValue)> {
+        let ours = self.as_slice();
+        other
+            .as_slice()
+            .iter()
+            .enumerate()
+            .filter(|(i, x)| ours.get(*i) != Some(*x))
+            .map(|(i, x)| (i, x.clone()))
+            .collect()
+    }
+
+    /// Set values at the indices of the patch, in order. A value with index equal to
+    /// the length of the array is appended to it.
+    ///
+    /// The array is only changed if the whole patch can be applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MissingValue`][ConfigError::MissingValue] if an index is past the end
+    /// of the array, and [`NotAllowed`][ConfigError::NotAllowed] if a value is an array
+    /// or an object.
+    pub fn apply_patch(&mut self, patch: &[(usize, JsonValue)]) -> Result<(), ConfigError> {
+        let mut len = self.len();
+        for (i, x) in patch {
+            if x.is_array() || x.is_object() {
+                return Err(ConfigError::NotAllowed(*i));
+            }
+            match (*i).cmp(&len) {
+                std::cmp::Ordering::Less => (),
+                std::cmp::Ordering::Equal => len += 1,
+                std::cmp::Ordering::Greater => return Err(ConfigError::MissingValue(*i)),
+            }
+        }
+        let values = self.0.as_array_mut().unwrap();
+        for (i, x) in patch {
+            match values.get_mut(*i) {
+                Some(value) => *value = x.clone(),
+                None => values.push(x.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the value at `idx` as a float. Integers are converted to floats.
+    ///
+    /// Returns None if there is no value at `idx` or if it is not a number.
+    pub fn get_f64(&self, idx: usize) -> Option<f64> {
+        self.as_slice().get(idx)?.as_f64()
+    }
+
+    /// Get the value at `idx` as an integer.
+    ///
+    /// Returns None if there is no value at `idx` or if it is not an integer that fits
+    /// into `i64`.
+    pub fn get_i64(&self, idx: usize) -> Option<i64> {
+        self.as_slice().get(idx)?.as_i64()
+    }
+
+    /// Get the value at `idx` as a bool.
+    pub fn get_bool(&self, idx: usize) -> Option<bool> {
+        self.as_slice().get(idx)?.as_bool()
+    }
+
+    /// Get the value at `idx` as a string.
+    pub fn get_str(&self, idx: usize) -> Option<&str> {
+        self.as_slice().get(idx)?.as_str()
+    }
+
+    /// Get the value at `idx` as a float, like [`get_f64()`][JsonArray::get_f64].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the index and the type of the value if there is no
+    /// value at `idx` or if it is not a number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_json::json;
+    /// # use mleml::resource::{ConfigError, JsonArray};
+    /// let conf = JsonArray::from_value(json!([0.5, "one"])).unwrap();
+    /// assert_eq!(conf.try_f64(0), Ok(0.5));
+    /// assert_eq!(
+    ///     conf.try_f64(1).unwrap_err().to_string(),
+    ///     "argument 2 is string, expected number"
+    /// );
+    /// assert_eq!(conf.try_f64(2), Err(ConfigError::MissingValue(2)));
+    /// ```
+    pub fn try_f64(&self, idx: usize) -> Result<f64, ConfigError> {
+        self.try_get(idx, JsonType::Number, JsonValue::as_f64)
+    }
+
+    /// Get the value at `idx` as an integer, like [`get_i64()`][JsonArray::get_i64].
+    ///
+    /// # Errors
+    ///
+    /// See [`try_f64()`][JsonArray::try_f64].
+    pub fn try_i64(&self, idx: usize) -> Result<i64, ConfigError> {
+        self.try_get(idx, JsonType::Number, JsonValue::as_i64)
+    }
+
+    /// Get the value at `idx` as a bool.
+    ///
+    /// # Errors
+    ///
+    /// See [`try_f64()`][JsonArray::try_f64].
+    pub fn try_bool(&self, idx: usize) -> Result<bool, ConfigError> {
+        self.try_get(idx, JsonType::Bool, JsonValue::as_bool)
+    }
+
+    /// Get the value at `idx` as a string.
+    ///
+    /// # Errors
+    ///
+    /// See [`try_f64()`][JsonArray::try_f64].
+    pub fn try_str(&self, idx: usize) -> Result<&str, ConfigError> {
+        self.try_get(idx, JsonType::String, JsonValue::as_str)
+    }
+
+    fn try_get<'a, T>(
+        &'a self,
+        idx: usize,
+        expected: JsonType,
+        get: impl FnOnce(&'a JsonValue) -> Option<T>,
+    ) -> Result<T, ConfigError> {
+        let value = self
+            .as_slice()
+            .get(idx)
+            .ok_or(ConfigError::MissingValue(idx))?;
+        get(value).ok_or_else(|| ConfigError::mismatch(idx, expected, value))
+    }
+}
+
+/// Check that none of the values is an array or an object.
+fn is_flat(items: &[JsonValue]) -> bool {
+    !items.iter().any(|x| x.is_array() || x.is_object())
+}
+
+/// Type of a JSON value, without its contents.
+///
+/// Displayed in lowercase, as in error messages.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::resource::JsonType;
+/// # use serde_json::json;
+/// assert_eq!(JsonType::from(&json!(4.5)), JsonType::Number);
+/// assert_eq!(JsonType::from(&json!(4)), JsonType::Number);
+/// assert_eq!(JsonType::from(&json!("a")).to_string(), "string");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JsonType {
+    /// `null`.
+    Null,
+    /// Boolean.
+    Bool,
+    /// Number, either an integer or a float.
+    Number,
+    /// String.
+    String,
+    /// Array.
+    Array,
+    /// Object.
+    Object,
+}
+
+impl From<&JsonValue> for JsonType {
+    fn from(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Null => JsonType::Null,
+            JsonValue::Bool(_) => JsonType::Bool,
+            JsonValue::Number(_) => JsonType::Number,
+            JsonValue::String(_) => JsonType::String,
+            JsonValue::Array(_) => JsonType::Array,
+            JsonValue::Object(_) => JsonType::Object,
+        }
+    }
+}
+
+impl fmt::Display for JsonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonType::Null => "null",
+            JsonType::Bool => "bool",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonArray {
+    type Item = &'a JsonValue;
+    type IntoIter = std::slice::Iter<'a, JsonValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Find the first index at which the type of a value in `given` differs from the
+/// type of the value in `reference`.
+///
+/// If one array is longer than the other, the length of the shorter one is returned,
+/// unless a deviation is found before it.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_json::json;
+/// # use mleml::resource::{json_array_find_deviation, JsonArray};
+/// let schema = JsonArray::from_value(json!([0, "a", true])).unwrap();
+/// let good = JsonArray::from_value(json!([7, "b", false])).unwrap();
+/// let bad = JsonArray::from_value(json!([7, 8, false])).unwrap();
+/// let short = JsonArray::from_value(json!([7])).unwrap();
+/// assert_eq!(json_array_find_deviation(&schema, &good), None);
+/// assert_eq!(json_array_find_deviation(&schema, &bad), Some(1));
+/// assert_eq!(json_array_find_deviation(&schema, &short), Some(1));
+/// ```
+pub fn json_array_find_deviation(reference: &JsonArray, given: &JsonArray) -> Option<usize> {
+    reference
+        .iter()
+        .zip(given)
+        .position(|(a, b)| JsonType::from(a) != JsonType::from(b))
+        .or_else(|| (reference.len() != given.len()).then(|| reference.len().min(given.len())))
 }
 
 impl AsRef<JsonValue> for JsonArray {
@@ -197,6 +512,8 @@ impl AsRef<JsonValue> for JsonArray {
     }
 }
 
+/// Hashes the serialized array, which agrees with equality: `1` and `1.0` are
+/// different. See [`JsonArray::canonicalize()`].
 impl Hash for JsonArray {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.as_byte_vec().hash(state);
@@ -214,14 +531,107 @@ pub type ResState = [u8];
 /// Configuration error.
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum ConfigError {
-    /// A value has an unexpected type.
-    //TODO: discriminant's debug output is Discriminant(int). Replace with something else.
-    #[error("type mismatch at {0}: expected {1:?}, got {2:?}")]
-    BadValue(u32, Discriminant<JsonValue>, Discriminant<JsonValue>),
-
     /// Configuration has incorrect length.
     #[error("length mismatch: expected {0}, got {1}")]
     BadLength(u32, u32),
+
+    /// There is no value at the index.
+    #[error("argument {} is missing", .0 + 1)]
+    MissingValue(usize),
+
+    /// A value has an unexpected type. Arguments are counted from 1 in the message.
+    #[error("argument {} is {actual}, expected {expected}", .index + 1)]
+    WrongType {
+        /// Index of the value.
+        index: usize,
+        /// Expected type.
+        expected: JsonType,
+        /// Type of the value.
+        actual: JsonType,
+    },
+
+    /// A number is outside of the range that the schema allows.
+    #[error("argument {} is outside of its range", .0 + 1)]
+    OutOfRange(usize),
+
+    /// A number is expected to be an integer, but it is not.
+    #[error("argument {} is not an integer", .0 + 1)]
+    NotInteger(usize),
+
+    /// A value is not one of the values that are allowed.
+    #[error("argument {} is not an allowed value", .0 + 1)]
+    NotAllowed(usize),
+}
+
+impl ConfigError {
+    /// Get the index of the value that the error is about, if there is one.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            ConfigError::BadLength(_, _) => None,
+            ConfigError::MissingValue(i)
+            | ConfigError::OutOfRange(i)
+            | ConfigError::NotAllowed(i)
+            | ConfigError::NotInteger(i)
+            | ConfigError::WrongType { index: i, .. } => Some(*i),
+        }
+    }
+
+    /// Error for `value` at `index` that does not fit where a value of type `expected`
+    /// is needed.
+    ///
+    /// A number of the right type does not fit if it is a float where an integer is
+    /// needed, or if it is too large.
+    pub(crate) fn mismatch(index: usize, expected: JsonType, value: &JsonValue) -> Self {
+        let actual = JsonType::from(value);
+        match actual == expected {
+            true if value.is_f64() => ConfigError::NotInteger(index),
+            true => ConfigError::OutOfRange(index),
+            false => ConfigError::WrongType {
+                index,
+                expected,
+                actual,
+            },
+        }
+    }
+
+    /// Convert into [`StringError`], with `name` of the argument after its number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::resource::ConfigError;
+    /// let err = ConfigError::MissingValue(0).named("octave");
+    /// assert_eq!(err.0, "argument 1 (octave) is missing");
+    /// ```
+    pub fn named(&self, name: &str) -> StringError {
+        StringError(match self {
+            ConfigError::MissingValue(i) => format!("argument {} ({name}) is missing", i + 1),
+            ConfigError::WrongType {
+                index,
+                expected,
+                actual,
+            } => format!(
+                "argument {} ({name}) is {actual}, expected {expected}",
+                index + 1
+            ),
+            ConfigError::OutOfRange(i) => {
+                format!("argument {} ({name}) is outside of its range", i + 1)
+            }
+            ConfigError::NotAllowed(i) => {
+                format!("argument {} ({name}) is not an allowed value", i + 1)
+            }
+            ConfigError::NotInteger(i) => {
+                format!("argument {} ({name}) is not an integer", i + 1)
+            }
+            other => other.to_string(),
+        })
+    }
+}
+
+impl From<ConfigError> for StringError {
+    fn from(value: ConfigError) -> Self {
+        StringError(value.to_string())
+    }
 }
 
 //TODO: use Cow? Would this be significant?
@@ -246,6 +656,155 @@ pub trait Resource {
 
     ///Get resource's description.
     fn description(&self) -> &str;
+
+    ///Version of the config and state formats used by the resource.
+    ///
+    ///Resources that change the layout of their config or state should increase it
+    ///and override [`migrate_config()`][Resource::migrate_config()] and
+    ///[`migrate_state()`][Resource::migrate_state()].
+    fn schema_version(&self) -> u32 {
+        1
+    }
+
+    ///Convert config that was made for `old_version` of the resource into the current format.
+    ///
+    ///Default implementation returns the config unchanged if `old_version` is the
+    ///current [`schema_version()`][Resource::schema_version()], and an error otherwise,
+    ///since the resource does not know how to upgrade it.
+    fn migrate_config(&self, old_version: u32, conf: ResConfig) -> Result<ResConfig, StringError> {
+        let current = self.schema_version();
+        match old_version == current {
+            true => Ok(conf),
+            false => Err(StringError(format!(
+                "can not migrate config from version {old_version} to {current}"
+            ))),
+        }
+    }
+
+    ///Convert state that was made for `old_version` of the resource into the current format.
+    ///
+    ///Like [`migrate_config()`][Resource::migrate_config()], default implementation
+    ///returns the state unchanged if `old_version` is the current
+    ///[`schema_version()`][Resource::schema_version()], and an error otherwise.
+    fn migrate_state(
+        &self,
+        old_version: u32,
+        state: Box<ResState>,
+    ) -> Result<Box<ResState>, StringError> {
+        let current = self.schema_version();
+        match old_version == current {
+            true => Ok(state),
+            false => Err(StringError(format!(
+                "can not migrate state from version {old_version} to {current}"
+            ))),
+        }
+    }
+
+    ///State that the resource starts from.
+    ///
+    ///Default implementation returns empty state.
+    fn initial_state(&self) -> Box<ResState> {
+        Box::new([])
+    }
+
+    ///Names, types and descriptions of config's values.
+    ///
+    ///Default implementation returns None, meaning that the config is not described.
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        None
+    }
+}
+
+impl<T: Resource + ?Sized> Resource for Box<T> {
+    fn orig_name(&self) -> &str {
+        (**self).orig_name()
+    }
+
+    fn id(&self) -> &str {
+        (**self).id()
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        (**self).check_config(conf)
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (**self).check_state(state)
+    }
+
+    fn description(&self) -> &str {
+        (**self).description()
+    }
+
+    fn schema_version(&self) -> u32 {
+        (**self).schema_version()
+    }
+
+    fn migrate_config(&self, old_version: u32, conf: ResConfig) -> Result<ResConfig, StringError> {
+        (**self).migrate_config(old_version, conf)
+    }
+
+    fn migrate_state(
+        &self,
+        old_version: u32,
+        state: Box<ResState>,
+    ) -> Result<Box<ResState>, StringError> {
+        (**self).migrate_state(old_version, state)
+    }
+
+    fn initial_state(&self) -> Box<ResState> {
+        (**self).initial_state()
+    }
+
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        (**self).config_schema()
+    }
+}
+
+impl<T: Resource + ?Sized> Resource for Rc<T> {
+    fn orig_name(&self) -> &str {
+        (**self).orig_name()
+    }
+
+    fn id(&self) -> &str {
+        (**self).id()
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        (**self).check_config(conf)
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (**self).check_state(state)
+    }
+
+    fn description(&self) -> &str {
+        (**self).description()
+    }
+
+    fn schema_version(&self) -> u32 {
+        (**self).schema_version()
+    }
+
+    fn migrate_config(&self, old_version: u32, conf: ResConfig) -> Result<ResConfig, StringError> {
+        (**self).migrate_config(old_version, conf)
+    }
+
+    fn migrate_state(
+        &self,
+        old_version: u32,
+        state: Box<ResState>,
+    ) -> Result<Box<ResState>, StringError> {
+        (**self).migrate_state(old_version, state)
+    }
+
+    fn initial_state(&self) -> Box<ResState> {
+        (**self).initial_state()
+    }
+
+    fn config_schema(&self) -> Option<ConfigSchema> {
+        (**self).config_schema()
+    }
 }
 
 impl Hash for dyn Resource {
@@ -254,14 +813,6 @@ impl Hash for dyn Resource {
     }
 }
 
-/// Type to hold unused bits of sound.
-pub type LeftoverSound<'a> = Box<[Option<&'a [Stereo<f32>]>]>;
-
-/// Input type for the mixer.
-///
-/// Each sound has a flag to indicate whether it is a new sound or not.
-pub type PremixedSound<'a> = &'a [(bool, &'a [Stereo<f32>])];
-
 /// Mixer combines multiple sounds into one, returning it together with unused sound pieces.
 pub trait Mixer<'a>: Resource {
     /// Get mixer values as JSON array.
@@ -271,6 +822,10 @@ pub trait Mixer<'a>: Resource {
     ///
     /// It is expected that the leftover sound bits from before are not shuffled around,
     /// as the mixer may depend on their position.
+    ///
+    /// Mixers should accumulate channels in a fixed order and with enough precision
+    /// that the output is reproducible. `extra::builtin::sum_channels()` is a helper
+    /// that does this.
     fn mix(
         &self,
         channels: PremixedSound<'a>,
@@ -278,6 +833,33 @@ pub trait Mixer<'a>: Resource {
         conf: &ResConfig,
         state: &ResState,
     ) -> Result<(Box<Sound>, Box<ResState>, LeftoverSound<'a>), StringError>;
+
+    /// Same as [`mix()`][Mixer::mix()], but with arguments and results that can get
+    /// new fields without breaking the signature.
+    fn mix_structured(
+        &self,
+        input: MixInput<'a>,
+        conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<MixOutput<'a>, StringError> {
+        self.mix(input.channels, input.play_time, conf, state)
+            .map(MixOutput::from)
+    }
+
+    /// Sampling rate of the sound that the mixer produces.
+    ///
+    /// Default is 48000, which is what the builtin resources use.
+    fn sampling_rate(&self) -> u32 {
+        48000
+    }
+
+    /// Produce `samples` frames of silence at mixer's [sampling rate][Mixer::sampling_rate()].
+    fn mix_silent(&self, samples: usize) -> Box<Sound> {
+        Sound::new(
+            vec![[0.0, 0.0]; samples].into_boxed_slice(),
+            self.sampling_rate(),
+        )
+    }
 }
 
 /// Types that the mods can process.
@@ -293,6 +875,12 @@ pub enum ModData {
 
     /// Sound
     Sound(Box<Sound>),
+
+    /// Notes that start at the same time, such as a chord
+    Notes(Vec<Note>),
+
+    /// ReadyNotes that start at the same time, such as a chord
+    ReadyNotes(Vec<ReadyNote>),
 }
 
 impl ModData {
@@ -328,6 +916,22 @@ impl ModData {
         matches!(self, Self::Sound(..))
     }
 
+    /// Returns `true` if the mod data is [`Notes`].
+    ///
+    /// [`Notes`]: ModData::Notes
+    #[must_use]
+    pub fn is_notes(&self) -> bool {
+        matches!(self, Self::Notes(..))
+    }
+
+    /// Returns `true` if the mod data is [`ReadyNotes`].
+    ///
+    /// [`ReadyNotes`]: ModData::ReadyNotes
+    #[must_use]
+    pub fn is_ready_notes(&self) -> bool {
+        matches!(self, Self::ReadyNotes(..))
+    }
+
     /// If the value is a String, returns it, otherwise returns None.
     pub fn as_string(&self) -> Option<&str> {
         if let Self::String(v) = self {
@@ -363,6 +967,24 @@ impl ModData {
             None
         }
     }
+
+    /// If the value is Notes, returns them, otherwise returns None.
+    pub fn as_notes(&self) -> Option<&[Note]> {
+        if let Self::Notes(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// If the value is ReadyNotes, returns them, otherwise returns None.
+    pub fn as_ready_notes(&self) -> Option<&[ReadyNote]> {
+        if let Self::ReadyNotes(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
 }
 
 /// Mods are used to produce new data from given data.
@@ -380,6 +1002,22 @@ pub trait Mod: Resource {
 
     /// Discriminant of type that this mod will produce.
     fn output_type(&self) -> Discriminant<ModData>;
+
+    /// Mod's display name.
+    ///
+    /// Same as [`Resource::orig_name()`].
+    fn name(&self) -> &str {
+        self.orig_name()
+    }
+
+    /// How long the mod keeps producing sound after its input becomes silent, in
+    /// seconds, for example the length of an echo.
+    ///
+    /// Default implementation returns 0.
+    fn tail_seconds(&self, conf: &ResConfig) -> f32 {
+        let _ = conf;
+        0.0
+    }
 }
 
 /// Error type for pipeline.
@@ -394,7 +1032,7 @@ pub enum PipelineError {
     PipelineBroken(usize),
 
     //TODO: should additional info be given? (allowed input and output, position)
-    /// Inserting the mod will break the pipeline
+    /// Inserting or replacing the mod will break the pipeline
     #[error("inserting mod will break the pipeline")]
     InsertBreaksPipeline,
 }
@@ -404,9 +1042,40 @@ pub enum PipelineError {
 pub trait Pipeline {
     /// Insert a [`Mod`] into the pipeline, making sure that it does not break the pipeline or
     /// alter pipeline's input and output types.
+    ///
+    /// Any mod can be inserted into an empty pipeline. Otherwise, a mod that changes
+    /// the type (its input type differs from its output type) can only be inserted
+    /// between two mods whose types it connects. This means that a converting mod
+    /// cannot be added at the start or at the end of a non-empty pipeline, even if it
+    /// would produce what the pipeline accepts: that would change pipeline's input or
+    /// output type, which is intentionally not allowed. To change them, build a new
+    /// pipeline instead.
     //TODO: usage example (will require multiple mods)
     fn insert_checked(&mut self, index: usize, item: Rc<dyn Mod>) -> Result<(), PipelineError>;
 
+    /// Replace the [`Mod`] at `index`, returning the old one.
+    ///
+    /// The new mod has to have the same input and output types as the old one, so
+    /// that the pipeline stays exactly as valid as it was.
+    ///
+    /// Default implementation works on any pipeline that can be viewed as a mutable
+    /// slice of mods.
+    fn replace_checked(
+        &mut self,
+        index: usize,
+        item: Rc<dyn Mod>,
+    ) -> Result<Rc<dyn Mod>, PipelineError>
+    where
+        Self: AsMut<[Rc<dyn Mod>]>,
+    {
+        let mods = self.as_mut();
+        let old = mods.get(index).ok_or(PipelineError::IndexOutsideRange)?;
+        if (item.input_type() != old.input_type()) || (item.output_type() != old.output_type()) {
+            return Err(PipelineError::InsertBreaksPipeline);
+        }
+        Ok(std::mem::replace(&mut mods[index], item))
+    }
+
     /// Check that the pipeline is valid (each mod produces the type that the next mod accepts).
     fn is_valid(&self) -> Result<(), PipelineError>;
 
@@ -452,7 +1121,7 @@ impl Pipeline for Vec<Rc<dyn Mod>> {
             }
 
             // Mod is inserted at the start
-            _ if (index == 0) == (item.input_type() == self[0].input_type()) => {
+            _ if (index == 0) && (item.output_type() == self[0].input_type()) => {
                 self.insert(0, item);
                 Ok(())
             }
@@ -470,8 +1139,8 @@ impl Pipeline for Vec<Rc<dyn Mod>> {
     }
 
     fn is_valid(&self) -> Result<(), PipelineError> {
-        for i in 0..self.len() - 1 {
-            if self[i].output_type() != self[i + 1].input_type() {
+        for (i, pair) in self.windows(2).enumerate() {
+            if pair[0].output_type() != pair[1].input_type() {
                 return Err(PipelineError::PipelineBroken(i));
             }
         }
@@ -501,12 +1170,43 @@ impl Pipeline for Vec<Rc<dyn Mod>> {
     }
 }
 
-/// Type to hold every newly created state when the pipeline is used
-pub type PipelineStateChanges = Vec<Box<ResState>>;
+/// Command that changes channel's state, like MML's `>` or `l8`.
+///
+/// Commands are applied with [`Channel::apply_command()`], between the notes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChannelCommand {
+    /// Raise the octave by one.
+    OctaveUp,
+
+    /// Lower the octave by one.
+    OctaveDown,
+
+    /// Set the octave.
+    Octave(u8),
+
+    /// Set the length, in ticks, of notes that do not specify it.
+    DefaultLength(u16),
+
+    /// Set the volume.
+    Volume(u8),
+
+    /// Set the detune, in cents.
+    Detune(i16),
+
+    /// Set the duration of the sound after the note has been released, in ticks.
+    PostRelease(u8),
+
+    /// Command that has no standard meaning, to be interpreted by the channel.
+    Raw(String),
+}
 
 /// Channels are expected to pass their input through a pipeline of mods.
 pub trait Channel: Resource {
     /// Pass the data through the channel
+    ///
+    /// Every error returned by this function is expected to be prefixed with
+    /// channel's name, as in `format!("[{}] ", self.name())`, so that it is clear which
+    /// channel has failed.
     fn play(
         &self,
         item: ModData,
@@ -519,6 +1219,45 @@ pub trait Channel: Resource {
 
     /// Type that the channel returns
     fn output_type(&self) -> Discriminant<ModData>;
+
+    /// Get the sound that the channel's effects still produce after the last note,
+    /// up to `max_seconds` long, at `sampling_rate`.
+    ///
+    /// Hosts are expected to call it after the last note with the rate of the sounds
+    /// the channel rendered, so that echoes and other tails are not cut. Default
+    /// implementation returns `None`, meaning that there is no tail.
+    fn flush_tail(
+        &self,
+        state: &ResState,
+        config: &ResConfig,
+        max_seconds: f32,
+        sampling_rate: u32,
+    ) -> Result<Option<Box<Sound>>, StringError> {
+        let _ = (state, config, max_seconds, sampling_rate);
+        Ok(None)
+    }
+
+    /// Apply a command to the channel's state, returning the new state.
+    ///
+    /// Default implementation supports no commands and always returns an error.
+    fn apply_command(
+        &self,
+        command: &ChannelCommand,
+        state: &ResState,
+    ) -> Result<Box<ResState>, StringError> {
+        let _ = state;
+        Err(StringError(format!(
+            "[{}] command {command:?} is unsupported",
+            self.name()
+        )))
+    }
+
+    /// Channel's display name.
+    ///
+    /// Same as [`Resource::orig_name()`].
+    fn name(&self) -> &str {
+        self.orig_name()
+    }
 }
 
 /// What note to play on what channel.
@@ -614,6 +1353,66 @@ mod tests {
         assert_eq!(arr.as_byte_vec(), r#"[]"#.as_bytes())
     }
 
+    #[test]
+    fn json_array_rejects_mixed_values() {
+        let mixed = [json!(1), json!([2, 3])];
+        assert!(JsonArray::from_values(mixed).is_none());
+        assert!(JsonArray::from_values([json!({"a": 1}), json!(2)]).is_none());
+        assert!(JsonArray::from_value(json!([1, [2, 3]])).is_none());
+        assert!(JsonArray::from_value(json!([[1], 2, 3])).is_none());
+        assert!(JsonArray::from_value(json!(5)).is_none());
+        assert!(JsonArray::from_values([json!(1), json!(null)]).is_some());
+    }
+
+    #[test]
+    fn json_array_patch_is_checked() {
+        let mut arr = JsonArray::from_value(good_data()).unwrap();
+        let other = JsonArray::from_value(json!([5, 1, "munching", true, null])).unwrap();
+        let patch = arr.diff(&other);
+        assert_eq!(patch, [(1, json!(1)), (4, json!(null))]);
+        assert!(arr.diff(&arr).is_empty());
+
+        let bad = [(1, json!(2)), (6, json!(3))];
+        assert_eq!(arr.apply_patch(&bad), Err(ConfigError::MissingValue(6)));
+        let nested = [(0, json!(2)), (1, json!([3]))];
+        assert!(arr.apply_patch(&nested).is_err());
+        assert!(arr.apply_patch(&[(0, json!({"a": 1}))]).is_err());
+        // Failed patches leave the array unchanged
+        assert_eq!(arr, JsonArray::from_value(good_data()).unwrap());
+
+        arr.apply_patch(&patch).unwrap();
+        assert_eq!(arr, other);
+        // Appended values can be followed by more appended values
+        arr.apply_patch(&[(5, json!(6)), (6, json!(7))]).unwrap();
+        assert_eq!(arr.len(), 7);
+    }
+
+    #[test]
+    fn json_array_typed_getters() {
+        let arr = JsonArray::from_value(good_data()).unwrap();
+        assert_eq!(arr.get_i64(0), Some(5));
+        assert_eq!(arr.get_f64(1), Some(0.0));
+        assert_eq!(arr.get_str(2), Some("munching"));
+        assert_eq!(arr.get_bool(3), Some(true));
+        assert_eq!(arr.get_bool(0), None);
+        assert_eq!(arr.get_i64(4), None);
+
+        assert_eq!(arr.try_str(2), Ok("munching"));
+        assert_eq!(
+            arr.try_i64(2),
+            Err(ConfigError::WrongType {
+                index: 2,
+                expected: JsonType::Number,
+                actual: JsonType::String
+            })
+        );
+        let float = JsonArray::from_value(json!([0.5])).unwrap();
+        assert_eq!(float.try_i64(0), Err(ConfigError::NotInteger(0)));
+        assert_eq!(arr.try_bool(4), Err(ConfigError::MissingValue(4)));
+        let err = arr.try_i64(3).unwrap_err().named("flag");
+        assert_eq!(err.0, "argument 4 (flag) is bool, expected number");
+    }
+
     #[test]
     fn json_array_insert() {
         let mut arr = JsonArray::new();
@@ -656,4 +1455,116 @@ mod tests {
             r#"[5,3,["bad"],{"no":false}]"#.as_bytes()
         );
     }
+
+    /// Mod that only declares its types.
+    struct Converter(Discriminant<ModData>, Discriminant<ModData>);
+
+    impl Resource for Converter {
+        fn orig_name(&self) -> &str {
+            "Converter"
+        }
+
+        fn id(&self) -> &str {
+            "TEST_CONVERTER"
+        }
+
+        fn check_config(&self, _conf: &ResConfig) -> Result<(), StringError> {
+            Ok(())
+        }
+
+        fn check_state(&self, _state: &ResState) -> Option<()> {
+            Some(())
+        }
+
+        fn description(&self) -> &str {
+            "Mod with given input and output types"
+        }
+    }
+
+    impl Mod for Converter {
+        fn apply(
+            &self,
+            _input: &ModData,
+            _conf: &ResConfig,
+            _state: &ResState,
+        ) -> Result<(ModData, Box<ResState>), StringError> {
+            Err(StringError("converter cannot be applied".to_string()))
+        }
+
+        fn input_type(&self) -> Discriminant<ModData> {
+            self.0
+        }
+
+        fn output_type(&self) -> Discriminant<ModData> {
+            self.1
+        }
+    }
+
+    #[test]
+    fn smart_pointers_are_resources() {
+        fn id_of(res: &impl Resource) -> &str {
+            res.id()
+        }
+        let note = std::mem::discriminant(&ModData::Note(Note::default()));
+        let rc: Rc<dyn Mod> = Rc::new(Converter(note, note));
+        let boxed: Box<dyn Resource> = Box::new(Converter(note, note));
+        assert_eq!(id_of(&rc), "TEST_CONVERTER");
+        assert_eq!(id_of(&boxed), "TEST_CONVERTER");
+        assert_eq!(rc.schema_version(), 1);
+        assert!(rc.migrate_config(1, JsonArray::new()).is_ok());
+        assert!(boxed.migrate_config(0, JsonArray::new()).is_err());
+        assert!(rc.migrate_state(1, Box::new([])).is_ok());
+        assert!(boxed.migrate_state(0, Box::new([])).is_err());
+        assert!(Resource::check_state(&boxed, &[]).is_some());
+    }
+
+    #[test]
+    fn converting_mod_can_only_start_empty_pipeline() {
+        let note = std::mem::discriminant(&ModData::Note(Note::default()));
+        let ready_note = std::mem::discriminant(&ModData::ReadyNote(ReadyNote::default()));
+
+        let mut empty: Vec<Rc<dyn Mod>> = Vec::new();
+        empty
+            .insert_checked(0, Rc::new(Converter(note, ready_note)))
+            .unwrap();
+        assert_eq!(Pipeline::input_type(&empty), Some(note));
+
+        let mut pipeline: Vec<Rc<dyn Mod>> = vec![Rc::new(Converter(note, note))];
+        assert!(matches!(
+            pipeline.insert_checked(0, Rc::new(Converter(note, ready_note))),
+            Err(PipelineError::InsertBreaksPipeline)
+        ));
+        assert!(matches!(
+            pipeline.insert_checked(0, Rc::new(Converter(ready_note, note))),
+            Err(PipelineError::InsertBreaksPipeline)
+        ));
+        assert_eq!(pipeline.len(), 1);
+        // Mods that keep the type are accepted
+        pipeline
+            .insert_checked(0, Rc::new(Converter(note, note)))
+            .unwrap();
+        assert_eq!(pipeline.len(), 2);
+    }
+
+    #[test]
+    fn replacement_keeps_types() {
+        let note = std::mem::discriminant(&ModData::Note(Note::default()));
+        let ready_note = std::mem::discriminant(&ModData::ReadyNote(ReadyNote::default()));
+
+        let first: Rc<dyn Mod> = Rc::new(Converter(note, ready_note));
+        let mut pipeline: Vec<Rc<dyn Mod>> = vec![first.clone()];
+        assert!(matches!(
+            pipeline.replace_checked(0, Rc::new(Converter(note, note))),
+            Err(PipelineError::InsertBreaksPipeline)
+        ));
+        assert!(matches!(
+            pipeline.replace_checked(1, Rc::new(Converter(note, ready_note))),
+            Err(PipelineError::IndexOutsideRange)
+        ));
+        let old = pipeline
+            .replace_checked(0, Rc::new(Converter(note, ready_note)))
+            .unwrap();
+        assert!(Rc::ptr_eq(&old, &first));
+        assert!(!Rc::ptr_eq(&pipeline[0], &first));
+    }
 }
@@ -0,0 +1,173 @@
+//! Types that are passed to and from [mixers][super::Mixer].
+
+use dasp::frame::Stereo;
+
+use super::{ResState, StringError};
+use crate::types::Sound;
+
+/// Type to hold unused bits of sound.
+pub type LeftoverSound<'a> = Box<[Option<&'a [Stereo<f32>]>]>;
+
+/// Input type for the mixer.
+///
+/// Each sound has a flag to indicate whether it is a new sound or not.
+pub type PremixedSound<'a> = &'a [(bool, &'a [Stereo<f32>])];
+
+/// Helper for constructing [`PremixedSound`] that keeps track of the flags.
+///
+/// Channels are added in order, either as newly produced sounds or as leftovers
+/// from the previous call to the mixer.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::resource::PremixBuilder;
+/// # use mleml::types::Sound;
+/// let sound = Sound::new(Box::new([[0.5, 0.5]; 4]), 48000);
+/// let leftover = [[0.25, 0.25]; 2];
+/// let premix = PremixBuilder::new()
+///     .channel_count(2)
+///     .new_sound(&sound)
+///     .leftover(&leftover);
+/// let premixed = premix.as_premixed().expect("wrong number of channels");
+/// assert!(premixed[0].0);
+/// assert!(!premixed[1].0);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct PremixBuilder<'a> {
+    channels: Vec<(bool, &'a [Stereo<f32>])>,
+    expected: Option<usize>,
+}
+
+impl<'a> PremixBuilder<'a> {
+    /// Create an empty builder that accepts any number of channels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require exactly `count` channels to be added.
+    pub fn channel_count(mut self, count: usize) -> Self {
+        self.expected = Some(count);
+        self
+    }
+
+    /// Add a newly produced sound as the next channel.
+    pub fn new_sound(mut self, sound: &'a Sound) -> Self {
+        self.channels.push((true, sound.data()));
+        self
+    }
+
+    /// Add a piece of sound that was left over by the mixer as the next channel.
+    pub fn leftover(mut self, data: &'a [Stereo<f32>]) -> Self {
+        self.channels.push((false, data));
+        self
+    }
+
+    /// Number of channels added so far.
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Check if no channels were added.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Get the input for the mixer.
+    ///
+    /// Returns an error if the number of channels differs from the one that was set
+    /// with [`channel_count()`][PremixBuilder::channel_count()].
+    pub fn as_premixed(&self) -> Result<PremixedSound<'_>, StringError> {
+        match self.expected {
+            Some(count) if count != self.channels.len() => Err(StringError(format!(
+                "wrong number of channels: expected {}, got {}",
+                count,
+                self.channels.len()
+            ))),
+            _ => Ok(self.channels.as_slice()),
+        }
+    }
+}
+
+/// Type to hold every newly created state when the pipeline is used
+pub type PipelineStateChanges = Vec<Box<ResState>>;
+
+/// Input of [`Mixer::mix_structured()`][super::Mixer::mix_structured()].
+///
+/// More fields may be added later, so it is created with [`MixInput::new()`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MixInput<'a> {
+    /// Sounds to mix, see [`PremixedSound`].
+    pub channels: PremixedSound<'a>,
+
+    /// Number of frames to mix.
+    pub play_time: u32,
+}
+
+impl<'a> MixInput<'a> {
+    /// Create input from sounds and the number of frames to mix.
+    pub fn new(channels: PremixedSound<'a>, play_time: u32) -> Self {
+        MixInput {
+            channels,
+            play_time,
+        }
+    }
+}
+
+/// Output of [`Mixer::mix_structured()`][super::Mixer::mix_structured()].
+///
+/// More fields may be added later, so it is created from the tuple that
+/// [`Mixer::mix()`][super::Mixer::mix()] returns.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct MixOutput<'a> {
+    /// Mixed sound.
+    pub sound: Box<Sound>,
+
+    /// New state of the mixer.
+    pub state: Box<ResState>,
+
+    /// Unused pieces of the sounds, one per channel.
+    pub leftovers: LeftoverSound<'a>,
+}
+
+impl<'a> From<(Box<Sound>, Box<ResState>, LeftoverSound<'a>)> for MixOutput<'a> {
+    fn from((sound, state, leftovers): (Box<Sound>, Box<ResState>, LeftoverSound<'a>)) -> Self {
+        MixOutput {
+            sound,
+            state,
+            leftovers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premix_builder_preserves_flag_order() {
+        let sound = Sound::new(Box::new([[1.0, 1.0]; 3]), 48000);
+        let leftover = [[0.5, 0.5]; 2];
+        let premix = PremixBuilder::new()
+            .leftover(&leftover)
+            .new_sound(&sound)
+            .leftover(&leftover);
+        let premixed = premix.as_premixed().unwrap();
+        assert_eq!(
+            premixed.iter().map(|x| x.0).collect::<Vec<_>>(),
+            [false, true, false]
+        );
+        assert_eq!(premixed[1].1.len(), 3);
+    }
+
+    #[test]
+    fn premix_builder_rejects_wrong_count() {
+        let sound = Sound::new(Box::new([[1.0, 1.0]; 3]), 48000);
+        let premix = PremixBuilder::new().channel_count(2).new_sound(&sound);
+        assert!(premix.as_premixed().is_err());
+        assert!(premix.new_sound(&sound).as_premixed().is_ok());
+    }
+}
@@ -0,0 +1,516 @@
+//! Named description of [resource configs][super::ResConfig].
+
+use serde_json::json;
+
+use super::{ConfigError, JsonArray, JsonType, JsonValue, ResConfig};
+
+/// Type of a value in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// `true` or `false`.
+    Bool,
+
+    /// Integer that fits into `i64`.
+    Integer,
+
+    /// Any number.
+    Float,
+
+    /// String.
+    String,
+}
+
+impl FieldType {
+    /// Value of this type that is used in positional schemas.
+    fn placeholder(&self) -> JsonValue {
+        match self {
+            FieldType::Bool => json!(false),
+            FieldType::Integer => json!(0),
+            FieldType::Float => json!(0.0),
+            FieldType::String => json!(""),
+        }
+    }
+
+    fn json_type(&self) -> JsonType {
+        match self {
+            FieldType::Bool => JsonType::Bool,
+            FieldType::Integer | FieldType::Float => JsonType::Number,
+            FieldType::String => JsonType::String,
+        }
+    }
+
+    fn matches(&self, value: &JsonValue) -> bool {
+        match self {
+            FieldType::Bool => value.is_boolean(),
+            FieldType::Integer => value.is_i64(),
+            FieldType::Float => value.is_number(),
+            FieldType::String => value.is_string(),
+        }
+    }
+}
+
+/// Constraint on a single value of the config.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::resource::{validate, JsonArray, ValueConstraint};
+/// # use serde_json::json;
+/// let constraints = [
+///     ValueConstraint::IntRange { min: 0, max: 7 },
+///     ValueConstraint::OneOf(vec![json!("linear"), json!("quadratic")]),
+///     ValueConstraint::StringPattern("*.wav".to_string()),
+/// ];
+/// let good = JsonArray::from_value(json!([7, "linear", "kick.wav"])).unwrap();
+/// assert!(validate(&good, &constraints).is_ok());
+/// let bad = JsonArray::from_value(json!([99, "linear", "kick.wav"])).unwrap();
+/// assert!(validate(&bad, &constraints).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueConstraint {
+    /// Any value is allowed.
+    Any,
+
+    /// Boolean value.
+    Bool,
+
+    /// Integer in range [`min`, `max`].
+    IntRange {
+        /// Smallest allowed value.
+        min: i64,
+        /// Largest allowed value.
+        max: i64,
+    },
+
+    /// Number in range [`min`, `max`].
+    FloatRange {
+        /// Smallest allowed value.
+        min: f64,
+        /// Largest allowed value.
+        max: f64,
+    },
+
+    /// One of the listed values.
+    OneOf(Vec<JsonValue>),
+
+    /// String that matches the pattern, where `*` matches any number of characters
+    /// and `?` matches exactly one.
+    StringPattern(String),
+}
+
+impl ValueConstraint {
+    /// Check `value`, which is at position `idx` of the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value has a wrong type, is outside of the range or is
+    /// not allowed.
+    pub fn check(&self, idx: usize, value: &JsonValue) -> Result<(), ConfigError> {
+        let wrong_type = |expected| ConfigError::mismatch(idx, expected, value);
+        match self {
+            ValueConstraint::Any => Ok(()),
+            ValueConstraint::Bool => value
+                .is_boolean()
+                .then_some(())
+                .ok_or_else(|| wrong_type(JsonType::Bool)),
+            ValueConstraint::IntRange { min, max } => {
+                let x = value.as_i64().ok_or_else(|| wrong_type(JsonType::Number))?;
+                (*min..=*max)
+                    .contains(&x)
+                    .then_some(())
+                    .ok_or(ConfigError::OutOfRange(idx))
+            }
+            ValueConstraint::FloatRange { min, max } => {
+                let x = value.as_f64().ok_or_else(|| wrong_type(JsonType::Number))?;
+                (*min..=*max)
+                    .contains(&x)
+                    .then_some(())
+                    .ok_or(ConfigError::OutOfRange(idx))
+            }
+            ValueConstraint::OneOf(allowed) => allowed
+                .contains(value)
+                .then_some(())
+                .ok_or(ConfigError::NotAllowed(idx)),
+            ValueConstraint::StringPattern(pattern) => {
+                let x = value.as_str().ok_or_else(|| wrong_type(JsonType::String))?;
+                matches_pattern(pattern, x)
+                    .then_some(())
+                    .ok_or(ConfigError::NotAllowed(idx))
+            }
+        }
+    }
+}
+
+/// Check every value of `config` against the constraint at the same position.
+///
+/// Values past the end of `constraints` are not checked, so that optional trailing
+/// values can be checked separately.
+///
+/// # Errors
+///
+/// Returns the first error of [`ValueConstraint::check()`], or
+/// [`MissingValue`][ConfigError::MissingValue] if the config is shorter than
+/// `constraints`.
+pub fn validate(config: &ResConfig, constraints: &[ValueConstraint]) -> Result<(), ConfigError> {
+    for (i, constraint) in constraints.iter().enumerate() {
+        let value = config
+            .as_slice()
+            .get(i)
+            .ok_or(ConfigError::MissingValue(i))?;
+        constraint.check(i, value)?;
+    }
+    Ok(())
+}
+
+/// Match `text` against a pattern with `*` and `?` wildcards.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    //Position of the last `*` in the pattern and of the text it was matched at.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                //Let the last `*` match one more character.
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+/// Description of one value in the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigField {
+    /// Name of the value, unique within the schema.
+    pub name: String,
+
+    /// Type of the value.
+    pub kind: FieldType,
+
+    /// Smallest allowed value, for numbers.
+    pub min: Option<f64>,
+
+    /// Largest allowed value, for numbers.
+    pub max: Option<f64>,
+
+    /// What the value means.
+    pub doc: String,
+
+    /// Value that is used when none is given.
+    pub default: Option<JsonValue>,
+}
+
+impl ConfigField {
+    /// Create a field without range limits.
+    pub fn new(name: impl Into<String>, kind: FieldType, doc: impl Into<String>) -> Self {
+        ConfigField {
+            name: name.into(),
+            kind,
+            min: None,
+            max: None,
+            doc: doc.into(),
+            default: None,
+        }
+    }
+
+    /// Set the value that is used when none is given.
+    pub fn with_default(self, default: JsonValue) -> Self {
+        ConfigField {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    /// Limit the value of a number to range [`min`, `max`].
+    pub fn with_range(self, min: f64, max: f64) -> Self {
+        ConfigField {
+            min: Some(min),
+            max: Some(max),
+            ..self
+        }
+    }
+
+    /// Limit the value of a number to be at least `min`.
+    pub fn with_min(self, min: f64) -> Self {
+        ConfigField {
+            min: Some(min),
+            ..self
+        }
+    }
+}
+
+/// Config schema that gives every position a name, a type, an optional range and
+/// a description.
+///
+/// Positional schemas, which are configs of example values, are what
+/// [`ConfigBuilder`][crate::extra::config_builder::ConfigBuilder] uses; a schema can
+/// be converted to and from them.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::resource::{ConfigField, ConfigSchema, FieldType};
+/// # use serde_json::json;
+/// let schema = ConfigSchema::new(vec![
+///     ConfigField::new("octave", FieldType::Integer, "Octave of the note").with_min(0.0),
+///     ConfigField::new("name", FieldType::String, "Name of the note"),
+/// ]);
+/// assert_eq!(schema.position("name"), Some(1));
+/// assert!(schema.check_value(0, &json!(4)).is_ok());
+/// assert!(schema.check_value(0, &json!(-1)).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigSchema {
+    fields: Vec<ConfigField>,
+    positional: ResConfig,
+}
+
+impl ConfigSchema {
+    /// Create a schema from fields in the order of their positions.
+    pub fn new(fields: Vec<ConfigField>) -> Self {
+        let positional = JsonArray::from_values(
+            fields
+                .iter()
+                .map(|x| x.kind.placeholder())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        ConfigSchema { fields, positional }
+    }
+
+    /// Create a schema from a positional one.
+    ///
+    /// Fields are named `argument 1`, `argument 2` and so on, numbers that are
+    /// integers become [`Integer`][FieldType::Integer] fields, and nulls become
+    /// [`String`][FieldType::String] fields.
+    pub fn from_positional(schema: &ResConfig) -> Self {
+        let fields = schema
+            .as_slice()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let kind = match x {
+                    JsonValue::Bool(_) => FieldType::Bool,
+                    JsonValue::Number(x) if x.is_f64() => FieldType::Float,
+                    JsonValue::Number(_) => FieldType::Integer,
+                    _ => FieldType::String,
+                };
+                ConfigField::new(format!("argument {}", i + 1), kind, "")
+            })
+            .collect();
+        Self::new(fields)
+    }
+
+    /// Get the positional schema.
+    pub fn positional(&self) -> &ResConfig {
+        &self.positional
+    }
+
+    /// Get the fields in the order of their positions.
+    pub fn fields(&self) -> &[ConfigField] {
+        &self.fields
+    }
+
+    /// Get the number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Check if the schema has no fields.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Find the position of the field called `name`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|x| x.name == name)
+    }
+
+    /// Check that `value` fits the field at position `idx`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no field at `idx`, if the value has a wrong
+    /// type, or if the number is outside of field's range.
+    pub fn check_value(&self, idx: usize, value: &JsonValue) -> Result<(), ConfigError> {
+        let field = self.fields.get(idx).ok_or(ConfigError::MissingValue(idx))?;
+        if !field.kind.matches(value) {
+            return Err(ConfigError::mismatch(idx, field.kind.json_type(), value));
+        }
+        if let Some(x) = value.as_f64() {
+            let low = field.min.is_none_or(|min| x >= min);
+            let high = field.max.is_none_or(|max| x <= max);
+            if !(low && high) {
+                return Err(ConfigError::OutOfRange(idx));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every value of `conf` against the schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of [`check_value()`][ConfigSchema::check_value], or
+    /// [`BadLength`][ConfigError::BadLength] if the lengths differ.
+    pub fn check(&self, conf: &ResConfig) -> Result<(), ConfigError> {
+        if conf.len() != self.len() {
+            return Err(ConfigError::BadLength(self.len() as u32, conf.len() as u32));
+        }
+        conf.as_slice()
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, x)| self.check_value(i, x))
+    }
+}
+
+impl ConfigSchema {
+    /// Get the constraints of the fields, to be used with [`validate()`].
+    ///
+    /// Numbers are limited to their ranges, booleans and strings are only checked
+    /// for their type.
+    pub fn constraints(&self) -> Vec<ValueConstraint> {
+        self.fields
+            .iter()
+            .map(|x| match x.kind {
+                FieldType::Bool => ValueConstraint::Bool,
+                FieldType::Integer => ValueConstraint::IntRange {
+                    min: x.min.map_or(i64::MIN, |x| x.ceil() as i64),
+                    max: x.max.map_or(i64::MAX, |x| x.floor() as i64),
+                },
+                FieldType::Float => ValueConstraint::FloatRange {
+                    min: x.min.unwrap_or(f64::NEG_INFINITY),
+                    max: x.max.unwrap_or(f64::INFINITY),
+                },
+                FieldType::String => ValueConstraint::StringPattern("*".to_string()),
+            })
+            .collect()
+    }
+}
+
+impl From<&ResConfig> for ConfigSchema {
+    fn from(value: &ResConfig) -> Self {
+        ConfigSchema::from_positional(value)
+    }
+}
+
+impl From<ConfigSchema> for ResConfig {
+    fn from(value: ConfigSchema) -> Self {
+        value.positional
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_round_trips_through_positional() {
+        let positional = JsonArray::from_value(json!([0, 0.0, false, ""])).unwrap();
+        let schema = ConfigSchema::from_positional(&positional);
+        let kinds: Vec<_> = schema.fields().iter().map(|x| x.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Bool,
+                FieldType::String
+            ]
+        );
+        assert_eq!(schema.position("argument 3"), Some(2));
+        assert_eq!(ResConfig::from(schema), positional);
+    }
+
+    #[test]
+    fn schema_checks_types_and_ranges() {
+        let schema = ConfigSchema::new(vec![
+            ConfigField::new("rate", FieldType::Integer, "").with_range(0.0, 511.0),
+            ConfigField::new("gain", FieldType::Float, ""),
+        ]);
+        let conf = |x| JsonArray::from_value(x).unwrap();
+        assert!(schema.check(&conf(json!([511, 2]))).is_ok());
+        assert_eq!(
+            schema.check(&conf(json!([512, 2.0]))),
+            Err(ConfigError::OutOfRange(0))
+        );
+        assert!(schema.check(&conf(json!([1.5, 2.0]))).is_err());
+        assert_eq!(
+            schema.check(&conf(json!([1]))),
+            Err(ConfigError::BadLength(2, 1))
+        );
+    }
+
+    #[test]
+    fn patterns_match_wildcards() {
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*.wav", "kick.wav"));
+        assert!(!matches_pattern("*.wav", "kick.wave"));
+        assert!(matches_pattern("k?ck*", "kick.wav"));
+        assert!(matches_pattern("*a*b", "xaxxab"));
+        assert!(!matches_pattern("?", ""));
+        assert!(matches_pattern("exponential *", "exponential 2"));
+    }
+
+    #[test]
+    fn constraints_report_the_position() {
+        let conf = JsonArray::from_value(json!([3, 0.5, "saw"])).unwrap();
+        let one_of = ValueConstraint::OneOf(vec![json!("sine"), json!("saw")]);
+        assert!(validate(&conf, &[ValueConstraint::Any]).is_ok());
+        assert_eq!(
+            validate(&conf, &[ValueConstraint::IntRange { min: 0, max: 2 }]),
+            Err(ConfigError::OutOfRange(0))
+        );
+        let range = ValueConstraint::FloatRange { min: 0.0, max: 1.0 };
+        assert!(validate(
+            &conf,
+            &[ValueConstraint::Any, range.clone(), one_of.clone()]
+        )
+        .is_ok());
+        assert_eq!(
+            validate(&conf, &[one_of, range]),
+            Err(ConfigError::NotAllowed(0))
+        );
+        assert_eq!(
+            validate(&conf, &vec![ValueConstraint::Any; 4]),
+            Err(ConfigError::MissingValue(3))
+        );
+    }
+
+    #[test]
+    fn constraints_report_the_type() {
+        let conf = JsonArray::from_value(json!([3, "saw"])).unwrap();
+        let schema = ConfigSchema::new(vec![
+            ConfigField::new("rate", FieldType::Float, ""),
+            ConfigField::new("saw", FieldType::Bool, ""),
+        ]);
+        assert_eq!(
+            validate(&conf, &schema.constraints()),
+            Err(ConfigError::WrongType {
+                index: 1,
+                expected: JsonType::Bool,
+                actual: JsonType::String
+            })
+        );
+        let err = ValueConstraint::FloatRange { min: 0.0, max: 1.0 }
+            .check(0, &json!("half"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "argument 1 is string, expected number");
+    }
+}
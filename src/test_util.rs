@@ -0,0 +1,378 @@
+//! Small resources for testing code that takes mods, enabled by feature `test-util`.
+//!
+//! Every resource here does only what its description says, and most accept any
+//! config and state.
+
+use std::{
+    cell::Cell,
+    mem::{discriminant, Discriminant},
+};
+
+use crate::{
+    resource::{Mod, ModData, ResConfig, ResState, Resource, StringError},
+    types::{ReadyNote, Sound},
+};
+
+fn sound_type() -> Discriminant<ModData> {
+    discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+}
+
+/// Mod that can not be applied but has the given input and output types.
+///
+/// # Examples
+///
+/// ```
+/// # use std::mem::discriminant;
+/// # use mleml::{resource::{Mod, ModData}, test_util::MockMod};
+/// let kind = discriminant(&ModData::String(String::new()));
+/// let mock = MockMod { input: kind, output: kind };
+/// assert_eq!(mock.input_type(), kind);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MockMod {
+    /// Type of data that the mod claims to take.
+    pub input: Discriminant<ModData>,
+
+    /// Type of data that the mod claims to return.
+    pub output: Discriminant<ModData>,
+}
+
+impl Resource for MockMod {
+    fn orig_name(&self) -> &str {
+        "Mock mod"
+    }
+
+    fn id(&self) -> &str {
+        "MOCK"
+    }
+
+    fn check_config(&self, _conf: &ResConfig) -> Result<(), StringError> {
+        Ok(())
+    }
+
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Mod with arbitrary input and output types"
+    }
+}
+
+impl Mod for MockMod {
+    fn apply(
+        &self,
+        _input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        Err(StringError("mock mod cannot be applied".to_string()))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        self.input
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        self.output
+    }
+}
+
+/// Mod that plays a sound of full volume for the length of the note, including
+/// decay, at 1000 Hz.
+#[derive(Debug, Clone, Copy)]
+pub struct Constant();
+
+impl Resource for Constant {
+    fn orig_name(&self) -> &str {
+        "Constant"
+    }
+
+    fn id(&self) -> &str {
+        "CONSTANT"
+    }
+
+    fn check_config(&self, _conf: &ResConfig) -> Result<(), StringError> {
+        Ok(())
+    }
+
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Constant sound"
+    }
+}
+
+impl Mod for Constant {
+    fn apply(
+        &self,
+        input: &ModData,
+        _conf: &ResConfig,
+        _state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let note = input
+            .as_ready_note()
+            .ok_or(StringError("input has to be a ReadyNote".to_string()))?;
+        let frames = ((note.len + note.decay_time) * 1000.0) as usize;
+        let data = vec![[1.0, 1.0]; frames].into_boxed_slice();
+        Ok((ModData::Sound(Sound::new(data, 1000)), Box::new([])))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        sound_type()
+    }
+}
+
+/// Mod that repeats a string twice.
+#[derive(Debug, Clone, Copy)]
+pub struct Double();
+
+impl Resource for Double {
+    fn orig_name(&self) -> &str {
+        "Doubler"
+    }
+
+    fn id(&self) -> &str {
+        "TEST_DOUBLE"
+    }
+
+    fn check_config(&self, _conf: &ResConfig) -> Result<(), StringError> {
+        Ok(())
+    }
+
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Repeats the string twice"
+    }
+}
+
+impl Mod for Double {
+    fn apply(
+        &self,
+        input: &ModData,
+        _conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let s = input
+            .as_string()
+            .ok_or(StringError("input has to be a String".to_string()))?;
+        Ok((ModData::String(s.repeat(2)), state.into()))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::String(String::new()))
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        discriminant(&ModData::String(String::new()))
+    }
+}
+
+/// Length of [`Delay`] in frames.
+pub const DELAY: usize = 10;
+
+/// Mod that adds the left channel, delayed by [`DELAY`] frames at half volume, to
+/// itself. The state keeps the last [`DELAY`] output frames.
+///
+/// The tail is three echoes long, assuming a rate of 48000 Hz.
+#[derive(Debug, Clone, Copy)]
+pub struct Delay();
+
+impl Resource for Delay {
+    fn orig_name(&self) -> &str {
+        "Delay"
+    }
+
+    fn id(&self) -> &str {
+        "DELAY"
+    }
+
+    fn check_config(&self, _conf: &ResConfig) -> Result<(), StringError> {
+        Ok(())
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        (state.is_empty() || state.len() == DELAY * 4).then_some(())
+    }
+
+    fn description(&self) -> &str {
+        "Feedback delay"
+    }
+}
+
+impl Mod for Delay {
+    fn apply(
+        &self,
+        input: &ModData,
+        _conf: &ResConfig,
+        state: &ResState,
+    ) -> Result<(ModData, Box<ResState>), StringError> {
+        let mut history: Vec<f32> = match state.is_empty() {
+            true => vec![0.0; DELAY],
+            false => state
+                .chunks(4)
+                .map(|x| f32::from_le_bytes(x.try_into().unwrap()))
+                .collect(),
+        };
+        let sound = input
+            .as_sound()
+            .ok_or(StringError("input has to be a Sound".to_string()))?;
+        let out = sound
+            .data()
+            .iter()
+            .map(|x| {
+                let y = x[0] + history[history.len() - DELAY] * 0.5;
+                history.push(y);
+                [y, y]
+            })
+            .collect();
+        let state: Box<ResState> = history[history.len() - DELAY..]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        Ok((
+            ModData::Sound(Sound::new(out, sound.sampling_rate())),
+            state,
+        ))
+    }
+
+    fn input_type(&self) -> Discriminant<ModData> {
+        sound_type()
+    }
+
+    fn output_type(&self) -> Discriminant<ModData> {
+        sound_type()
+    }
+
+    fn tail_seconds(&self, _conf: &ResConfig) -> f32 {
+        (DELAY * 3) as f32 / 48000.0
+    }
+}
+
+/// Resource that accepts `[integer 0..=10, float 0..=1, string]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Ranged();
+
+impl Resource for Ranged {
+    fn orig_name(&self) -> &str {
+        "Ranged"
+    }
+
+    fn id(&self) -> &str {
+        "RANGED"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        let conf = conf.as_slice();
+        let valid = conf.len() == 3
+            && conf[0].as_i64().is_some_and(|x| (0..=10).contains(&x))
+            && conf[1].as_f64().is_some_and(|x| (0.0..=1.0).contains(&x))
+            && conf[2].is_string();
+        valid
+            .then_some(())
+            .ok_or(StringError("config is out of range".to_string()))
+    }
+
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Resource with ranged config"
+    }
+}
+
+/// Resource that accepts configs of one integer and counts how many times a config
+/// was checked.
+#[derive(Debug, Default)]
+pub struct Counted(pub Cell<usize>);
+
+impl Resource for Counted {
+    fn orig_name(&self) -> &str {
+        "Counted"
+    }
+
+    fn id(&self) -> &str {
+        "COUNTED"
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        self.0.set(self.0.get() + 1);
+        match conf.as_slice() {
+            [x] if x.is_i64() => Ok(()),
+            _ => Err(StringError("expected one integer".to_string())),
+        }
+    }
+
+    fn check_state(&self, _state: &ResState) -> Option<()> {
+        Some(())
+    }
+
+    fn description(&self) -> &str {
+        "Counts config checks"
+    }
+}
+
+/// [`FourOpFm`][crate::extra::builtin::FourOpFm] as if configs before schema
+/// version 2 had no velocity curve, which is appended when migrating them.
+#[cfg(feature = "builtin")]
+#[derive(Debug, Clone, Copy)]
+pub struct VersionedFm;
+
+#[cfg(feature = "builtin")]
+impl Resource for VersionedFm {
+    fn orig_name(&self) -> &str {
+        crate::extra::builtin::FourOpFm().orig_name()
+    }
+
+    fn id(&self) -> &str {
+        crate::extra::builtin::FourOpFm().id()
+    }
+
+    fn check_config(&self, conf: &ResConfig) -> Result<(), StringError> {
+        match conf.len() {
+            35 => crate::extra::builtin::FourOpFm().check_config(conf),
+            len => Err(StringError(format!(
+                "wrong number of values: expected 35, got {len}"
+            ))),
+        }
+    }
+
+    fn check_state(&self, state: &ResState) -> Option<()> {
+        crate::extra::builtin::FourOpFm().check_state(state)
+    }
+
+    fn description(&self) -> &str {
+        crate::extra::builtin::FourOpFm().description()
+    }
+
+    fn schema_version(&self) -> u32 {
+        2
+    }
+
+    fn migrate_config(
+        &self,
+        old_version: u32,
+        mut conf: ResConfig,
+    ) -> Result<ResConfig, StringError> {
+        match old_version {
+            1 if conf.len() == 34 => {
+                conf.push(serde_json::json!("linear")).unwrap();
+                Ok(conf)
+            }
+            2 => Ok(conf),
+            _ => Err(StringError(format!(
+                "can not migrate config from version {old_version}"
+            ))),
+        }
+    }
+}
@@ -1,39 +1,305 @@
 //! Main data types that the library uses.
 
-use dasp::frame::Stereo;
+use crate::{extra::rng::Rng, resource::StringError};
+use dasp::{frame::Stereo, interpolate::linear::Linear, signal, Signal};
+use serde::{Deserialize, Serialize};
 use slice_dst::SliceWithHeader;
-use std::num::{NonZeroI8, NonZeroU8};
+use std::num::{NonZeroU32, NonZeroU8};
+
+pub mod timing;
+pub mod units;
+
+pub use units::VelocityCurve;
+
+/// Positive length of a note in ticks.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::TickDuration;
+/// // Dotted whole note with 192 ticks per whole note
+/// let dotted = TickDuration::from_fraction(192, 1, 1).expect("length is not whole ticks");
+/// assert_eq!(dotted.ticks(), 288);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TickDuration(NonZeroU32);
+
+impl TickDuration {
+    /// Create a duration of `ticks` ticks. Returns None if `ticks` is 0.
+    pub const fn new(ticks: u32) -> Option<Self> {
+        match NonZeroU32::new(ticks) {
+            Some(x) => Some(TickDuration(x)),
+            None => None,
+        }
+    }
+
+    /// Get the number of ticks.
+    pub fn ticks(self) -> u32 {
+        self.0.get()
+    }
+
+    /// Get the length of a `1/denominator` note with `dots` dots, where `zenlen` is
+    /// the number of ticks in a whole note.
+    ///
+    /// Every dot adds half of the length that the previous one added.
+    ///
+    /// Returns None if the length is not a whole positive number of ticks.
+    pub fn from_fraction(zenlen: u32, denominator: u32, dots: u8) -> Option<Self> {
+        if denominator == 0 || !zenlen.is_multiple_of(denominator) {
+            return None;
+        }
+        let mut added = zenlen / denominator;
+        let mut total = added;
+        for _ in 0..dots {
+            if !added.is_multiple_of(2) {
+                return None;
+            }
+            added /= 2;
+            total += added;
+        }
+        Self::new(total)
+    }
+
+    /// Get the length of two tied notes. Returns None on overflow.
+    pub fn tie(self, other: TickDuration) -> Option<Self> {
+        self.ticks().checked_add(other.ticks()).and_then(Self::new)
+    }
+}
+
+impl From<NonZeroU8> for TickDuration {
+    fn from(ticks: NonZeroU8) -> Self {
+        TickDuration(ticks.into())
+    }
+}
 
 /// Note, defined in abstract, platform-defined values.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct Note {
     /// Note length in ticks.
     ///
     /// May be unspecified.
-    pub len: Option<NonZeroU8>,
+    pub len: Option<TickDuration>,
 
-    /// Note's pitch in semitones relative to C.
+    /// Note's pitch in semitones relative to C, so 0 is C itself.
     ///
     /// If None, then this is a rest.
-    pub pitch: Option<NonZeroI8>,
+    pub pitch: Option<i8>,
+
+    /// Pitch that the note glides to while it is held, in semitones relative to C.
+    ///
+    /// If None, the pitch stays the same.
+    pub end_pitch: Option<i8>,
 
     ///One cent is 1/100th of a semitone.
     pub cents: i8,
 
     /// Flag to indicate that the note is intended to be natural (its pitch should not
-    /// be affected by the key signature).
+    /// be affected by the [key signature][KeySignature]).
     pub natural: bool,
 
-    //TODO: MIDI uses 7 bits for velocity. Should I adhere to that?
+    /// Flag to indicate that the note is percussive (noise or drum).
+    ///
+    /// A percussive note without pitch is not a rest and is expected to be played by
+    /// mods that can produce unpitched sound.
+    pub percussive: bool,
+
     /// Velocity of a note.
     ///
-    /// Default is 128 (defined by `dasp` as u8::EQUILIBRIUM).
+    /// 0 is silence, see [`units::velocity_to_gain()`] for how it becomes loudness.
+    /// Default is 128, about half of the [full range][units::VelocityConvention::Byte].
     pub velocity: u8,
 }
 
+/// Default velocity of [`Note`] and [`ReadyNote`].
+const DEFAULT_VELOCITY: u8 = 128;
+
+impl Default for Note {
+    fn default() -> Self {
+        Note {
+            len: None,
+            pitch: None,
+            end_pitch: None,
+            cents: 0,
+            natural: false,
+            percussive: false,
+            velocity: DEFAULT_VELOCITY,
+        }
+    }
+}
+
+impl Note {
+    /// Create a rest of the given length.
+    pub fn rest(len: Option<TickDuration>) -> Note {
+        Note {
+            len,
+            ..Default::default()
+        }
+    }
+
+    /// Check if the note is a rest: it has no pitch and is not percussive.
+    pub fn is_rest(&self) -> bool {
+        self.pitch.is_none() && !self.percussive
+    }
+
+    /// Get a copy of the note, shifted by `semitones`.
+    ///
+    /// Notes without pitch are returned unchanged. [End pitch][Note::end_pitch] is
+    /// shifted as well.
+    ///
+    /// Returns None if the new pitch is outside of `i8`'s range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Note;
+    /// let note = Note { pitch: Some(9), ..Default::default() };
+    /// assert_eq!(note.transpose(3).and_then(|x| x.pitch), Some(12));
+    /// assert_eq!(note.transpose(-9).and_then(|x| x.pitch), Some(0));
+    /// assert!(note.transpose(120).is_none());
+    /// ```
+    pub fn transpose(&self, semitones: i8) -> Option<Note> {
+        let pitch = match self.pitch {
+            Some(pitch) => Some(pitch.checked_add(semitones)?),
+            None => None,
+        };
+        let end_pitch = match self.end_pitch {
+            Some(pitch) => Some(pitch.checked_add(semitones)?),
+            None => None,
+        };
+        Some(Note {
+            pitch,
+            end_pitch,
+            ..self.clone()
+        })
+    }
+
+    /// Get a copy of the note with length of `ticks`. Length of 0 ticks makes it
+    /// unspecified.
+    pub fn with_length(&self, ticks: u32) -> Note {
+        Note {
+            len: TickDuration::new(ticks),
+            ..self.clone()
+        }
+    }
+}
+
+/// Accidentals that apply to every note of a letter, like F# in G major.
+///
+/// Only notes on white keys are affected: notes on black keys are taken to already
+/// have an accidental, and the signature does not change them.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::{KeySignature, Note};
+/// let g_major = KeySignature::from_sharps_flats(1);
+/// let f = Note { pitch: Some(5), ..Default::default() };
+/// assert_eq!(g_major.apply(&f).pitch, Some(6));
+/// let natural = Note { natural: true, ..f };
+/// assert_eq!(g_major.apply(&natural).pitch, Some(5));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeySignature {
+    /// Semitones added to notes of each letter, from C to B.
+    pub accidentals: [i8; 7],
+}
+
+impl KeySignature {
+    /// Letters in the order that sharps are added to a key signature, as indices
+    /// into [`accidentals`][KeySignature::accidentals]. Flats go in reverse.
+    const SHARP_ORDER: [usize; 7] = [3, 0, 4, 1, 5, 2, 6];
+
+    /// Create a key signature from accidentals of letters from C to B.
+    pub fn new(accidentals: [i8; 7]) -> Self {
+        KeySignature { accidentals }
+    }
+
+    /// Create a key signature of a major or minor key with `count` sharps, or with
+    /// `-count` flats if it is negative. Values beyond 7 sharps or flats are clamped.
+    pub fn from_sharps_flats(count: i8) -> Self {
+        let mut accidentals = [0; 7];
+        let sharps = count.clamp(-7, 7);
+        for i in 0..sharps.unsigned_abs() as usize {
+            match sharps > 0 {
+                true => accidentals[Self::SHARP_ORDER[i]] = 1,
+                false => accidentals[Self::SHARP_ORDER[6 - i]] = -1,
+            }
+        }
+        KeySignature { accidentals }
+    }
+
+    /// Get the letter of a pitch on a white key, as an index from C to B.
+    fn letter(pitch: i8) -> Option<usize> {
+        match pitch.rem_euclid(12) {
+            0 => Some(0),
+            2 => Some(1),
+            4 => Some(2),
+            5 => Some(3),
+            7 => Some(4),
+            9 => Some(5),
+            11 => Some(6),
+            _ => None,
+        }
+    }
+
+    /// Get a copy of the note with the key signature applied.
+    ///
+    /// Rests, [natural][Note::natural] notes and notes on black keys are returned
+    /// unchanged. Pitch saturates at the bounds of `i8`.
+    pub fn apply(&self, note: &Note) -> Note {
+        let pitch = match note.pitch {
+            Some(pitch) if !note.natural => Some(
+                Self::letter(pitch).map_or(pitch, |x| pitch.saturating_add(self.accidentals[x])),
+            ),
+            pitch => pitch,
+        };
+        Note {
+            pitch,
+            ..note.clone()
+        }
+    }
+}
+
+/// Get the frequency of a note in equal temperament.
+///
+/// `base_c` is the frequency of C-1, the lowest C in MIDI, so `octave` 0 is one
+/// octave above it. `semitones` and `cents` are counted from C of `octave`.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::semitones_to_hz;
+/// let a4 = semitones_to_hz(8.1758, 9, 0, 4);
+/// assert!((a4 - 440.0).abs() < 0.01);
+/// ```
+pub fn semitones_to_hz(base_c: f64, semitones: i32, cents: i32, octave: u32) -> f64 {
+    // C-1 is octave -1, hence the extra octave
+    let octaves = (octave as f64 + 1.0) + semitones as f64 / 12.0 + cents as f64 / 1200.0;
+    base_c * 2.0_f64.powf(octaves)
+}
+
+/// Parameters of conversion from [`Note`] to [`ReadyNote`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteConversionParams {
+    /// Frequency of C-1, in Hz.
+    pub c_neg1_hz: f64,
+
+    /// Length of one tick, in seconds.
+    pub tick_length: f64,
+
+    /// Octave that the note is played in.
+    pub octave: u64,
+
+    /// Length of sound after the note is released, in ticks.
+    pub post_release_ticks: i64,
+
+    /// Cents added to the pitch.
+    pub added_cents: i64,
+}
+
 /// Note, defined in SI units.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct ReadyNote {
     /// Length of a note in seconds.
     pub len: f32,
@@ -41,11 +307,183 @@ pub struct ReadyNote {
     /// Length of the sound generated while it decays, in seconds.
     pub decay_time: f32,
 
-    /// Pitch of a note in Hz. None indicates a rest.
+    /// Pitch of a note in Hz. None indicates a rest, unless the note is percussive.
     pub pitch: Option<f32>,
 
-    /// Velocity of a note. Default is 128 (defined by `dasp` as u8::EQUILIBRIUM).
+    /// Flag to indicate that the note is percussive (noise or drum).
+    ///
+    /// Melodic mods treat a percussive note without pitch as a rest.
+    pub percussive: bool,
+
+    /// Velocity of a note.
+    ///
+    /// 0 is silence, see [`units::velocity_to_gain()`] for how it becomes loudness.
+    /// Default is 128, about half of the [full range][units::VelocityConvention::Byte].
     pub velocity: u8,
+
+    /// Velocity with which the key was released.
+    ///
+    /// 0 means a very slow fade and 255 means that the sound is cut immediately.
+    /// None leaves the release up to the mod.
+    pub release_velocity: Option<u8>,
+
+    /// Pitch in Hz that the note glides to over its length.
+    ///
+    /// If None, the pitch stays the same.
+    pub end_pitch: Option<f32>,
+
+    /// Shape of the glide to [`end_pitch`][ReadyNote::end_pitch].
+    pub glide_curve: GlideCurve,
+}
+
+/// How the pitch of a note moves from its start to its end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlideCurve {
+    /// Frequency changes by the same number of Hz every second.
+    #[default]
+    Linear,
+
+    /// Frequency changes by the same number of semitones every second.
+    Exponential,
+}
+
+impl GlideCurve {
+    /// Get the frequency at `progress` of the way from `start` to `end`.
+    ///
+    /// `progress` is clamped to [0, 1].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::GlideCurve;
+    /// assert_eq!(GlideCurve::Linear.frequency(220.0, 880.0, 0.5), 550.0);
+    /// assert_eq!(GlideCurve::Exponential.frequency(220.0, 880.0, 0.5), 440.0);
+    /// assert_eq!(GlideCurve::Linear.frequency(220.0, 880.0, 2.0), 880.0);
+    /// ```
+    pub fn frequency(&self, start: f64, end: f64, progress: f64) -> f64 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            GlideCurve::Linear => start + (end - start) * progress,
+            GlideCurve::Exponential => start * (end / start).powf(progress),
+        }
+    }
+}
+
+impl ReadyNote {
+    /// Convert a note to SI units.
+    ///
+    /// Pitch is found with [`semitones_to_hz()`], with note's cents and
+    /// [added cents][NoteConversionParams::added_cents] combined. Notes with unspecified
+    /// length are 0 seconds long. [End pitch][Note::end_pitch] is converted the same
+    /// way and glides linearly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::{Note, NoteConversionParams, ReadyNote, TickDuration};
+    /// let params = NoteConversionParams {
+    ///     c_neg1_hz: 8.1758,
+    ///     tick_length: 0.125,
+    ///     octave: 4,
+    ///     post_release_ticks: 2,
+    ///     added_cents: 0,
+    /// };
+    /// let note = Note { len: TickDuration::new(4), pitch: Some(9), ..Default::default() };
+    /// let ready = ReadyNote::from_note(&note, &params);
+    /// assert_eq!((ready.len, ready.decay_time), (0.5, 0.25));
+    /// assert!((ready.pitch.unwrap() - 440.0).abs() < 0.01);
+    /// ```
+    pub fn from_note(note: &Note, params: &NoteConversionParams) -> ReadyNote {
+        let ticks = note.len.map_or(0, |x| x.ticks());
+        let cents = (note.cents as i64).saturating_add(params.added_cents);
+        let cents = cents.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        let octave = params.octave.min(u32::MAX as u64) as u32;
+        let to_hz = |x: i8| semitones_to_hz(params.c_neg1_hz, x as i32, cents, octave) as f32;
+        ReadyNote {
+            len: (ticks as f64 * params.tick_length) as f32,
+            decay_time: (params.post_release_ticks as f64 * params.tick_length) as f32,
+            pitch: note.pitch.map(to_hz),
+            percussive: note.percussive,
+            velocity: note.velocity,
+            release_velocity: None,
+            end_pitch: note.end_pitch.map(to_hz),
+            glide_curve: GlideCurve::Linear,
+        }
+    }
+}
+
+impl Default for ReadyNote {
+    fn default() -> Self {
+        ReadyNote {
+            len: 0.0,
+            decay_time: 0.0,
+            pitch: None,
+            percussive: false,
+            velocity: DEFAULT_VELOCITY,
+            release_velocity: None,
+            end_pitch: None,
+            glide_curve: GlideCurve::Linear,
+        }
+    }
+}
+
+/// How [`Sound::resample_with_quality()`] computes the frames between the original ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResampleQuality {
+    /// Take the closest original frame. Fast, but adds steps and aliasing.
+    Nearest,
+
+    /// Interpolate linearly between the two closest original frames.
+    #[default]
+    Linear,
+
+    /// Windowed sinc interpolation over [`SINC_DEPTH`] original frames on each side,
+    /// which also filters out frequencies that do not fit into a lower rate. Slow,
+    /// but keeps high frequencies clean.
+    Sinc,
+}
+
+/// Number of original frames on each side of a frame that
+/// [`ResampleQuality::Sinc`] looks at, when the rate is not lowered.
+pub const SINC_DEPTH: usize = 16;
+
+/// What to do when sounds that are combined have different sampling rates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateMismatchPolicy {
+    /// Return an error.
+    #[default]
+    Error,
+
+    /// [Resample][Sound::resample_with_quality] every sound to the rate of the first one.
+    ResampleToFirst(ResampleQuality),
+
+    /// [Resample][Sound::resample_with_quality] every sound to the highest rate among them.
+    ResampleToHighest(ResampleQuality),
+}
+
+impl RateMismatchPolicy {
+    /// Get the rate that sounds with `rates` are combined at, and the quality of
+    /// resampling to it. The first rate is taken as the rate of the first sound.
+    fn common_rate(
+        self,
+        mut rates: impl Iterator<Item = u32>,
+    ) -> Result<(u32, ResampleQuality), StringError> {
+        let first = rates
+            .next()
+            .ok_or(StringError("no sounds to combine".to_string()))?;
+        match self {
+            RateMismatchPolicy::Error => match rates.find(|x| *x != first) {
+                Some(other) => Err(StringError(format!(
+                    "sampling rates differ: {first} and {other}"
+                ))),
+                None => Ok((first, ResampleQuality::default())),
+            },
+            RateMismatchPolicy::ResampleToFirst(quality) => Ok((first, quality)),
+            RateMismatchPolicy::ResampleToHighest(quality) => {
+                Ok((rates.fold(first, u32::max), quality))
+            }
+        }
+    }
 }
 
 /// Immutable slice of PCM (Stereo, 32 bit float) data with sampling rate.
@@ -63,6 +501,61 @@ impl Sound {
         unsafe { Box::from_raw(Box::into_raw(slice) as *mut Sound) }
     }
 
+    /// Create new sound from an iterator of frames.
+    ///
+    /// If the iterator knows its exact length, as iterators over slices, ranges and
+    /// their maps do, the frames are written directly into the sound. Otherwise they
+    /// are collected into a temporary buffer first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the iterator yields a different number of frames than its exact
+    /// [`size_hint()`][Iterator::size_hint] promised.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let ramp = Sound::from_frames((0..4).map(|x| [x as f32, 0.0]), 48000);
+    /// assert_eq!(ramp.data()[3], [3.0, 0.0]);
+    /// ```
+    pub fn from_frames<I: IntoIterator<Item = Stereo<f32>>>(iter: I, rate: u32) -> Box<Sound> {
+        let iter = iter.into_iter();
+        match iter.size_hint() {
+            (low, Some(high)) if low == high => Sound::from_exact(ExactLen(iter, low), rate),
+            _ => {
+                let frames: Vec<Stereo<f32>> = iter.collect();
+                Sound::from_exact(frames.into_iter(), rate)
+            }
+        }
+    }
+
+    /// Create new sound from the first `frames` frames of a signal.
+    ///
+    /// The frames are written directly into the sound, without a temporary buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// # use dasp::{signal, Signal};
+    /// let tone = signal::rate(48000.0).const_hz(440.0).sine().map(|x| [x as f32, x as f32]);
+    /// assert_eq!(Sound::from_signal(tone, 480, 48000).len_frames(), 480);
+    /// ```
+    pub fn from_signal<S: Signal<Frame = Stereo<f32>>>(
+        signal: S,
+        frames: usize,
+        rate: u32,
+    ) -> Box<Sound> {
+        Sound::from_exact(signal.take(frames), rate)
+    }
+
+    fn from_exact<I: ExactSizeIterator<Item = Stereo<f32>>>(iter: I, rate: u32) -> Box<Sound> {
+        let slice: Box<SliceWithHeader<u32, Stereo<f32>>> = SliceWithHeader::new(rate, iter);
+        // SAFETY: Sound is a transparent wrapper around the same type that slice has.
+        unsafe { Box::from_raw(Box::into_raw(slice) as *mut Sound) }
+    }
+
     /// Get sampling rate.
     pub fn sampling_rate(&self) -> u32 {
         self.0.header
@@ -71,6 +564,806 @@ impl Sound {
     pub fn data(&self) -> &[Stereo<f32>] {
         self.0.slice.as_ref()
     }
+
+    /// Create a sound that plays the same data on both sides.
+    pub fn from_mono(data: Box<[f32]>, sampling_rate: u32) -> Box<Sound> {
+        Sound::from_exact(Vec::from(data).into_iter().map(|x| [x, x]), sampling_rate)
+    }
+
+    /// Average the sides, so that both play the same data.
+    pub fn to_mono(&self) -> Box<Sound> {
+        let data = self.data().iter().map(|x| (x[0] + x[1]) / 2.0);
+        Sound::from_exact(data.map(|x| [x, x]), self.sampling_rate())
+    }
+
+    /// Get samples of the left side.
+    pub fn left(&self) -> impl Iterator<Item = f32> + '_ {
+        self.data().iter().map(|x| x[0])
+    }
+
+    /// Get samples of the right side.
+    pub fn right(&self) -> impl Iterator<Item = f32> + '_ {
+        self.data().iter().map(|x| x[1])
+    }
+
+    /// Create a silent sound, rounding its length down to whole frames.
+    ///
+    /// Negative or NaN durations produce an empty sound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let silence = Sound::silence(0.5, 48000);
+    /// assert_eq!(silence.len_frames(), 24000);
+    /// assert_eq!(silence.duration_secs(), 0.5);
+    /// ```
+    pub fn silence(duration_secs: f32, sampling_rate: u32) -> Box<Sound> {
+        let frames = (duration_secs * sampling_rate as f32) as usize;
+        Sound::new(vec![[0.0, 0.0]; frames].into_boxed_slice(), sampling_rate)
+    }
+
+    /// Get length in frames.
+    pub fn len_frames(&self) -> usize {
+        self.data().len()
+    }
+
+    /// Get length in seconds.
+    ///
+    /// Sound with sampling rate of 0 has a duration of 0.
+    pub fn duration_secs(&self) -> f64 {
+        match self.sampling_rate() {
+            0 => 0.0,
+            rate => self.len_frames() as f64 / rate as f64,
+        }
+    }
+
+    /// Returns `true` if the sound has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.data().is_empty()
+    }
+
+    /// Split the data into frames before `frame` and frames from it on.
+    ///
+    /// `frame` past the end of the sound is treated as the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]]), 48000);
+    /// let (played, leftover) = sound.split_at_frame(2);
+    /// assert_eq!(played, &[[0.0, 0.0], [1.0, 1.0]]);
+    /// assert_eq!(leftover, &[[2.0, 2.0]]);
+    /// ```
+    pub fn split_at_frame(&self, frame: usize) -> (&[Stereo<f32>], &[Stereo<f32>]) {
+        self.data().split_at(frame.min(self.len_frames()))
+    }
+
+    /// Get the frames between `start` and `end` seconds.
+    ///
+    /// Both bounds are rounded down to whole frames and clamped to the sound.
+    /// Returns None if either bound is NaN or if `start` is after `end`.
+    pub fn slice_secs(&self, start: f32, end: f32) -> Option<&[Stereo<f32>]> {
+        if start.is_nan() || end.is_nan() || start > end {
+            return None;
+        }
+        let frame = |secs: f32| {
+            ((secs as f64 * self.sampling_rate() as f64).max(0.0) as usize).min(self.len_frames())
+        };
+        Some(&self.data()[frame(start)..frame(end)])
+    }
+
+    /// Join two sounds, linearly blending the last `overlap_samples` frames of `before`
+    /// with the first `overlap_samples` frames of `after`.
+    ///
+    /// The resulting sound is `overlap_samples` frames shorter than the two sounds
+    /// together.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if sampling rates of the sounds differ or if `overlap_samples`
+    /// is longer than either sound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let before = Sound::new(Box::new([[1.0, 1.0]; 4]), 48000);
+    /// let after = Sound::new(Box::new([[0.0, 0.0]; 4]), 48000);
+    /// let joined = Sound::crossfade(&before, &after, 2).expect("sounds are too short");
+    /// assert_eq!(joined.data(), &[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0], [0.5, 0.5], [0.0, 0.0], [0.0, 0.0]]);
+    /// ```
+    pub fn crossfade(
+        before: &Sound,
+        after: &Sound,
+        overlap_samples: usize,
+    ) -> Result<Box<Sound>, StringError> {
+        if before.sampling_rate() != after.sampling_rate() {
+            return Err(StringError(format!(
+                "sampling rates differ: {} and {}",
+                before.sampling_rate(),
+                after.sampling_rate()
+            )));
+        }
+        if (overlap_samples > before.len_frames()) || (overlap_samples > after.len_frames()) {
+            return Err(StringError(format!(
+                "overlap of {} frames is longer than one of the sounds ({} and {} frames)",
+                overlap_samples,
+                before.len_frames(),
+                after.len_frames()
+            )));
+        }
+        let split = before.len_frames() - overlap_samples;
+        let (head, fading_out) = before.data().split_at(split);
+        let (fading_in, tail) = after.data().split_at(overlap_samples);
+        let blended = fading_out
+            .iter()
+            .zip(fading_in)
+            .enumerate()
+            .map(|(i, (a, b))| {
+                let t = i as f32 / overlap_samples as f32;
+                [a[0] * (1.0 - t) + b[0] * t, a[1] * (1.0 - t) + b[1] * t]
+            });
+        let data: Box<[Stereo<f32>]> = head
+            .iter()
+            .copied()
+            .chain(blended)
+            .chain(tail.iter().copied())
+            .collect();
+        Ok(Sound::new(data, before.sampling_rate()))
+    }
+
+    /// Apply a function to every frame, clamping the results to [-1, 1].
+    fn map_clamped(&self, f: impl Fn(usize, Stereo<f32>) -> Stereo<f32>) -> Box<Sound> {
+        let data = self
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| f(i, *x).map(|x| x.clamp(-1.0, 1.0)))
+            .collect();
+        Sound::new(data, self.sampling_rate())
+    }
+
+    /// Get the highest absolute sample on either side.
+    pub fn peak(&self) -> f32 {
+        self.data()
+            .iter()
+            .flatten()
+            .fold(0.0_f32, |acc, x| acc.max(x.abs()))
+    }
+
+    /// Get the root mean square of samples of both sides.
+    ///
+    /// Empty sound has RMS of 0.
+    pub fn rms(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .data()
+            .iter()
+            .flatten()
+            .map(|x| *x as f64 * *x as f64)
+            .sum();
+        (sum / (self.len_frames() * 2) as f64).sqrt() as f32
+    }
+
+    /// Get the largest absolute difference between samples of two sounds.
+    ///
+    /// Returns `None` if lengths or sampling rates of the sounds differ. A NaN sample
+    /// makes the difference NaN.
+    pub fn max_difference(&self, other: &Sound) -> Option<f32> {
+        if self.sampling_rate() != other.sampling_rate() || self.len_frames() != other.len_frames()
+        {
+            return None;
+        }
+        let diff = self
+            .data()
+            .iter()
+            .flatten()
+            .zip(other.data().iter().flatten())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0_f32, |acc, x| match acc.is_nan() || x.is_nan() {
+                true => f32::NAN,
+                false => acc.max(x),
+            });
+        Some(diff)
+    }
+
+    /// Check that two sounds have the same length and sampling rate, and that their
+    /// samples differ by at most `tolerance`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let a = Sound::new(Box::new([[0.5, 0.5]]), 48000);
+    /// let b = Sound::new(Box::new([[0.5001, 0.5]]), 48000);
+    /// assert!(a.approx_eq(&b, 1e-3));
+    /// assert!(!a.approx_eq(&b, 1e-5));
+    /// ```
+    pub fn approx_eq(&self, other: &Sound, tolerance: f32) -> bool {
+        self.max_difference(other).is_some_and(|x| x <= tolerance)
+    }
+
+    /// Scale the sound so that its [peak][Sound::peak()] is `target_peak`.
+    ///
+    /// Silent sounds are returned unchanged. Samples are not clamped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.25, -0.1]]), 48000);
+    /// assert_eq!(sound.normalize(0.5).data(), &[[0.5, -0.2]]);
+    /// ```
+    pub fn normalize(&self, target_peak: f32) -> Box<Sound> {
+        let peak = self.peak();
+        if peak == 0.0 || !peak.is_finite() {
+            return self.to_owned_sound();
+        }
+        let gain = target_peak / peak;
+        let data = self.data().iter().map(|x| x.map(|x| x * gain)).collect();
+        Sound::new(data, self.sampling_rate())
+    }
+
+    /// Multiply every sample by `gain`.
+    ///
+    /// Samples are clamped to [-1, 1].
+    pub fn amplify(&self, gain: f32) -> Box<Sound> {
+        self.map_clamped(|_, x| x.map(|x| x * gain))
+    }
+
+    /// Move the sound between the sides with constant-power panning.
+    ///
+    /// `pan` is clamped to [-1, 1], where -1 silences the right side and 1 silences
+    /// the left side. In the center, both sides are scaled by `1/sqrt(2)`. Samples
+    /// are clamped to [-1, 1].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.5, 0.5]]), 48000);
+    /// assert_eq!(sound.pan(-1.0).data(), &[[0.5, 0.0]]);
+    /// ```
+    pub fn pan(&self, pan: f32) -> Box<Sound> {
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        let (left, right) = (angle.cos(), angle.sin());
+        self.map_clamped(|_, x| [x[0] * left, x[1] * right])
+    }
+
+    /// Linearly raise the volume from silence over the first `fade_in_secs` seconds,
+    /// and lower it to silence over the last `fade_out_secs` seconds.
+    ///
+    /// If the fades overlap, both are applied. Samples are clamped to [-1, 1].
+    pub fn fade(&self, fade_in_secs: f32, fade_out_secs: f32) -> Box<Sound> {
+        let frames = |secs: f32| (secs.max(0.0) * self.sampling_rate() as f32) as usize;
+        let (fade_in, fade_out) = (frames(fade_in_secs), frames(fade_out_secs));
+        let len = self.len_frames();
+        self.map_clamped(|i, x| {
+            let mut gain = 1.0;
+            if i < fade_in {
+                gain *= i as f32 / fade_in as f32;
+            }
+            if len - i <= fade_out {
+                gain *= (len - i - 1) as f32 / fade_out as f32;
+            }
+            x.map(|x| x * gain)
+        })
+    }
+
+    /// Quantize samples to a signed integer of `bits` bits, as a DAC of that
+    /// resolution would output them.
+    ///
+    /// Full scale [-1, 1] is mapped to the integer range, samples are clamped to it and
+    /// truncated towards zero. NaN becomes silence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bits` is outside of 2..=32.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.3, -1.0]]), 48000);
+    /// let psg = sound.quantize(4).expect("bit depth is invalid");
+    /// assert_eq!(psg.data(), &[[0.25, -1.0]]);
+    /// ```
+    pub fn quantize(&self, bits: u8) -> Result<Box<Sound>, StringError> {
+        if !(2..=32).contains(&bits) {
+            return Err(StringError(format!(
+                "bit depth {bits} is outside of range [2, 32]"
+            )));
+        }
+        let data = self
+            .data()
+            .iter()
+            .map(|x| x.map(|x| quantize_sample(x as f64, bits) as f32));
+        Ok(Sound::from_exact(data, self.sampling_rate()))
+    }
+
+    /// Join sounds one after another.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `parts` is empty or if sampling rates of the sounds differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let a = Sound::new(Box::new([[0.0, 0.0]]), 48000);
+    /// let b = Sound::new(Box::new([[1.0, 1.0]]), 48000);
+    /// let joined = Sound::concat(&[&a, &b, &a]).expect("sampling rates differ");
+    /// assert_eq!(joined.data(), &[[0.0, 0.0], [1.0, 1.0], [0.0, 0.0]]);
+    /// ```
+    pub fn concat(parts: &[&Sound]) -> Result<Box<Sound>, StringError> {
+        Sound::concat_with_policy(parts, RateMismatchPolicy::Error)
+    }
+
+    /// Join sounds one after another, handling different sampling rates according
+    /// to `policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `parts` is empty, if rates differ and the policy is
+    /// [`Error`][RateMismatchPolicy::Error], or if resampling fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::{RateMismatchPolicy, ResampleQuality, Sound};
+    /// let a = Sound::new(Box::new([[0.0, 0.0]; 480]), 48000);
+    /// let b = Sound::new(Box::new([[1.0, 1.0]; 441]), 44100);
+    /// let policy = RateMismatchPolicy::ResampleToFirst(ResampleQuality::Linear);
+    /// let joined = Sound::concat_with_policy(&[&a, &b], policy).expect("can not resample");
+    /// assert_eq!(joined.sampling_rate(), 48000);
+    /// assert_eq!(joined.len_frames(), 960);
+    /// ```
+    pub fn concat_with_policy(
+        parts: &[&Sound],
+        policy: RateMismatchPolicy,
+    ) -> Result<Box<Sound>, StringError> {
+        let (rate, quality) = policy.common_rate(parts.iter().map(|x| x.sampling_rate()))?;
+        let resampled = Sound::resample_all(parts, rate, quality)?;
+        let data = parts
+            .iter()
+            .zip(&resampled)
+            .flat_map(|(x, resampled)| resampled.as_deref().unwrap_or(x).data())
+            .copied();
+        Ok(Sound::from_frames(data, rate))
+    }
+
+    /// Make a new sound with `other` played after this one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if sampling rates of the sounds differ.
+    pub fn append(&self, other: &Sound) -> Result<Box<Sound>, StringError> {
+        Sound::concat(&[self, other])
+    }
+
+    /// Play sounds at the same time, adding them together. The result is as long as
+    /// the longest sound.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `parts` is empty or if sampling rates of the sounds differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let a = Sound::new(Box::new([[0.5, 0.5], [0.5, 0.5]]), 48000);
+    /// let b = Sound::new(Box::new([[0.25, -0.25]]), 48000);
+    /// let mixed = Sound::overlay(&[&a, &b]).expect("sampling rates differ");
+    /// assert_eq!(mixed.data(), &[[0.75, 0.25], [0.5, 0.5]]);
+    /// ```
+    pub fn overlay(parts: &[&Sound]) -> Result<Box<Sound>, StringError> {
+        Sound::overlay_with_policy(parts, RateMismatchPolicy::Error)
+    }
+
+    /// Play sounds at the same time, handling different sampling rates according to
+    /// `policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `parts` is empty, if rates differ and the policy is
+    /// [`Error`][RateMismatchPolicy::Error], or if resampling fails.
+    pub fn overlay_with_policy(
+        parts: &[&Sound],
+        policy: RateMismatchPolicy,
+    ) -> Result<Box<Sound>, StringError> {
+        let (rate, quality) = policy.common_rate(parts.iter().map(|x| x.sampling_rate()))?;
+        let resampled = Sound::resample_all(parts, rate, quality)?;
+        let mut data: Vec<Stereo<f32>> = Vec::new();
+        for (x, resampled) in parts.iter().zip(&resampled) {
+            let sound = resampled.as_deref().unwrap_or(x).data();
+            if sound.len() > data.len() {
+                data.resize(sound.len(), [0.0, 0.0]);
+            }
+            data.iter_mut()
+                .zip(sound)
+                .for_each(|(x, y)| *x = [x[0] + y[0], x[1] + y[1]]);
+        }
+        Ok(Sound::new(data.into_boxed_slice(), rate))
+    }
+
+    /// Resample the sounds whose rate is not `rate`, leaving `None` for the others.
+    fn resample_all(
+        parts: &[&Sound],
+        rate: u32,
+        quality: ResampleQuality,
+    ) -> Result<Vec<Option<Box<Sound>>>, StringError> {
+        parts
+            .iter()
+            .map(|x| match x.sampling_rate() == rate {
+                true => Ok(None),
+                false => x.resample_with_quality(rate, quality).map(Some),
+            })
+            .collect()
+    }
+
+    /// Play the sound up to `loop_end`, then repeat the frames between `loop_start`
+    /// and `loop_end` forever.
+    ///
+    /// The iterator never ends, use [`take`][Iterator::take] to get a sound of
+    /// concrete length.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `loop_end` is past the end of the sound or if the loop is
+    /// empty (`loop_start >= loop_end`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]), 48000);
+    /// let looped: Vec<_> = sound.loop_at(1, 3).expect("loop is out of bounds").take(7).collect();
+    /// assert_eq!(looped, [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [1.0, 1.0], [2.0, 2.0], [1.0, 1.0], [2.0, 2.0]]);
+    /// ```
+    pub fn loop_at(
+        &self,
+        loop_start: usize,
+        loop_end: usize,
+    ) -> Result<impl Iterator<Item = Stereo<f32>> + '_, StringError> {
+        if loop_end > self.len_frames() {
+            return Err(StringError(format!(
+                "loop end {loop_end} is past the end of the sound ({} frames)",
+                self.len_frames()
+            )));
+        }
+        if loop_start >= loop_end {
+            return Err(StringError(format!(
+                "loop is empty: start {loop_start} is not before end {loop_end}"
+            )));
+        }
+        let intro = self.data()[..loop_end].iter().copied();
+        let body = self.data()[loop_start..loop_end].iter().copied().cycle();
+        Ok(intro.chain(body))
+    }
+
+    /// Convert the sound to another sampling rate with linear interpolation.
+    ///
+    /// The length of the result is scaled by the ratio of the rates, rounded down.
+    /// Past the last frame, the sound holds the last frame's value instead of fading
+    /// to silence. If the rates already match, the sound is copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sampling rate of the sound or `target_rate` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[0.0, 1.0], [1.0, 0.0]]), 24000);
+    /// let upsampled = sound.resample(48000).unwrap();
+    /// assert_eq!(upsampled.sampling_rate(), 48000);
+    /// assert_eq!(upsampled.data(), &[[0.0, 1.0], [0.5, 0.5], [1.0, 0.0], [1.0, 0.0]]);
+    /// ```
+    pub fn resample(&self, target_rate: u32) -> Result<Box<Sound>, StringError> {
+        self.resample_with_quality(target_rate, ResampleQuality::Linear)
+    }
+
+    /// Convert the sound to another sampling rate with the given interpolation.
+    ///
+    /// Like with [`resample()`][Sound::resample()], the length of the result is
+    /// scaled by the ratio of the rates, rounded down, and the sound holds its edge
+    /// frames past its ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sampling rate of the sound or `target_rate` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::{ResampleQuality, Sound};
+    /// let sound = Sound::new(Box::new([[0.0, 1.0], [1.0, 0.0]]), 24000);
+    /// let upsampled = sound.resample_with_quality(48000, ResampleQuality::Nearest).unwrap();
+    /// assert_eq!(upsampled.data(), &[[0.0, 1.0], [1.0, 0.0], [1.0, 0.0], [1.0, 0.0]]);
+    /// ```
+    pub fn resample_with_quality(
+        &self,
+        target_rate: u32,
+        quality: ResampleQuality,
+    ) -> Result<Box<Sound>, StringError> {
+        let rate = self.sampling_rate();
+        if rate == 0 || target_rate == 0 {
+            return Err(StringError(format!(
+                "can not resample from {rate} Hz to {target_rate} Hz"
+            )));
+        }
+        let Some(&last) = self.data().last() else {
+            return Ok(Sound::new(Box::new([]), target_rate));
+        };
+        if rate == target_rate {
+            return Ok(self.to_owned_sound());
+        }
+        let frames = (self.len_frames() as u64 * target_rate as u64 / rate as u64) as usize;
+        let step = rate as f64 / target_rate as f64;
+        let data: Box<[Stereo<f32>]> = match quality {
+            ResampleQuality::Nearest => (0..frames)
+                .map(|i| {
+                    self.data()[((i as f64 * step).round() as usize).min(self.len_frames() - 1)]
+                })
+                .collect(),
+            ResampleQuality::Linear => {
+                let held = self.data().iter().copied().chain(std::iter::repeat(last));
+                let mut source = signal::from_iter(held);
+                let interp = Linear::new(source.next(), source.next());
+                source
+                    .from_hz_to_hz(interp, rate as f64, target_rate as f64)
+                    .take(frames)
+                    .collect()
+            }
+            ResampleQuality::Sinc => (0..frames)
+                .map(|i| sinc_frame(self.data(), i as f64 * step, step))
+                .collect(),
+        };
+        Ok(Sound::new(data, target_rate))
+    }
+}
+
+/// Interpolate the frame at position `t` of `data` with a Hann-windowed sinc, where
+/// `step` is the number of original frames per new frame. Frames before the start and
+/// past the end of `data` repeat the edge frames.
+fn sinc_frame(data: &[Stereo<f32>], t: f64, step: f64) -> Stereo<f32> {
+    use std::f64::consts::PI;
+
+    // When the rate is lowered, the cutoff moves down with it so that frequencies
+    // above the new Nyquist frequency are filtered out instead of aliasing
+    let cutoff = step.recip().min(1.0);
+    let radius = SINC_DEPTH as f64 / cutoff;
+    let last = data.len() as i64 - 1;
+    let mut out = [0.0_f64; 2];
+    for n in (t - radius).ceil() as i64..=(t + radius).floor() as i64 {
+        let x = (t - n as f64) * cutoff;
+        let sinc = match x == 0.0 {
+            true => 1.0,
+            false => (PI * x).sin() / (PI * x),
+        };
+        let window = 0.5 + 0.5 * (PI * x / SINC_DEPTH as f64).cos();
+        let frame = data[n.clamp(0, last) as usize];
+        out[0] += cutoff * sinc * window * frame[0] as f64;
+        out[1] += cutoff * sinc * window * frame[1] as f64;
+    }
+    out.map(|x| x as f32)
+}
+
+/// Iterator whose exact length is known from its size hint.
+struct ExactLen<I>(I, usize);
+
+impl<I: Iterator> Iterator for ExactLen<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.0.next()?;
+        self.1 = self.1.saturating_sub(1);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.1, Some(self.1))
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for ExactLen<I> {}
+
+impl Sound {
+    /// Copy the sound into a new box.
+    pub fn to_owned_sound(&self) -> Box<Sound> {
+        Sound::new(self.data().into(), self.sampling_rate())
+    }
+}
+
+/// Interleaved little-endian stereo PCM.
+impl Sound {
+    /// Convert into interleaved little-endian 32-bit floats.
+    pub fn to_interleaved_f32_le(&self) -> Vec<u8> {
+        self.data()
+            .iter()
+            .flatten()
+            .flat_map(|x| x.to_le_bytes())
+            .collect()
+    }
+
+    /// Convert into interleaved little-endian 16-bit integers. Samples outside
+    /// [-1, 1] are clamped.
+    ///
+    /// If `dither` is true, triangular noise of one least significant bit is added
+    /// to the samples before rounding. The noise is seeded, so the output is
+    /// reproducible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::Sound;
+    /// let sound = Sound::new(Box::new([[1.0, -2.0]]), 48000);
+    /// assert_eq!(sound.to_interleaved_i16_le(false), [0xff, 0x7f, 0x00, 0x80]);
+    /// ```
+    pub fn to_interleaved_i16_le(&self, dither: bool) -> Vec<u8> {
+        let mut rng = Rng::new(DITHER_SEED);
+        self.data()
+            .iter()
+            .flatten()
+            .flat_map(|x| {
+                let noise = match dither {
+                    true => rng.next_f64() - rng.next_f64(),
+                    false => 0.0,
+                };
+                (quantize_to_int(*x, 16, noise) as i16).to_le_bytes()
+            })
+            .collect()
+    }
+
+    /// Create a sound from interleaved little-endian 32-bit floats.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of the data is not a whole number of frames.
+    pub fn from_interleaved_f32_le(
+        data: &[u8],
+        sampling_rate: u32,
+    ) -> Result<Box<Sound>, StringError> {
+        Self::from_interleaved(data, 4, sampling_rate, |x| {
+            f32::from_le_bytes([x[0], x[1], x[2], x[3]])
+        })
+    }
+
+    /// Create a sound from interleaved little-endian 16-bit integers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of the data is not a whole number of frames.
+    pub fn from_interleaved_i16_le(
+        data: &[u8],
+        sampling_rate: u32,
+    ) -> Result<Box<Sound>, StringError> {
+        Self::from_interleaved(data, 2, sampling_rate, |x| {
+            i16::from_le_bytes([x[0], x[1]]) as f32 / 32768.0
+        })
+    }
+
+    fn from_interleaved(
+        data: &[u8],
+        sample_bytes: usize,
+        sampling_rate: u32,
+        sample: impl Fn(&[u8]) -> f32,
+    ) -> Result<Box<Sound>, StringError> {
+        let frame_bytes = sample_bytes * 2;
+        if !data.len().is_multiple_of(frame_bytes) {
+            return Err(StringError(format!(
+                "{} bytes is not a whole number of {frame_bytes}-byte frames",
+                data.len()
+            )));
+        }
+        let frames = data
+            .chunks_exact(frame_bytes)
+            .map(|x| [sample(&x[..sample_bytes]), sample(&x[sample_bytes..])]);
+        Ok(Sound::from_exact(frames, sampling_rate))
+    }
+}
+
+impl ToOwned for Sound {
+    type Owned = Box<Sound>;
+
+    fn to_owned(&self) -> Self::Owned {
+        self.to_owned_sound()
+    }
+}
+
+impl Clone for Box<Sound> {
+    fn clone(&self) -> Self {
+        self.to_owned_sound()
+    }
+}
+
+/// Serialized the same way as [`SoundVec`].
+impl Serialize for Sound {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut out = serializer.serialize_struct("Sound", 2)?;
+        out.serialize_field("rate", &self.sampling_rate())?;
+        out.serialize_field("data", self.data())?;
+        out.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<Sound> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SoundVec::deserialize(deserializer).map(SoundVec::into_sound)
+    }
+}
+
+/// Assert that two sounds are [approximately equal][Sound::approx_eq].
+///
+/// Takes two values that dereference to [`Sound`] and a tolerance. On failure, the
+/// message contains the largest difference between samples, or says that lengths or
+/// sampling rates differ.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::{assert_sound_approx_eq, types::Sound};
+/// let a = Sound::new(Box::new([[0.5, 0.5]]), 48000);
+/// let b = a.amplify(1.0001);
+/// assert_sound_approx_eq!(a, b, 1e-3);
+/// ```
+#[cfg(feature = "test-util")]
+#[macro_export]
+macro_rules! assert_sound_approx_eq {
+    ($left:expr, $right:expr, $tolerance:expr $(,)?) => {{
+        let (left, right): (&$crate::types::Sound, &$crate::types::Sound) = (&$left, &$right);
+        let tolerance: f32 = $tolerance;
+        match left.max_difference(right) {
+            Some(diff) => assert!(
+                diff <= tolerance,
+                "sounds differ by {} (tolerance is {})",
+                diff,
+                tolerance
+            ),
+            None => panic!(
+                "sounds have different shapes: {} frames at {} Hz and {} frames at {} Hz",
+                left.len_frames(),
+                left.sampling_rate(),
+                right.len_frames(),
+                right.sampling_rate()
+            ),
+        }
+    }};
+}
+
+/// Quantize a sample to a signed integer of `bits` bits, where full scale is [-1, 1].
+///
+/// `bits` is expected to be in range 2..=32.
+pub(crate) fn quantize_sample(x: f64, bits: u8) -> f64 {
+    if x.is_nan() {
+        return 0.0;
+    }
+    let scale = (1_u64 << (bits - 1)) as f64;
+    (x * scale).clamp(-scale, scale - 1.0).trunc() / scale
+}
+
+/// Seed of the dither noise, so that exports are reproducible.
+pub(crate) const DITHER_SEED: u64 = 0x006D_6C65_6D6C;
+
+/// Scale a sample to a signed integer of `bits` bits, where [-1, 1) maps onto the
+/// full range of the integer, adding `noise` (in least significant bits) before
+/// rounding. The result is clamped, and NaN becomes 0.
+pub(crate) fn quantize_to_int(x: f32, bits: u32, noise: f64) -> i64 {
+    let scale = (1_i64 << (bits - 1)) as f64;
+    let value = (x as f64 * scale + noise).round();
+    match value.is_nan() {
+        true => 0,
+        false => (value as i64).clamp(-(scale as i64), scale as i64 - 1),
+    }
 }
 
 impl std::convert::AsRef<[Stereo<f32>]> for Sound {
@@ -78,3 +1371,979 @@ impl std::convert::AsRef<[Stereo<f32>]> for Sound {
         self.data()
     }
 }
+
+/// Owned, resizable PCM data with sampling rate.
+///
+/// Unlike [`Sound`], it can be cloned, serialized and grown in place. Use it as a
+/// buffer while building a sound and convert it with
+/// [`into_sound()`][SoundVec::into_sound()] when done.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::{Sound, SoundVec};
+/// let mut buffer = SoundVec { rate: 48000, data: Vec::new() };
+/// buffer.data.extend([[0.5, 0.5]; 4]);
+/// let sound = buffer.clone().into_sound();
+/// assert_eq!(SoundVec::from(sound.as_ref()), buffer);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoundVec {
+    /// Sampling rate.
+    pub rate: u32,
+
+    /// PCM data.
+    pub data: Vec<Stereo<f32>>,
+}
+
+impl SoundVec {
+    /// Convert into a [`Sound`].
+    pub fn into_sound(self) -> Box<Sound> {
+        Sound::new(self.data.into_boxed_slice(), self.rate)
+    }
+}
+
+impl From<&Sound> for SoundVec {
+    fn from(sound: &Sound) -> Self {
+        SoundVec {
+            rate: sound.sampling_rate(),
+            data: sound.data().to_vec(),
+        }
+    }
+}
+
+impl std::convert::AsRef<[Stereo<f32>]> for SoundVec {
+    fn as_ref(&self) -> &[Stereo<f32>] {
+        &self.data
+    }
+}
+
+/// Sound with an optional sustain loop, such as an instrument sample.
+///
+/// The sound is split into three parts: the attack before the loop, the loop and the
+/// tail after it. A note of any length is rendered by repeating the loop and
+/// playing the tail at the end of the note.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::{LoopedSound, Sound};
+/// let sample = Sound::new(Box::new([[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]), 48000);
+/// let looped = LoopedSound::new(sample, 1, 3).expect("loop points are invalid");
+/// assert_eq!(
+///     looped.render(6).data(),
+///     &[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopedSound {
+    sound: Box<Sound>,
+    loop_points: Option<(usize, usize)>,
+}
+
+impl LoopedSound {
+    /// Create a sound that loops the frames from `loop_start` up to `loop_end`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `loop_end` is past the end of the sound or if the loop is
+    /// empty (`loop_start >= loop_end`).
+    pub fn new(sound: Box<Sound>, loop_start: usize, loop_end: usize) -> Result<Self, StringError> {
+        // Checks the loop points
+        let _ = sound.loop_at(loop_start, loop_end)?;
+        Ok(LoopedSound {
+            sound,
+            loop_points: Some((loop_start, loop_end)),
+        })
+    }
+
+    /// Create a sound without a loop.
+    pub fn without_loop(sound: Box<Sound>) -> Self {
+        LoopedSound {
+            sound,
+            loop_points: None,
+        }
+    }
+
+    /// Get the sound.
+    pub fn sound(&self) -> &Sound {
+        &self.sound
+    }
+
+    /// Get the start and the end of the loop, if there is one.
+    pub fn loop_points(&self) -> Option<(usize, usize)> {
+        self.loop_points
+    }
+
+    /// Render exactly `total_frames` frames, releasing the note so that the tail ends
+    /// on the last frame.
+    ///
+    /// Here the note length alone decides where the release is. If the sound is longer
+    /// than `total_frames`, it is cut, and the tail is not played. Otherwise, the loop
+    /// is repeated and the tail is played so that it ends on the last frame. Sounds
+    /// without a loop are padded with silence. Use
+    /// [render_released](LoopedSound::render_released) to release at a given frame.
+    pub fn render(&self, total_frames: usize) -> Box<Sound> {
+        let data = self.sound.data();
+        let rate = self.sound.sampling_rate();
+        if total_frames <= data.len() {
+            return Sound::new(data[..total_frames].into(), rate);
+        }
+        match self.loop_points {
+            Some((start, end)) => {
+                let tail = &data[end..];
+                let sustain = total_frames - tail.len();
+                // Loop points were checked in new()
+                let body = self.sound.loop_at(start, end).unwrap().take(sustain);
+                Sound::from_frames(body.chain(tail.iter().copied()), rate)
+            }
+            None => {
+                let silence = std::iter::repeat_n([0.0, 0.0], total_frames - data.len());
+                Sound::from_frames(data.iter().copied().chain(silence), rate)
+            }
+        }
+    }
+
+    /// Render exactly `total_frames` frames, releasing the note at `release_frame`.
+    ///
+    /// Until the release, the sound is played with its loop repeated. At the release,
+    /// playback leaves the loop and continues with the tail. If the release comes
+    /// before the end of the first pass through the loop, the rest of the sound is
+    /// played as is. The result is padded with silence or cut to `total_frames`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::{LoopedSound, Sound};
+    /// let sample = Sound::new(Box::new([[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]), 48000);
+    /// let looped = LoopedSound::new(sample, 1, 3).expect("loop points are invalid");
+    /// assert_eq!(
+    ///     looped.render_released(4, 6).data(),
+    ///     &[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [1.0, 1.0], [3.0, 3.0], [0.0, 0.0]]
+    /// );
+    /// ```
+    pub fn render_released(&self, release_frame: usize, total_frames: usize) -> Box<Sound> {
+        let data = self.sound.data();
+        let rate = self.sound.sampling_rate();
+        let silence = std::iter::repeat([0.0, 0.0]);
+        match self.loop_points {
+            Some((start, end)) if release_frame >= end => {
+                // Loop points were checked in new()
+                let body = self.sound.loop_at(start, end).unwrap().take(release_frame);
+                let tail = data[end..].iter().copied();
+                Sound::from_frames(body.chain(tail).chain(silence).take(total_frames), rate)
+            }
+            _ => Sound::from_frames(data.iter().copied().chain(silence).take(total_frames), rate),
+        }
+    }
+}
+
+/// PCM data with sampling rate, stored in blocks of fixed size.
+///
+/// Growing a stream never moves frames that were already written, and completed
+/// blocks can be [drained][SoundStream::drain_complete()] while rendering continues,
+/// for example, to write them to disk. Converting between a stream and a [`Sound`]
+/// keeps all frames as they are.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::{Sound, SoundStream};
+/// let mut stream = SoundStream::with_block_frames(48000, 2);
+/// stream
+///     .extend_from_sound(&Sound::new(Box::new([[0.5, 0.5]; 3]), 48000))
+///     .expect("sampling rates differ");
+/// stream.push_frame([0.0, 0.0]);
+/// let done: Vec<Box<Sound>> = stream.drain_complete().collect();
+/// assert_eq!(done.len(), 2);
+/// assert!(stream.is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundStream {
+    rate: u32,
+    block_frames: usize,
+    blocks: Vec<Box<[Stereo<f32>]>>,
+    tail: Vec<Stereo<f32>>,
+}
+
+impl SoundStream {
+    /// Number of frames in a block of a stream created with [`new()`][SoundStream::new()].
+    pub const DEFAULT_BLOCK_FRAMES: usize = 4096;
+
+    /// Create an empty stream with blocks of default size.
+    pub fn new(sampling_rate: u32) -> Self {
+        Self::with_block_frames(sampling_rate, Self::DEFAULT_BLOCK_FRAMES)
+    }
+
+    /// Create an empty stream with blocks of `block_frames` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_frames` is 0.
+    pub fn with_block_frames(sampling_rate: u32, block_frames: usize) -> Self {
+        assert!(block_frames > 0, "block size has to be positive");
+        SoundStream {
+            rate: sampling_rate,
+            block_frames,
+            blocks: Vec::new(),
+            tail: Vec::with_capacity(block_frames),
+        }
+    }
+
+    /// Get sampling rate.
+    pub fn sampling_rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Get number of frames in one block.
+    pub fn block_frames(&self) -> usize {
+        self.block_frames
+    }
+
+    /// Get number of frames in the stream.
+    pub fn len_frames(&self) -> usize {
+        self.blocks.len() * self.block_frames + self.tail.len()
+    }
+
+    /// Check if the stream has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len_frames() == 0
+    }
+
+    /// Append a frame.
+    pub fn push_frame(&mut self, frame: Stereo<f32>) {
+        self.tail.push(frame);
+        if self.tail.len() == self.block_frames {
+            let block = std::mem::replace(&mut self.tail, Vec::with_capacity(self.block_frames));
+            self.blocks.push(block.into_boxed_slice());
+        }
+    }
+
+    /// Append all frames of a sound.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if sampling rate of the sound differs from that of the stream.
+    pub fn extend_from_sound(&mut self, sound: &Sound) -> Result<(), StringError> {
+        if sound.sampling_rate() != self.rate {
+            return Err(StringError(format!(
+                "sampling rates differ: {} and {}",
+                self.rate,
+                sound.sampling_rate()
+            )));
+        }
+        sound.data().iter().for_each(|x| self.push_frame(*x));
+        Ok(())
+    }
+
+    /// Append all frames of a sound, handling a different sampling rate according
+    /// to `policy`. The stream counts as the first sound.
+    ///
+    /// With [`ResampleToHighest`][RateMismatchPolicy::ResampleToHighest] and a sound
+    /// of a higher rate, the frames that are still in the stream are resampled and
+    /// the stream takes the rate of the sound. Blocks that were already
+    /// [drained][SoundStream::drain_complete()] keep the old rate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if rates differ and the policy is
+    /// [`Error`][RateMismatchPolicy::Error], or if resampling fails. The stream is
+    /// not changed then.
+    pub fn extend_from_sound_with_policy(
+        &mut self,
+        sound: &Sound,
+        policy: RateMismatchPolicy,
+    ) -> Result<(), StringError> {
+        let (rate, quality) = policy.common_rate([self.rate, sound.sampling_rate()].into_iter())?;
+        if rate != self.rate {
+            let old = Sound::from_frames(self.frames(), self.rate)
+                .resample_with_quality(rate, quality)?;
+            *self = SoundStream::with_block_frames(rate, self.block_frames);
+            self.extend_from_sound(&old)?;
+        }
+        match sound.sampling_rate() == rate {
+            true => self.extend_from_sound(sound),
+            false => self.extend_from_sound(&sound.resample_with_quality(rate, quality)?),
+        }
+    }
+
+    /// Iterate over all frames of the stream.
+    pub fn frames(&self) -> impl Iterator<Item = Stereo<f32>> + '_ {
+        self.blocks
+            .iter()
+            .flat_map(|x| x.iter())
+            .chain(self.tail.iter())
+            .copied()
+    }
+
+    /// Remove completed blocks from the stream and return them as sounds, leaving
+    /// only the incomplete block.
+    pub fn drain_complete(&mut self) -> impl Iterator<Item = Box<Sound>> + '_ {
+        let rate = self.rate;
+        self.blocks.drain(..).map(move |x| Sound::new(x, rate))
+    }
+
+    /// Remove all frames from the stream and return them as one sound.
+    pub fn flush_to_sound(&mut self) -> Box<Sound> {
+        let mut data = Vec::with_capacity(self.len_frames());
+        self.blocks
+            .drain(..)
+            .for_each(|x| data.extend_from_slice(&x));
+        data.append(&mut self.tail);
+        Sound::from_exact(data.into_iter(), self.rate)
+    }
+}
+
+impl From<&Sound> for SoundStream {
+    fn from(sound: &Sound) -> Self {
+        let mut out = SoundStream::new(sound.sampling_rate());
+        sound.data().iter().for_each(|x| out.push_frame(*x));
+        out
+    }
+}
+
+impl From<SoundStream> for Box<Sound> {
+    fn from(mut stream: SoundStream) -> Self {
+        stream.flush_to_sound()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_velocity_is_half() {
+        assert_eq!(Note::default().velocity, 128);
+        assert_eq!(ReadyNote::default().velocity, 128);
+    }
+
+    #[test]
+    fn crossfade_has_expected_length() {
+        let before = Sound::new(Box::new([[0.5, 0.5]; 10]), 48000);
+        let after = Sound::new(Box::new([[0.25, 0.25]; 6]), 48000);
+        let joined = Sound::crossfade(&before, &after, 4).unwrap();
+        assert_eq!(joined.data().len(), 12);
+        assert_eq!(joined.data()[0], [0.5, 0.5]);
+        assert_eq!(joined.data()[11], [0.25, 0.25]);
+    }
+
+    #[test]
+    fn crossfade_without_overlap_concatenates() {
+        let before = Sound::new(Box::new([[0.5, 0.5]; 2]), 48000);
+        let after = Sound::new(Box::new([[0.25, 0.25]; 2]), 48000);
+        let joined = Sound::crossfade(&before, &after, 0).unwrap();
+        assert_eq!(
+            joined.data(),
+            &[[0.5, 0.5], [0.5, 0.5], [0.25, 0.25], [0.25, 0.25]]
+        );
+    }
+
+    #[test]
+    fn crossfade_rejects_long_overlap() {
+        let before = Sound::new(Box::new([[0.5, 0.5]; 10]), 48000);
+        let after = Sound::new(Box::new([[0.25, 0.25]; 3]), 48000);
+        assert!(Sound::crossfade(&before, &after, 4).is_err());
+        assert!(Sound::crossfade(&after, &before, 4).is_err());
+    }
+
+    #[test]
+    fn crossfade_rejects_different_rates() {
+        let before = Sound::new(Box::new([[0.5, 0.5]; 10]), 48000);
+        let after = Sound::new(Box::new([[0.25, 0.25]; 10]), 44100);
+        assert!(Sound::crossfade(&before, &after, 4).is_err());
+    }
+
+    #[test]
+    fn sound_vec_round_trips() {
+        let sound = Sound::new(Box::new([[0.25, -0.5], [1.0, 0.0]]), 44100);
+        let buffer = SoundVec::from(sound.as_ref());
+        assert_eq!(buffer.rate, 44100);
+        assert_eq!(buffer.as_ref(), sound.data());
+        assert_eq!(buffer.into_sound(), sound);
+
+        let empty = Sound::new(Box::new([]), 22050);
+        let buffer = SoundVec::from(empty.as_ref());
+        assert!(buffer.data.is_empty());
+        assert_eq!(buffer.into_sound(), empty);
+    }
+
+    #[test]
+    fn length_helpers_agree() {
+        let sound = Sound::new(Box::new([[0.0, 0.0]; 441]), 44100);
+        assert_eq!(sound.len_frames(), 441);
+        assert_eq!(sound.duration_secs(), 0.01);
+        assert!(!sound.is_empty());
+        let silence = Sound::silence(0.01, 44100);
+        assert_eq!(*silence, *sound);
+        assert!(Sound::silence(-1.0, 48000).is_empty());
+        assert!(Sound::silence(f32::NAN, 48000).is_empty());
+        assert_eq!(Sound::new(Box::new([]), 0).duration_secs(), 0.0);
+    }
+
+    #[test]
+    fn slices_are_clamped() {
+        let data: Box<[Stereo<f32>]> = (0..10).map(|x| [x as f32, x as f32]).collect();
+        let sound = Sound::new(data, 10);
+        assert_eq!(sound.split_at_frame(20).1.len(), 0);
+        assert_eq!(sound.split_at_frame(0).1.len(), 10);
+        assert_eq!(sound.slice_secs(0.25, 0.5).unwrap(), &sound.data()[2..5]);
+        assert_eq!(sound.slice_secs(-1.0, 5.0).unwrap(), sound.data());
+        assert_eq!(sound.slice_secs(0.5, 0.5).unwrap().len(), 0);
+        assert!(sound.slice_secs(0.5, 0.25).is_none());
+        assert!(sound.slice_secs(f32::NAN, 0.5).is_none());
+    }
+
+    #[test]
+    fn clone_is_bit_exact() {
+        let sound = Sound::new(Box::new([[f32::MIN_POSITIVE, -0.0], [1.0e-40, 0.1]]), 44100);
+        let copy = sound.clone();
+        assert_eq!(copy.sampling_rate(), 44100);
+        let bits =
+            |x: &Sound| -> Vec<u32> { x.data().iter().flatten().map(|x| x.to_bits()).collect() };
+        assert_eq!(bits(&copy), bits(&sound));
+        assert_eq!(bits(&sound.as_ref().to_owned()), bits(&sound));
+    }
+
+    #[test]
+    fn serde_round_trips() {
+        let sound = Sound::new(Box::new([[0.1, -0.0], [1.0e-40, f32::MAX]]), 22050);
+        let json = serde_json::to_string(sound.as_ref()).unwrap();
+        let back: Box<Sound> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.sampling_rate(), 22050);
+        let bits =
+            |x: &Sound| -> Vec<u32> { x.data().iter().flatten().map(|x| x.to_bits()).collect() };
+        assert_eq!(bits(&back), bits(&sound));
+        // Same format as SoundVec
+        let buffer: SoundVec = serde_json::from_str(&json).unwrap();
+        assert_eq!(buffer.into_sound(), sound);
+    }
+
+    #[test]
+    fn key_signature_respects_naturals() {
+        let f_major = KeySignature::from_sharps_flats(-1);
+        assert_eq!(f_major.accidentals, [0, 0, 0, 0, 0, 0, -1]);
+        assert_eq!(
+            KeySignature::from_sharps_flats(3).accidentals,
+            [1, 0, 0, 1, 1, 0, 0]
+        );
+        assert_eq!(KeySignature::from_sharps_flats(-100).accidentals, [-1; 7]);
+
+        let play = |pitch: i8, natural: bool| {
+            let note = Note {
+                pitch: Some(pitch),
+                natural,
+                ..Default::default()
+            };
+            f_major.apply(&note).pitch.unwrap()
+        };
+        assert_eq!(play(11, false), 10);
+        assert_eq!(play(-1, false), -2);
+        assert_eq!(play(23, false), 22);
+        assert_eq!(play(11, true), 11);
+        // Already has an accidental
+        assert_eq!(play(10, false), 10);
+        assert_eq!(play(0, false), 0);
+
+        let rest = Note::rest(TickDuration::new(4));
+        assert!(f_major.apply(&rest).is_rest());
+        let sharp = KeySignature::new([0, 0, 0, 0, 0, 0, 1]);
+        let top = Note {
+            pitch: Some(119),
+            ..Default::default()
+        };
+        assert_eq!(sharp.apply(&top).pitch, Some(120));
+    }
+
+    #[test]
+    fn tick_durations_from_fractions() {
+        let ticks = |denominator, dots| {
+            TickDuration::from_fraction(192, denominator, dots).map(TickDuration::ticks)
+        };
+        assert_eq!(ticks(1, 1), Some(288));
+        assert_eq!(ticks(4, 0), Some(48));
+        assert_eq!(ticks(4, 2), Some(84));
+        assert_eq!(ticks(3, 0), Some(64));
+        assert_eq!(ticks(3, 6), Some(127));
+        // 64 ticks can not be halved 7 times
+        assert_eq!(ticks(3, 7), None);
+        assert_eq!(ticks(5, 0), None);
+        assert_eq!(ticks(0, 0), None);
+        assert_eq!(ticks(384, 0), None);
+
+        let quarter = TickDuration::from_fraction(192, 4, 0).unwrap();
+        assert_eq!(quarter.tie(quarter).map(TickDuration::ticks), Some(96));
+        let longest = TickDuration::new(u32::MAX).unwrap();
+        assert_eq!(longest.tie(quarter), None);
+        assert_eq!(TickDuration::from(NonZeroU8::MAX).ticks(), 255);
+    }
+
+    #[test]
+    fn equal_temperament_is_pinned_to_a4() {
+        let hz = |semitones, cents, octave| semitones_to_hz(8.1758, semitones, cents, octave);
+        assert!((hz(9, 0, 4) - 440.0).abs() < 0.01);
+        assert!((hz(0, 0, 4) - 261.63).abs() < 0.01);
+        // C-1 itself is below octave 0
+        assert!((hz(-12, 0, 0) - 8.1758).abs() < 1e-9);
+        assert!((hz(21, 0, 3) - hz(9, 0, 4)).abs() < 1e-9);
+        assert!((hz(9, -1200, 5) - hz(9, 0, 4)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn from_note_combines_cents() {
+        let params = NoteConversionParams {
+            c_neg1_hz: 8.1758,
+            tick_length: 0.5,
+            octave: 4,
+            post_release_ticks: 1,
+            added_cents: 50,
+        };
+        let note = Note {
+            pitch: Some(9),
+            cents: 50,
+            percussive: true,
+            velocity: 7,
+            ..Default::default()
+        };
+        let ready = ReadyNote::from_note(&note, &params);
+        assert!((ready.pitch.unwrap() - 466.16).abs() < 0.01);
+        assert_eq!((ready.len, ready.decay_time), (0.0, 0.5));
+        assert!(ready.percussive);
+        assert_eq!(ready.velocity, 7);
+        assert_eq!(ReadyNote::from_note(&Note::rest(None), &params).pitch, None);
+    }
+
+    #[test]
+    fn transpose_rejects_unrepresentable_pitch() {
+        let note = Note {
+            pitch: Some(-2),
+            cents: 15,
+            ..Default::default()
+        };
+        let up = note.transpose(5).unwrap();
+        assert_eq!(up.pitch, Some(3));
+        assert_eq!(up.cents, 15);
+        assert_eq!(note.transpose(2).unwrap().pitch, Some(0));
+        assert!(note.transpose(i8::MIN).is_none());
+        assert_eq!(note.transpose(0).unwrap().pitch, note.pitch);
+
+        let rest = Note::rest(TickDuration::new(4));
+        assert!(rest.is_rest());
+        assert!(rest.transpose(100).unwrap().is_rest());
+        let drum = Note {
+            percussive: true,
+            ..rest.clone()
+        };
+        assert!(!drum.is_rest());
+
+        assert_eq!(rest.with_length(2).len, TickDuration::new(2));
+        assert_eq!(rest.with_length(0).len, None);
+    }
+
+    #[test]
+    fn looped_sound_plays_tail_at_the_end() {
+        let sample = Sound::from_frames((0..5).map(|x| [x as f32, x as f32]), 8000);
+        let looped = LoopedSound::new(sample.clone(), 1, 3).unwrap();
+        let left = |x: Box<Sound>| x.left().collect::<Vec<_>>();
+        assert_eq!(left(looped.render(3)), [0.0, 1.0, 2.0]);
+        assert_eq!(left(looped.render(5)), [0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(
+            left(looped.render(8)),
+            [0.0, 1.0, 2.0, 1.0, 2.0, 1.0, 3.0, 4.0]
+        );
+
+        let plain = LoopedSound::without_loop(sample.clone());
+        assert_eq!(left(plain.render(7)), [0.0, 1.0, 2.0, 3.0, 4.0, 0.0, 0.0]);
+
+        assert_eq!(
+            left(looped.render_released(6, 10)),
+            [0.0, 1.0, 2.0, 1.0, 2.0, 1.0, 3.0, 4.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            left(looped.render_released(6, 7)),
+            [0.0, 1.0, 2.0, 1.0, 2.0, 1.0, 3.0]
+        );
+        // Released during the first pass through the loop
+        assert_eq!(
+            left(looped.render_released(2, 7)),
+            [0.0, 1.0, 2.0, 3.0, 4.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            left(plain.render_released(1, 6)),
+            [0.0, 1.0, 2.0, 3.0, 4.0, 0.0]
+        );
+
+        assert!(LoopedSound::new(sample.clone(), 3, 3).is_err());
+        assert!(LoopedSound::new(sample, 0, 6).is_err());
+    }
+
+    #[test]
+    fn sound_stream_is_lossless() {
+        let sound = Sound::from_frames((0..10).map(|x| [x as f32, -x as f32]), 8000);
+        let mut stream = SoundStream::with_block_frames(8000, 4);
+        stream.extend_from_sound(&sound).unwrap();
+        assert_eq!(stream.len_frames(), 10);
+        assert!(stream.frames().eq(sound.data().iter().copied()));
+        assert_eq!(Box::<Sound>::from(stream.clone()), sound);
+        assert_eq!(Box::<Sound>::from(SoundStream::from(&*sound)), sound);
+        assert!(stream
+            .extend_from_sound(&Sound::new(Box::new([]), 44100))
+            .is_err());
+
+        let done: Vec<Box<Sound>> = stream.drain_complete().collect();
+        assert_eq!(done.len(), 2);
+        assert_eq!(done[1].data(), &sound.data()[4..8]);
+        assert_eq!(stream.len_frames(), 2);
+        assert_eq!(stream.flush_to_sound().data(), &sound.data()[8..]);
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn max_difference_needs_same_shape() {
+        let a = Sound::new(Box::new([[0.5, 0.5], [0.0, 0.0]]), 8000);
+        let b = Sound::new(Box::new([[0.5, 0.25], [0.0, 0.125]]), 8000);
+        assert_eq!(a.max_difference(&b), Some(0.25));
+        assert_eq!(a.max_difference(&a), Some(0.0));
+        assert_eq!(
+            a.max_difference(&Sound::new(Box::new([[0.5, 0.5]]), 8000)),
+            None
+        );
+        assert_eq!(a.max_difference(&a.resample(16000).unwrap()), None);
+        let nan = Sound::new(Box::new([[f32::NAN, 0.5], [0.0, 0.0]]), 8000);
+        assert!(!a.approx_eq(&nan, 1.0));
+    }
+
+    #[test]
+    fn quantize_matches_bit_depth() {
+        let sound = Sound::new(Box::new([[0.3, -0.3], [1.0, f32::NAN]]), 8000);
+        let psg = sound.quantize(4).unwrap();
+        assert_eq!(psg.data(), &[[0.25, -0.25], [0.875, 0.0]]);
+        let wide = sound.quantize(32).unwrap();
+        assert_eq!(wide.data()[0], [0.3, -0.3]);
+        assert!(sound.quantize(1).is_err());
+        assert!(sound.quantize(33).is_err());
+    }
+
+    #[test]
+    fn frames_and_signals_build_sounds() {
+        let frames = [[0.5, -0.5], [0.25, 0.0], [1.0, 1.0]];
+        let sound = Sound::new(Box::new(frames), 8000);
+        assert_eq!(Sound::from_frames(frames, 8000), sound);
+        // Iterators of unknown length are collected first
+        let unknown = [[9.0, 9.0]]
+            .into_iter()
+            .chain(frames)
+            .filter(|x| x[0] < 2.0);
+        assert_eq!(Sound::from_frames(unknown, 8000), sound);
+        let signal = signal::from_iter(frames);
+        assert_eq!(Sound::from_signal(signal, 3, 8000), sound);
+        // Exhausted signal is padded with silence
+        let signal = signal::from_iter(frames);
+        assert_eq!(Sound::from_signal(signal, 4, 8000).data()[3], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn mono_conversions() {
+        let sound = Sound::new(Box::new([[1.0, 0.0], [-0.5, 0.25]]), 48000);
+        assert_eq!(sound.left().collect::<Vec<_>>(), [1.0, -0.5]);
+        assert_eq!(sound.right().collect::<Vec<_>>(), [0.0, 0.25]);
+        let mono = sound.to_mono();
+        assert_eq!(mono.data(), &[[0.5, 0.5], [-0.125, -0.125]]);
+        assert_eq!(Sound::from_mono(Box::new([0.5, -0.125]), 48000), mono);
+    }
+
+    #[test]
+    fn levels_are_measured() {
+        let sound = Sound::new(Box::new([[0.5, -0.5], [-1.0, 1.0]]), 48000);
+        assert_eq!(sound.peak(), 1.0);
+        assert!((sound.rms() - 0.625_f32.sqrt()).abs() < 1e-6);
+        let quiet = sound.normalize(0.25);
+        assert_eq!(quiet.peak(), 0.25);
+        assert_eq!(quiet.data()[0], [0.125, -0.125]);
+
+        let silence = Sound::silence(0.01, 48000);
+        assert_eq!(silence.peak(), 0.0);
+        assert_eq!(silence.rms(), 0.0);
+        assert_eq!(silence.normalize(1.0), silence);
+        assert_eq!(Sound::new(Box::new([]), 48000).rms(), 0.0);
+    }
+
+    #[test]
+    fn amplify_clamps() {
+        let sound = Sound::new(Box::new([[0.25, -0.5], [0.75, 0.0]]), 48000);
+        assert_eq!(sound.amplify(2.0).data(), &[[0.5, -1.0], [1.0, 0.0]]);
+    }
+
+    #[test]
+    fn pan_extremes_silence_one_side() {
+        let sound = Sound::new(Box::new([[0.5, 0.5]; 4]), 48000);
+        let left = sound.pan(-1.0);
+        let right = sound.pan(1.0);
+        assert!(left.data().iter().all(|x| x[0] == 0.5 && x[1].abs() < 1e-7));
+        assert!(right
+            .data()
+            .iter()
+            .all(|x| x[0].abs() < 1e-7 && x[1] == 0.5));
+        let center = sound.pan(0.0);
+        assert_eq!(center.data()[0][0], center.data()[0][1]);
+        // Constant power: squared gains sum to 1
+        let power = |x: Stereo<f32>| (x[0] * x[0] + x[1] * x[1]) / 0.25;
+        for pan in [-0.7, -0.2, 0.3, 0.9] {
+            assert!((power(sound.pan(pan).data()[0]) - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn fades_are_monotonic() {
+        let sound = Sound::new(Box::new([[1.0, -1.0]; 100]), 100);
+        let faded = sound.fade(0.3, 0.4);
+        let left: Vec<f32> = faded.data().iter().map(|x| x[0]).collect();
+        assert_eq!(left[0], 0.0);
+        assert_eq!(left[99], 0.0);
+        assert!(left[..30].windows(2).all(|x| x[0] <= x[1]));
+        assert!(left[60..].windows(2).all(|x| x[0] >= x[1]));
+        assert!(left[30..60].iter().all(|x| *x == 1.0));
+        assert!(faded.data().iter().all(|x| x[1] == -x[0]));
+    }
+
+    #[test]
+    fn concat_keeps_order() {
+        let a = Sound::new(Box::new([[0.0, 0.0]; 2]), 44100);
+        let b = Sound::new(Box::new([[1.0, -1.0]]), 44100);
+        let c = Sound::new(Box::new([[0.5, 0.5]; 3]), 44100);
+        let joined = Sound::concat(&[&a, &b, &c]).unwrap();
+        assert_eq!(joined.sampling_rate(), 44100);
+        assert_eq!(joined.data().len(), 6);
+        assert_eq!(joined.data()[..3], [[0.0, 0.0], [0.0, 0.0], [1.0, -1.0]]);
+        assert_eq!(joined.data()[3..], [[0.5, 0.5]; 3]);
+        assert_eq!(a.append(&b).unwrap().data().len(), 3);
+    }
+
+    #[test]
+    fn concat_rejects_different_rates() {
+        let a = Sound::new(Box::new([[0.0, 0.0]]), 48000);
+        let b = Sound::new(Box::new([[0.0, 0.0]]), 44100);
+        let err = Sound::concat(&[&a, &a, &b]).unwrap_err();
+        assert!(err.0.contains("48000") && err.0.contains("44100"));
+        assert!(a.append(&b).is_err());
+        assert!(Sound::concat(&[]).is_err());
+    }
+
+    /// 10 ms of sound at 44100 Hz and at 48000 Hz.
+    fn mismatched_pair() -> (Box<Sound>, Box<Sound>) {
+        (
+            Sound::new(Box::new([[0.5, 0.5]; 441]), 44100),
+            Sound::new(Box::new([[0.25, 0.25]; 480]), 48000),
+        )
+    }
+
+    const QUALITIES: [ResampleQuality; 3] = [
+        ResampleQuality::Nearest,
+        ResampleQuality::Linear,
+        ResampleQuality::Sinc,
+    ];
+
+    #[test]
+    fn concat_resamples_by_policy() {
+        let (a, b) = mismatched_pair();
+        assert!(Sound::concat_with_policy(&[&a, &b], RateMismatchPolicy::Error).is_err());
+        for quality in QUALITIES {
+            for parts in [[&*a, &*b], [&*b, &*a]] {
+                for (policy, rate) in [
+                    (
+                        RateMismatchPolicy::ResampleToFirst(quality),
+                        parts[0].sampling_rate(),
+                    ),
+                    (RateMismatchPolicy::ResampleToHighest(quality), 48000),
+                ] {
+                    let joined = Sound::concat_with_policy(&parts, policy).unwrap();
+                    assert_eq!(joined.sampling_rate(), rate);
+                    // 20 ms in total
+                    assert_eq!(joined.len_frames(), rate as usize / 50);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overlay_resamples_by_policy() {
+        let (a, b) = mismatched_pair();
+        assert!(Sound::overlay(&[&a, &b]).is_err());
+        assert!(Sound::overlay(&[]).is_err());
+        for quality in QUALITIES {
+            for parts in [[&*a, &*b], [&*b, &*a]] {
+                for (policy, rate) in [
+                    (
+                        RateMismatchPolicy::ResampleToFirst(quality),
+                        parts[0].sampling_rate(),
+                    ),
+                    (RateMismatchPolicy::ResampleToHighest(quality), 48000),
+                ] {
+                    let mixed = Sound::overlay_with_policy(&parts, policy).unwrap();
+                    assert_eq!(mixed.sampling_rate(), rate);
+                    // Both sounds last 10 ms
+                    assert_eq!(mixed.len_frames(), rate as usize / 100);
+                    mixed
+                        .data()
+                        .iter()
+                        .for_each(|x| assert!((x[0] - 0.75).abs() < 1e-3, "{x:?}"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overlay_keeps_longest_length() {
+        let long = Sound::new(Box::new([[0.5, 0.5]; 4]), 48000);
+        let short = Sound::new(Box::new([[0.25, -0.25]; 2]), 48000);
+        let mixed = Sound::overlay(&[&short, &long]).unwrap();
+        assert_eq!(
+            mixed.data(),
+            &[[0.75, 0.25], [0.75, 0.25], [0.5, 0.5], [0.5, 0.5]]
+        );
+    }
+
+    #[test]
+    fn stream_resamples_by_policy() {
+        let (a, b) = mismatched_pair();
+        for quality in QUALITIES {
+            for (first, second) in [(&a, &b), (&b, &a)] {
+                for (policy, rate) in [
+                    (
+                        RateMismatchPolicy::ResampleToFirst(quality),
+                        first.sampling_rate(),
+                    ),
+                    (RateMismatchPolicy::ResampleToHighest(quality), 48000),
+                ] {
+                    let mut stream = SoundStream::from(&**first);
+                    stream
+                        .extend_from_sound_with_policy(second, policy)
+                        .unwrap();
+                    assert_eq!(stream.sampling_rate(), rate);
+                    assert_eq!(stream.len_frames(), rate as usize / 50);
+                }
+            }
+        }
+        let mut stream = SoundStream::from(&*a);
+        assert!(stream
+            .extend_from_sound_with_policy(&b, RateMismatchPolicy::Error)
+            .is_err());
+        assert_eq!(stream, SoundStream::from(&*a));
+    }
+
+    #[test]
+    fn resample_qualities_keep_original_frames() {
+        let data: Box<[Stereo<f32>]> = (0..64).map(|x| [(x as f32 * 0.3).sin(), 0.5]).collect();
+        let sound = Sound::new(data, 24000);
+        for quality in QUALITIES {
+            let up = sound.resample_with_quality(48000, quality).unwrap();
+            assert_eq!(up.len_frames(), 128);
+            // Every second frame falls exactly onto an original one
+            for (i, x) in sound.data().iter().enumerate() {
+                assert!((up.data()[i * 2][0] - x[0]).abs() < 1e-5, "{quality:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn sinc_filters_out_what_does_not_fit() {
+        // Nyquist frequency of 48000 Hz, which does not fit into 24000 Hz
+        let data: Box<[Stereo<f32>]> = (0..480)
+            .map(|x| match x % 2 {
+                0 => [0.5, 0.5],
+                _ => [-0.5, -0.5],
+            })
+            .collect();
+        let sound = Sound::new(data, 48000);
+        let nearest = sound
+            .resample_with_quality(24000, ResampleQuality::Nearest)
+            .unwrap();
+        assert_eq!(nearest.peak(), 0.5);
+        let sinc = sound
+            .resample_with_quality(24000, ResampleQuality::Sinc)
+            .unwrap();
+        assert_eq!(sinc.len_frames(), 240);
+        // Away from the edges, the tone is gone
+        assert!(sinc.data()[40..200].iter().all(|x| x[0].abs() < 0.01));
+    }
+
+    #[test]
+    fn loop_repeats_the_whole_sound() {
+        let sound = Sound::new(Box::new([[0.0, 0.0], [1.0, 1.0]]), 48000);
+        let looped: Vec<_> = sound.loop_at(0, 2).unwrap().take(5).collect();
+        assert_eq!(
+            looped,
+            [[0.0, 0.0], [1.0, 1.0], [0.0, 0.0], [1.0, 1.0], [0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn resample_scales_length() {
+        let data: Box<[Stereo<f32>]> = (0..480).map(|x| [x as f32, -x as f32]).collect();
+        let sound = Sound::new(data, 48000);
+        let down = sound.resample(44100).unwrap();
+        assert_eq!(down.sampling_rate(), 44100);
+        assert_eq!(down.data().len(), 441);
+        let up = sound.resample(96000).unwrap();
+        assert_eq!(up.data().len(), 960);
+        // Sides are interpolated independently
+        assert_eq!(up.data()[3], [1.5, -1.5]);
+        // The end holds the last frame instead of fading out
+        assert_eq!(up.data()[959], [479.0, -479.0]);
+        assert_eq!(sound.resample(48000).unwrap(), sound);
+        assert!(Sound::new(Box::new([]), 48000)
+            .resample(8000)
+            .unwrap()
+            .data()
+            .is_empty());
+    }
+
+    #[test]
+    fn resample_rejects_zero_rate() {
+        let silent = Sound::new(Box::new([[0.0, 0.0]; 4]), 0);
+        assert!(silent.resample(48000).is_err());
+        let sound = Sound::new(Box::new([[0.0, 0.0]; 4]), 48000);
+        assert!(sound.resample(0).is_err());
+    }
+
+    #[test]
+    fn loop_rejects_bad_bounds() {
+        let sound = Sound::new(Box::new([[0.0, 0.0]; 4]), 48000);
+        assert!(sound.loop_at(0, 5).is_err());
+        assert!(sound.loop_at(2, 2).is_err());
+        assert!(sound.loop_at(3, 1).is_err());
+        assert!(sound.loop_at(3, 4).is_ok());
+    }
+
+    #[test]
+    fn interleaved_accessors() {
+        let sound = Sound::new(Box::new([[0.5, -0.25], [3.0, -3.0]]), 48000);
+        let floats = sound.to_interleaved_f32_le();
+        assert_eq!(floats.len(), 16);
+        assert_eq!(
+            Sound::from_interleaved_f32_le(&floats, 48000).unwrap(),
+            sound
+        );
+
+        let ints: Vec<i16> = sound
+            .to_interleaved_i16_le(false)
+            .chunks(2)
+            .map(|x| i16::from_le_bytes([x[0], x[1]]))
+            .collect();
+        assert_eq!(ints, [16384, -8192, i16::MAX, i16::MIN]);
+
+        for len in [1, 3, 7] {
+            assert!(Sound::from_interleaved_f32_le(&floats[..len], 48000).is_err());
+        }
+        assert!(Sound::from_interleaved_i16_le(&[0; 3], 48000).is_err());
+    }
+
+    #[test]
+    fn interleaved_i16_is_dithered() {
+        let sound = Sound::new(vec![[0.25 / 32768.0; 2]; 64].into(), 48000);
+        assert!(sound.to_interleaved_i16_le(false).iter().all(|x| *x == 0));
+        let dithered = sound.to_interleaved_i16_le(true);
+        assert!(dithered.iter().any(|x| *x != 0));
+        assert_eq!(dithered, sound.to_interleaved_i16_le(true));
+    }
+}
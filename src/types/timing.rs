@@ -0,0 +1,202 @@
+//! Positioning notes by bars and beats instead of ticks.
+//!
+//! Bars and beats are counted from 0, lengths are given with `zenlen`: the number of
+//! ticks in a whole note.
+
+use super::{Note, TickDuration};
+
+/// Time signature, like 3/4 or 6/8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeSignature {
+    /// Number of beats in one bar (the upper number).
+    pub beats_per_bar: u8,
+
+    /// Note value of one beat, as a fraction of a whole note (the lower number).
+    pub beat_unit: u8,
+}
+
+impl TimeSignature {
+    /// Length of one bar in ticks, rounded down.
+    pub fn bar_ticks(&self, zenlen: u32) -> u32 {
+        (self.beats_per_bar as u64 * zenlen as u64 / self.beat_unit as u64) as u32
+    }
+}
+
+/// Get the tick at which the given position starts.
+///
+/// `subdivision` is a fraction of a beat, `(1, 2)` is the middle of the beat.
+/// The result is rounded down.
+///
+/// # Panics
+///
+/// Panics if `ts.beat_unit` or the denominator of `subdivision` is 0.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::timing::{tick_of, TimeSignature};
+/// let waltz = TimeSignature { beats_per_bar: 3, beat_unit: 4 };
+/// // Second half of the third beat of the second bar, with 96 ticks per whole note
+/// assert_eq!(tick_of(1, 2, (1, 2), &waltz, 96), 72 + 48 + 12);
+/// ```
+pub fn tick_of(
+    bar: u32,
+    beat: u32,
+    subdivision: (u32, u32),
+    ts: &TimeSignature,
+    zenlen: u32,
+) -> u32 {
+    bar * ts.bar_ticks(zenlen) + beat_offset(beat, subdivision, ts, zenlen)
+}
+
+/// Offset of a beat from the start of its bar, in ticks.
+fn beat_offset(beat: u32, subdivision: (u32, u32), ts: &TimeSignature, zenlen: u32) -> u32 {
+    let (num, den) = (subdivision.0 as u64, subdivision.1 as u64);
+    let beats_scaled = beat as u64 * den + num;
+    (beats_scaled * zenlen as u64 / (ts.beat_unit as u64 * den)) as u32
+}
+
+/// Note that starts at a given tick.
+#[derive(Debug, Default, Clone)]
+pub struct TimedNote {
+    /// Tick at which the note starts.
+    pub at_tick: u32,
+
+    /// The note.
+    pub note: Note,
+}
+
+/// Builder for a list of [`TimedNote`]s positioned by bars and beats.
+///
+/// The time signature can be changed at the start of any bar, and applies to every
+/// bar after it until the next change.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::{Note, timing::{EventTrack, TimeSignature}};
+/// let common = TimeSignature { beats_per_bar: 4, beat_unit: 4 };
+/// let waltz = TimeSignature { beats_per_bar: 3, beat_unit: 4 };
+/// let notes = EventTrack::new(common, 96)
+///     .meter(1, waltz)
+///     .note(0, 0, Note::default())
+///     .rest(2, 0, None)
+///     .build();
+/// assert_eq!(notes[0].at_tick, 0);
+/// // One bar of 4/4, then one bar of 3/4
+/// assert_eq!(notes[1].at_tick, 96 + 72);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventTrack {
+    zenlen: u32,
+    meters: Vec<(u32, TimeSignature)>,
+    notes: Vec<TimedNote>,
+}
+
+impl EventTrack {
+    /// Create a track that starts in the given time signature.
+    pub fn new(ts: TimeSignature, zenlen: u32) -> Self {
+        EventTrack {
+            zenlen,
+            meters: vec![(0, ts)],
+            notes: Vec::new(),
+        }
+    }
+
+    /// Change the time signature at the start of `bar`.
+    ///
+    /// Notes that were already added are not moved.
+    pub fn meter(mut self, bar: u32, ts: TimeSignature) -> Self {
+        let index = self.meters.partition_point(|(start, _)| *start <= bar);
+        match index > 0 && self.meters[index - 1].0 == bar {
+            true => self.meters[index - 1].1 = ts,
+            false => self.meters.insert(index, (bar, ts)),
+        }
+        self
+    }
+
+    /// Add a note at the start of the given beat.
+    pub fn note(self, bar: u32, beat: u32, note: Note) -> Self {
+        self.note_at(bar, beat, (0, 1), note)
+    }
+
+    /// Add a note at a fraction of the given beat.
+    pub fn note_at(mut self, bar: u32, beat: u32, subdivision: (u32, u32), note: Note) -> Self {
+        let at_tick = self.tick_of(bar, beat, subdivision);
+        self.notes.push(TimedNote { at_tick, note });
+        self
+    }
+
+    /// Add a rest at the start of the given beat.
+    pub fn rest(self, bar: u32, beat: u32, len: Option<TickDuration>) -> Self {
+        let rest = Note {
+            len,
+            ..Default::default()
+        };
+        self.note(bar, beat, rest)
+    }
+
+    /// Get the tick at which the given position starts, respecting meter changes.
+    pub fn tick_of(&self, bar: u32, beat: u32, subdivision: (u32, u32)) -> u32 {
+        let mut tick = 0;
+        let mut meters = self.meters.iter().peekable();
+        let mut current = meters.next().unwrap();
+        while let Some(next) = meters.peek().filter(|x| x.0 <= bar) {
+            tick += (next.0 - current.0) * current.1.bar_ticks(self.zenlen);
+            current = meters.next().unwrap();
+        }
+        tick + (bar - current.0) * current.1.bar_ticks(self.zenlen)
+            + beat_offset(beat, subdivision, &current.1, self.zenlen)
+    }
+
+    /// Get the notes, ordered by their starting tick.
+    pub fn build(mut self) -> Vec<TimedNote> {
+        self.notes.sort_by_key(|x| x.at_tick);
+        self.notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALTZ: TimeSignature = TimeSignature {
+        beats_per_bar: 3,
+        beat_unit: 4,
+    };
+    const JIG: TimeSignature = TimeSignature {
+        beats_per_bar: 6,
+        beat_unit: 8,
+    };
+
+    #[test]
+    fn compound_and_simple_meters_differ() {
+        // Bars are equally long, but beats are not
+        assert_eq!(tick_of(2, 0, (0, 1), &WALTZ, 96), 144);
+        assert_eq!(tick_of(2, 0, (0, 1), &JIG, 96), 144);
+        assert_eq!(tick_of(2, 1, (0, 1), &WALTZ, 96), 168);
+        assert_eq!(tick_of(2, 1, (0, 1), &JIG, 96), 156);
+    }
+
+    #[test]
+    fn meter_change_applies_after_its_bar() {
+        let track = EventTrack::new(WALTZ, 96).meter(2, JIG).meter(4, WALTZ);
+        assert_eq!(track.tick_of(1, 2, (0, 1)), 72 + 48);
+        assert_eq!(track.tick_of(2, 1, (0, 1)), 144 + 12);
+        assert_eq!(track.tick_of(5, 0, (0, 1)), 360);
+        // Changing the meter at the same bar replaces it
+        let track = track.meter(2, WALTZ);
+        assert_eq!(track.tick_of(2, 1, (0, 1)), 144 + 24);
+    }
+
+    #[test]
+    fn notes_are_sorted() {
+        let notes = EventTrack::new(WALTZ, 96)
+            .rest(1, 0, TickDuration::new(24))
+            .note_at(0, 0, (1, 2), Note::default())
+            .build();
+        assert_eq!(notes[0].at_tick, 12);
+        assert_eq!(notes[1].at_tick, 72);
+        assert!(notes[1].note.pitch.is_none());
+    }
+}
@@ -0,0 +1,184 @@
+//! Conversions between the abstract values of notes and physical quantities.
+
+use std::str::FromStr;
+
+use crate::resource::StringError;
+
+/// Range of values that a velocity is given in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VelocityConvention {
+    /// Full range of `u8`, 255 is the loudest.
+    #[default]
+    Byte,
+
+    /// MIDI's 7 bits, 127 is the loudest. Higher values are as loud as 127.
+    Midi,
+}
+
+impl VelocityConvention {
+    /// Loudest velocity of the convention.
+    pub fn max(self) -> u8 {
+        match self {
+            VelocityConvention::Byte => u8::MAX,
+            VelocityConvention::Midi => 127,
+        }
+    }
+}
+
+/// Convert a velocity into the gain that a mod should apply to its sound.
+///
+/// Velocity 0 is silence and the maximum of the convention is unity, the gain is
+/// linear in between and never exceeds 1.
+///
+/// This is the only conversion that builtin mods use, velocity has nothing to do
+/// with `dasp`'s equilibrium of `u8` samples.
+///
+/// # Examples
+///
+/// ```
+/// # use mleml::types::units::{velocity_to_gain, VelocityConvention};
+/// assert_eq!(velocity_to_gain(0, VelocityConvention::Byte), 0.0);
+/// assert_eq!(velocity_to_gain(255, VelocityConvention::Byte), 1.0);
+/// assert_eq!(velocity_to_gain(200, VelocityConvention::Midi), 1.0);
+/// ```
+pub fn velocity_to_gain(velocity: u8, convention: VelocityConvention) -> f32 {
+    velocity.min(convention.max()) as f32 / convention.max() as f32
+}
+
+/// Mapping of [`Byte`][VelocityConvention::Byte] velocity to gain, for mods that
+/// emulate how a chip responds to it.
+///
+/// Every curve maps 0 to silence and 255 to unity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VelocityCurve {
+    /// Same as [`velocity_to_gain()`].
+    Linear,
+
+    /// Linear gain raised to the power of `gamma`, which has to be positive.
+    Exponential {
+        /// Exponent, values above 1 make quiet notes quieter.
+        gamma: f32,
+    },
+
+    /// Linear gain cut down to one of `levels` evenly spaced values, like a chip
+    /// with a coarse volume register. Fewer than 2 levels are treated as 2.
+    Stepped {
+        /// Number of distinct gains, including silence.
+        levels: u8,
+    },
+}
+
+impl VelocityCurve {
+    /// Get the gain for a velocity, in range [0, 1].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use mleml::types::VelocityCurve;
+    /// assert_eq!(VelocityCurve::Exponential { gamma: 2.0 }.apply(255), 1.0);
+    /// assert_eq!(VelocityCurve::Stepped { levels: 16 }.apply(100), 6.0 / 15.0);
+    /// ```
+    pub fn apply(&self, velocity: u8) -> f32 {
+        let linear = velocity_to_gain(velocity, VelocityConvention::Byte);
+        match *self {
+            VelocityCurve::Linear => linear,
+            VelocityCurve::Exponential { .. } if velocity == 0 => 0.0,
+            VelocityCurve::Exponential { gamma } => linear.powf(gamma).clamp(0.0, 1.0),
+            VelocityCurve::Stepped { levels } => {
+                let levels = levels.max(2) as u32;
+                let step = velocity as u32 * levels / 256;
+                step as f32 / (levels - 1) as f32
+            }
+        }
+    }
+}
+
+/// Parses `"linear"`, `"exponential <gamma>"` or `"stepped <levels>"`.
+impl FromStr for VelocityCurve {
+    type Err = StringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let curve = match (words.next(), words.next()) {
+            (Some("linear"), None) => Some(VelocityCurve::Linear),
+            (Some("exponential"), Some(gamma)) => gamma
+                .parse::<f32>()
+                .ok()
+                .filter(|x| x.is_finite() && *x > 0.0)
+                .map(|gamma| VelocityCurve::Exponential { gamma }),
+            (Some("stepped"), Some(levels)) => levels
+                .parse::<u8>()
+                .ok()
+                .filter(|x| *x >= 2)
+                .map(|levels| VelocityCurve::Stepped { levels }),
+            _ => None,
+        };
+        match (curve, words.next()) {
+            (Some(curve), None) => Ok(curve),
+            _ => Err(StringError(format!("\"{s}\" is not a velocity curve"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_span_silence_to_full_scale() {
+        for curve in [
+            VelocityCurve::Linear,
+            VelocityCurve::Exponential { gamma: 0.5 },
+            VelocityCurve::Exponential { gamma: 3.0 },
+            VelocityCurve::Stepped { levels: 0 },
+            VelocityCurve::Stepped { levels: 16 },
+        ] {
+            assert_eq!(curve.apply(0), 0.0, "{curve:?}");
+            assert_eq!(curve.apply(255), 1.0, "{curve:?}");
+            assert!(curve.apply(128) >= curve.apply(127), "{curve:?}");
+        }
+        let switch = VelocityCurve::Stepped { levels: 2 };
+        assert_eq!((switch.apply(127), switch.apply(128)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn curves_parse() {
+        let parse = |s: &str| s.parse::<VelocityCurve>().ok();
+        assert_eq!(parse("linear"), Some(VelocityCurve::Linear));
+        assert_eq!(
+            parse("exponential 2.5"),
+            Some(VelocityCurve::Exponential { gamma: 2.5 })
+        );
+        assert_eq!(
+            parse(" stepped  16 "),
+            Some(VelocityCurve::Stepped { levels: 16 })
+        );
+        for bad in [
+            "",
+            "linear 2",
+            "exponential",
+            "exponential -1",
+            "stepped 1",
+            "cubic",
+        ] {
+            assert!(bad.parse::<VelocityCurve>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn byte_endpoints_and_midpoint() {
+        assert_eq!(velocity_to_gain(0, VelocityConvention::Byte), 0.0);
+        assert_eq!(velocity_to_gain(255, VelocityConvention::Byte), 1.0);
+        let mid = velocity_to_gain(128, VelocityConvention::Byte);
+        assert!((mid - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn midi_endpoints_and_midpoint() {
+        assert_eq!(velocity_to_gain(0, VelocityConvention::Midi), 0.0);
+        assert_eq!(velocity_to_gain(127, VelocityConvention::Midi), 1.0);
+        assert_eq!(velocity_to_gain(128, VelocityConvention::Midi), 1.0);
+        let mid = velocity_to_gain(64, VelocityConvention::Midi);
+        assert!((mid - 0.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,40 @@
+//! Configs of builtin resources written as TOML and YAML.
+
+use mleml::{
+    extra::{
+        builtin::{ConvertNote, FourOpFm},
+        config_builder::{from_toml_table, from_yaml_mapping, ConfigBuilderError},
+    },
+    resource::Resource,
+};
+use serde_json::json;
+
+#[test]
+fn toml_and_yaml_fixtures_agree() {
+    let schema = FourOpFm().config_schema().unwrap();
+    let toml = from_toml_table(&schema, include_str!("fixtures/organ.toml")).unwrap();
+    let yaml = from_yaml_mapping(&schema, include_str!("fixtures/organ.yaml")).unwrap();
+    assert_eq!(toml, yaml);
+    FourOpFm().check_config(&toml).unwrap();
+    let ml4 = schema.position("ml4").unwrap();
+    assert_eq!(toml.as_slice()[ml4], json!(8));
+}
+
+#[test]
+fn unknown_keys_are_named() {
+    let schema = FourOpFm().config_schema().unwrap();
+    let err = from_toml_table(&schema, include_str!("fixtures/typo.toml")).unwrap_err();
+    assert_eq!(err, ConfigBuilderError::UnknownName("tl5".to_string()));
+}
+
+#[test]
+fn missing_keys_are_named() {
+    let schema = ConvertNote().config_schema().unwrap();
+    let err = from_yaml_mapping(&schema, "c_neg1_hz: 8.1758\ntick_length: 0.125").unwrap_err();
+    assert_eq!(err, ConfigBuilderError::MissingName("octave".to_string()));
+
+    let err = from_toml_table(&schema, "octave = [4]").unwrap_err();
+    assert!(matches!(err, ConfigBuilderError::Schema(_)), "{err}");
+    let err = from_toml_table(&schema, "octave =").unwrap_err();
+    assert!(matches!(err, ConfigBuilderError::Parse(_)), "{err}");
+}
@@ -0,0 +1,135 @@
+//! Trait objects of the resource traits are the main way to use the library, so
+//! every trait method is called through one here.
+//!
+//! When a trait gains a method, call it here as well.
+
+use std::{mem::discriminant, rc::Rc};
+
+use mleml::{
+    extra::builtin::{AgcMixer, ConvertNote, EffectChannel, FourOpFm, SimpleChannel},
+    resource::{
+        Channel, ChannelCommand, JsonArray, MixInput, Mixer, Mod, ModData, PremixBuilder, Resource,
+    },
+    types::{Note, ReadyNote, Sound},
+};
+use serde_json::json;
+use static_assertions::{assert_impl_all, assert_obj_safe};
+
+assert_obj_safe!(Resource, Mod, Channel, Mixer<'static>);
+assert_impl_all!(Sound: Send, Sync);
+assert_impl_all!(JsonArray: Send, Sync, Clone);
+assert_impl_all!(ModData: Send, Sync);
+
+fn check_resource(res: &dyn Resource) {
+    assert!(!res.orig_name().is_empty());
+    assert!(!res.id().is_empty());
+    assert!(!res.description().is_empty());
+    let _ = res.check_config(&JsonArray::new());
+    let state = res.initial_state();
+    assert!(res.check_state(&state).is_some());
+    let version = res.schema_version();
+    res.migrate_config(version, JsonArray::new()).unwrap();
+    res.migrate_state(version, state).unwrap();
+    let _ = res.config_schema();
+}
+
+#[test]
+fn mod_is_usable_as_trait_object() {
+    let fm: Rc<dyn Mod> = Rc::new(FourOpFm());
+    check_resource(fm.as_ref());
+    assert_eq!(fm.name(), fm.orig_name());
+    assert_eq!(
+        fm.input_type(),
+        discriminant(&ModData::ReadyNote(ReadyNote::default()))
+    );
+    assert_eq!(
+        fm.output_type(),
+        discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+    );
+    let input = ModData::ReadyNote(ReadyNote {
+        len: 0.01,
+        pitch: Some(440.0),
+        velocity: 128,
+        ..Default::default()
+    });
+    assert!(fm.apply(&input, &JsonArray::new(), &[]).is_err());
+    assert_eq!(fm.tail_seconds(&JsonArray::new()), 0.0);
+    let schema = fm.config_schema().unwrap();
+    assert_eq!(schema.len(), 34);
+    assert_eq!(schema.position("alg"), Some(0));
+
+    let boxed: Box<dyn Resource> = Box::new(ConvertNote());
+    check_resource(&boxed);
+}
+
+#[test]
+fn channel_is_usable_as_trait_object() {
+    let channels: [Rc<dyn Channel>; 2] = [
+        Rc::new(SimpleChannel::default()),
+        Rc::new(EffectChannel::new(
+            "Bus".to_string(),
+            "BUS".to_string(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        )),
+    ];
+    for channel in channels {
+        check_resource(channel.as_ref());
+        assert_eq!(channel.name(), channel.orig_name());
+        assert_ne!(
+            channel.input_type(),
+            discriminant(&ModData::String(String::new()))
+        );
+        assert_eq!(
+            channel.output_type(),
+            discriminant(&ModData::Sound(Sound::new(Box::new([]), 0)))
+        );
+        let state = channel
+            .apply_command(&ChannelCommand::Volume(10), &channel.initial_state())
+            .unwrap_or(channel.initial_state());
+        let tail = channel
+            .flush_tail(&state, &JsonArray::new(), 1.0, 48000)
+            .unwrap();
+        assert!(tail.is_none());
+    }
+
+    let simple: Rc<dyn Channel> = Rc::new(SimpleChannel::default());
+    let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+    let result = simple.play(ModData::Note(Note::default()), &[], &config);
+    // Empty pipeline does not produce a sound
+    assert!(result.is_err());
+}
+
+#[test]
+fn mixer_is_usable_as_trait_object() {
+    let sound = Sound::new(Box::new([[0.5, 0.5]; 10]), 48000);
+    let premix = PremixBuilder::new().new_sound(&sound);
+    let mixer: Rc<dyn Mixer> = Rc::new(AgcMixer());
+    check_resource(mixer.as_ref());
+    assert!(mixer.get_values().is_empty());
+    assert_eq!(mixer.sampling_rate(), 48000);
+    assert_eq!(mixer.mix_silent(10).data().len(), 10);
+
+    let config = JsonArray::from_values([json!(0.5), json!(1.0), json!(4.0)]).unwrap();
+    let (mixed, state, leftovers) = mixer
+        .mix(
+            premix.as_premixed().unwrap(),
+            10,
+            &config,
+            &mixer.initial_state(),
+        )
+        .unwrap();
+    assert_eq!(mixed.data().len(), 10);
+    assert!(mixer.check_state(&state).is_some());
+    assert_eq!(leftovers.len(), 1);
+
+    let out = mixer
+        .mix_structured(
+            MixInput::new(premix.as_premixed().unwrap(), 10),
+            &config,
+            &mixer.initial_state(),
+        )
+        .unwrap();
+    assert_eq!(out.sound, mixed);
+}
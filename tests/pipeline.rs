@@ -0,0 +1,127 @@
+//! Complete Note -> Sound pipeline: a channel with a synthesizer and a mixer.
+
+use std::{mem::discriminant, rc::Rc};
+
+use mleml::{
+    extra::builtin::{sum_channels, ConvertNote, SimpleChannel, SimpleMixer, SimpleMod},
+    resource::{
+        Channel, JsonArray, LeftoverSound, Mixer, ModData, PremixBuilder, PremixedSound, ResConfig,
+        ResState, StringError,
+    },
+    types::{Note, ReadyNote, Sound, TickDuration},
+};
+use serde_json::json;
+
+fn square(
+    input: &ModData,
+    _conf: &ResConfig,
+    _state: &ResState,
+) -> Result<(ModData, Box<ResState>), StringError> {
+    let note = input
+        .as_ready_note()
+        .ok_or(StringError("input needs to be a ReadyNote".to_string()))?;
+    let frames = ((note.len + note.decay_time) * 48000.0) as usize;
+    let data = match note.pitch {
+        Some(hz) => (0..frames)
+            .map(|i| {
+                let x = match (i as f32 * hz / 48000.0).fract() < 0.5 {
+                    true => 0.25,
+                    false => -0.25,
+                };
+                [x, x]
+            })
+            .collect(),
+        None => vec![[0.0, 0.0]; frames].into_boxed_slice(),
+    };
+    Ok((ModData::Sound(Sound::new(data, 48000)), Box::new([])))
+}
+
+fn mix<'a>(
+    input: PremixedSound,
+    _play_time: u32,
+    _conf: &ResConfig,
+    _state: &ResState,
+) -> Result<(Box<Sound>, Box<ResState>, LeftoverSound<'a>), StringError> {
+    let len = input.iter().map(|x| x.1.len()).max().unwrap_or(0);
+    let out = sum_channels(input, len, false);
+    let leftovers = vec![None; input.len()].into_boxed_slice();
+    Ok((Sound::new(out, 48000), Box::new([]), leftovers))
+}
+
+fn channel() -> SimpleChannel {
+    let square = SimpleMod::builder()
+        .name("Square wave generator".to_string())
+        .id("SQUARE".to_string())
+        .desc("Square wave generator".to_string())
+        .schema(JsonArray::new())
+        .apply(square)
+        .check_state(|_| true)
+        .input_type(discriminant(&ModData::ReadyNote(ReadyNote::default())))
+        .output_type(discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))))
+        .build()
+        .unwrap();
+    SimpleChannel {
+        volume: 15,
+        mods: vec![Rc::new(ConvertNote()), Rc::new(square)],
+        states: vec![Rc::from([]), Rc::from([])],
+        configs: vec![Rc::new(JsonArray::new()), Rc::new(JsonArray::new())],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn notes_are_played_and_mixed() {
+    let channel = channel();
+    // [frequency of C-1, length of one tick, ticks in a whole note, ticks per beat, maximum volume]
+    let config = JsonArray::from_value(json!([8.1758, 0.125, 96, 4.0, 15])).unwrap();
+
+    // C, E and G, each 4 ticks long, with 1 tick of sound after release
+    let sounds: Vec<Box<Sound>> = [0, 4, 7]
+        .into_iter()
+        .map(|pitch| {
+            let note = Note {
+                len: TickDuration::new(4),
+                pitch: Some(pitch),
+                velocity: 128,
+                ..Default::default()
+            };
+            let (out, states, _) = channel.play(ModData::Note(note), &[], &config).unwrap();
+            assert_eq!(states.len(), 2);
+            match out {
+                ModData::Sound(sound) => sound,
+                _ => panic!("channel did not produce a sound"),
+            }
+        })
+        .collect();
+    for sound in &sounds {
+        assert_eq!(sound.data().len(), 30000);
+    }
+
+    let mixer = SimpleMixer::new(
+        "Chord mixer".to_string(),
+        "CHORD_MIXER".to_string(),
+        "Sums all channels".to_string(),
+        JsonArray::new(),
+        JsonArray::new(),
+        mix,
+        |_| true,
+    );
+    let premix = sounds
+        .iter()
+        .fold(PremixBuilder::new().channel_count(3), |premix, sound| {
+            premix.new_sound(sound)
+        });
+    let (mixed, _, leftovers) = mixer
+        .mix(premix.as_premixed().unwrap(), 30000, &JsonArray::new(), &[])
+        .unwrap();
+
+    assert_eq!(mixed.sampling_rate(), 48000);
+    assert_eq!(mixed.data().len(), 30000);
+    assert!(leftovers.iter().all(|x| x.is_none()));
+    let peak = mixed
+        .data()
+        .iter()
+        .flatten()
+        .fold(0.0_f32, |acc, x| acc.max(x.abs()));
+    assert!(peak > 0.25);
+}
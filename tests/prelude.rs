@@ -0,0 +1,46 @@
+//! The prelude has to stay importable with every combination of features.
+
+#![allow(unused_imports)]
+
+use mleml::prelude::*;
+
+#[test]
+fn core_items_are_exported() {
+    let _: Option<&dyn Resource> = None;
+    let _: Option<&dyn Mod> = None;
+    let _: Option<&dyn Mixer> = None;
+    let _: Option<&dyn Channel> = None;
+    let pipeline: Vec<std::rc::Rc<dyn Mod>> = Vec::new();
+    assert!(Pipeline::input_type(&pipeline).is_none());
+    let _ = PremixBuilder::new();
+    let _: Box<ResState> = Box::new([]);
+    let _: ResConfig = JsonArray::new();
+    let _ = StringError::default();
+    let _ = ModData::Note(Note::default());
+    let _ = ModData::ReadyNote(ReadyNote::default());
+    let _ = ModData::Sound(Sound::new(Box::new([]), 48000));
+}
+
+#[cfg(feature = "extra")]
+#[test]
+fn extra_items_are_exported() {
+    let schema = JsonArray::new();
+    let _ = ConfigBuilder::new(&schema);
+}
+
+#[cfg(feature = "builtin")]
+#[test]
+fn builtin_items_are_exported() {
+    let _ = SimpleChannel::default();
+    let _ = FourOpFm();
+    let _ = ConvertNote::config_from(ConvertNoteParams {
+        c_neg1_hz: 8.1758,
+        tick_length: 0.125,
+        octave: 4,
+        post_release_ticks: 1,
+        added_cents: 0,
+    });
+    let _ = sum_channels(&[], 0, false);
+    let _: Option<SimpleMod> = None;
+    let _: Option<SimpleMixer> = None;
+}
@@ -0,0 +1,121 @@
+//! Property-based tests for the config layer and pipelines.
+
+use std::{
+    mem::{discriminant, Discriminant},
+    rc::Rc,
+};
+
+use mleml::{
+    extra::config_builder::ConfigBuilder,
+    resource::{JsonArray, Mod, ModData, Pipeline, ResConfig},
+    test_util::MockMod,
+    types::{
+        units::{velocity_to_gain, VelocityConvention},
+        Note, ReadyNote, Sound,
+    },
+};
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+/// Any JSON value that is not an array or an object.
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".{0,8}".prop_map(Value::from),
+    ]
+}
+
+/// Any JSON value, including nested arrays and objects.
+fn value() -> impl Strategy<Value = Value> {
+    scalar().prop_recursive(3, 16, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+            prop::collection::hash_map(".{0,4}", inner, 0..4).prop_map(|map| json!(map)),
+        ]
+    })
+}
+
+fn is_flat(values: &[Value]) -> bool {
+    values.iter().all(|x| !(x.is_array() || x.is_object()))
+}
+
+fn kind(index: u8) -> Discriminant<ModData> {
+    match index % 4 {
+        0 => discriminant(&ModData::String(String::new())),
+        1 => discriminant(&ModData::Note(Note::default())),
+        2 => discriminant(&ModData::ReadyNote(ReadyNote::default())),
+        _ => discriminant(&ModData::Sound(Sound::new(Box::new([]), 0))),
+    }
+}
+
+proptest! {
+    #[test]
+    fn gain_is_monotonic_in_velocity(a in any::<u8>(), b in any::<u8>(), midi in any::<bool>()) {
+        let convention = match midi {
+            true => VelocityConvention::Midi,
+            false => VelocityConvention::Byte,
+        };
+        let (low, high) = (a.min(b), a.max(b));
+        let gain = velocity_to_gain(high, convention);
+        prop_assert!(velocity_to_gain(low, convention) <= gain);
+        prop_assert!((0.0..=1.0).contains(&gain));
+    }
+
+    #[test]
+    fn from_value_accepts_only_flat_arrays(item in value()) {
+        let expected = item.as_array().is_some_and(|x| is_flat(x));
+        let arr = JsonArray::from_value(item.clone());
+        prop_assert_eq!(arr.is_some(), expected);
+        if let Some(arr) = arr {
+            prop_assert_eq!(arr.into_inner(), item);
+        }
+    }
+
+    #[test]
+    fn from_values_accepts_only_scalars(items in prop::collection::vec(value(), 0..8)) {
+        let arr = JsonArray::from_values(&items);
+        prop_assert_eq!(arr.is_some(), is_flat(&items));
+    }
+
+    #[test]
+    fn json_array_survives_serialization(items in prop::collection::vec(scalar(), 0..8)) {
+        let arr = JsonArray::from_values(&items).unwrap();
+        let parsed: Value = serde_json::from_slice(&arr.as_byte_vec()).unwrap();
+        prop_assert!(JsonArray::from_value(parsed).is_some());
+    }
+
+    #[test]
+    fn built_config_matches_schema(
+        schema in prop::collection::vec(scalar(), 0..6),
+        stream in prop::collection::vec(scalar(), 0..12),
+    ) {
+        let schema = ResConfig::from_values(&schema).unwrap();
+        let mut builder = ConfigBuilder::new(&schema);
+        let _ = builder.inject(&stream);
+        if let ConfigBuilder::Config(conf) = builder {
+            prop_assert_eq!(conf.len(), schema.len());
+            for (a, b) in conf.as_slice().iter().zip(schema.as_slice()) {
+                prop_assert_eq!(discriminant(a), discriminant(b));
+            }
+        }
+    }
+
+    #[test]
+    fn accepted_insertions_keep_pipeline_valid(
+        insertions in prop::collection::vec((0..8_usize, 0..4_u8, 0..4_u8), 0..16),
+    ) {
+        let mut pipeline: Vec<Rc<dyn Mod>> = Vec::new();
+        for (index, input, output) in insertions {
+            let before = pipeline.len();
+            let item = Rc::new(MockMod { input: kind(input), output: kind(output) });
+            match pipeline.insert_checked(index, item) {
+                Ok(()) => prop_assert_eq!(pipeline.len(), before + 1),
+                Err(_) => prop_assert_eq!(pipeline.len(), before),
+            }
+            prop_assert!(pipeline.is_valid().is_ok());
+        }
+    }
+}